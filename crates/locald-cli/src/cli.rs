@@ -1,5 +1,6 @@
 use crate::channel::{BUILD_CHANNEL, BUILD_VERSION};
 use clap::{Parser, Subcommand};
+use locald_core::log_format::LogLevel;
 use std::sync::LazyLock;
 
 /// The long version string, computed once at startup.
@@ -110,6 +111,21 @@ pub enum Commands {
         #[command(subcommand)]
         command: ServiceCommands,
     },
+    /// Snapshot and restore database services
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    /// Inspect and replay HTTP requests sent to a service
+    Requests {
+        #[command(subcommand)]
+        command: RequestsCommands,
+    },
+    /// Control how the proxy handles requests to services
+    Proxy {
+        #[command(subcommand)]
+        command: ProxyCommands,
+    },
     /// Monitor running services (TUI)
     Monitor,
     /// Ping the locald daemon
@@ -128,6 +144,9 @@ pub enum Commands {
         /// Show verbose output
         #[arg(long, short)]
         verbose: bool,
+        /// Run with a profile from locald.toml (`[profiles.<name>]`)
+        #[arg(long)]
+        profile: Option<String>,
     },
     /// Open the dashboard in the default browser
     Dashboard,
@@ -161,6 +180,24 @@ pub enum Commands {
         /// Follow log output
         #[arg(short, long)]
         follow: bool,
+        /// Show logs since a time: relative (e.g. 10m, 2h, 1d) or absolute (e.g. 2024-05-01 14:00)
+        #[arg(long, value_parser = parse_time)]
+        since: Option<i64>,
+        /// Show logs until a time (same formats as --since)
+        #[arg(long, value_parser = parse_time)]
+        until: Option<i64>,
+        /// Show only the last N lines
+        #[arg(short = 'n', long)]
+        tail: Option<usize>,
+        /// Show only lines matching this regular expression
+        #[arg(long)]
+        grep: Option<String>,
+        /// Show only structured (JSON or logfmt) lines at or above this level
+        #[arg(long)]
+        level: Option<LogLevel>,
+        /// Show only structured lines with this field value (repeatable)
+        #[arg(long = "where", value_name = "KEY=VALUE", value_parser = parse_field)]
+        fields: Vec<(String, String)>,
     },
     /// Administrative commands
     Admin {
@@ -182,6 +219,11 @@ pub enum Commands {
         #[command(subcommand)]
         command: ConfigCommands,
     },
+    /// Convert another tool's project config into locald.toml
+    Import {
+        #[command(subcommand)]
+        command: ImportCommands,
+    },
     /// Registry management commands
     Registry {
         #[command(subcommand)]
@@ -325,6 +367,30 @@ pub enum ConfigCommands {
         #[arg(long)]
         provenance: bool,
     },
+    /// Check locald.toml and report every problem in it, with where it is
+    Validate {
+        /// Path to the project (default: current directory)
+        #[arg(default_value = ".")]
+        path: std::path::PathBuf,
+        /// Check the config with a profile applied
+        #[arg(long)]
+        profile: Option<String>,
+        /// Machine-readable JSON output
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommands {
+    /// Convert a Docker Compose file into locald.toml, listing what couldn't be translated
+    Compose {
+        /// The compose file (default: compose.yaml or docker-compose.yml in the current directory)
+        file: Option<std::path::PathBuf>,
+        /// Overwrite an existing locald.toml
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -361,6 +427,115 @@ pub enum ServiceCommands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Save a copy of a database's data
+    Snapshot {
+        /// Name of the database service
+        service: String,
+        /// Name of the snapshot (default: the current time)
+        name: Option<String>,
+    },
+    /// Replace a database's data with a snapshot
+    Restore {
+        /// Name of the database service
+        service: String,
+        /// Name of the snapshot to restore
+        name: String,
+    },
+    /// List snapshots
+    Snapshots {
+        /// Only list snapshots of this service
+        service: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RequestsCommands {
+    /// Start capturing requests the proxy sends to a service
+    On {
+        /// Name of the service
+        service: String,
+    },
+    /// Stop capturing requests to a service
+    Off {
+        /// Name of the service
+        service: String,
+    },
+    /// List captured requests
+    List {
+        /// Name of the service
+        service: String,
+    },
+    /// Show a captured request and its response
+    Show {
+        /// Name of the service
+        service: String,
+        /// Id of the request (from `locald requests list`)
+        id: u64,
+    },
+    /// Send a captured request to the service again
+    Replay {
+        /// Name of the service
+        service: String,
+        /// Id of the request (from `locald requests list`)
+        id: u64,
+    },
+    /// Discard captured requests
+    Clear {
+        /// Name of the service
+        service: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProxyCommands {
+    /// Inject latency, errors, resets and bandwidth limits into requests to a service
+    Fault {
+        #[command(subcommand)]
+        command: FaultCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FaultCommands {
+    /// Start injecting faults, using the rules in locald.toml unless any are given
+    On {
+        /// Name of the service
+        service: String,
+        /// Delay added to each request, in milliseconds
+        #[arg(long)]
+        latency: Option<u64>,
+        /// Random extra delay of up to this many milliseconds
+        #[arg(long)]
+        jitter: Option<u64>,
+        /// Percentage of requests answered with an error
+        #[arg(long)]
+        error_rate: Option<u8>,
+        /// Status of injected errors (default: 503)
+        #[arg(long)]
+        error_status: Option<u16>,
+        /// Percentage of connections closed without a response
+        #[arg(long)]
+        reset_rate: Option<u8>,
+        /// Limit responses to this many bytes per second
+        #[arg(long)]
+        bandwidth: Option<u64>,
+    },
+    /// Stop injecting faults into requests to a service
+    Off {
+        /// Name of the service
+        service: String,
+    },
+    /// Go back to the fault rules in locald.toml
+    Reset {
+        /// Name of the service
+        service: String,
+    },
+    /// List services with fault rules
+    List,
+}
+
 #[derive(Subcommand)]
 pub enum AddServiceType {
     /// Add a shell command service
@@ -383,6 +558,14 @@ pub enum AddServiceType {
         #[arg(long)]
         version: Option<String>,
     },
+    /// Add a managed Redis service
+    Redis {
+        /// Name of the service
+        name: String,
+        /// Redis version
+        #[arg(long)]
+        version: Option<String>,
+    },
     /// Add a container service
     Container {
         /// Docker image to run
@@ -449,6 +632,55 @@ pub enum DebugCommands {
     },
 }
 
+/// Parses a `--since`/`--until` value into Unix epoch seconds.
+///
+/// Accepts a duration ago (`30s`, `10m`, `2h`, `1d`), an RFC 3339 timestamp, or a
+/// local date and time (`2024-05-01 14:00`, `2024-05-01`).
+fn parse_time(value: &str) -> Result<i64, String> {
+    let now = chrono::Local::now();
+
+    if let Some(unit) = value.chars().last().filter(char::is_ascii_alphabetic)
+        && let Ok(amount) = value[..value.len() - 1].parse::<i64>()
+    {
+        let seconds = match unit {
+            's' => amount,
+            'm' => amount * 60,
+            'h' => amount * 60 * 60,
+            'd' => amount * 24 * 60 * 60,
+            _ => return Err(format!("unknown unit '{unit}' (use s, m, h or d)")),
+        };
+        return Ok(now.timestamp() - seconds);
+    }
+
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp());
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| format!("invalid time '{value}' (e.g. 10m, 2h, 2024-05-01 14:00)"))?;
+    naive
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or_else(|| format!("'{value}' does not exist in the local timezone"))
+}
+
+/// Parses a `--where` filter like `user_id=42`.
+fn parse_field(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid filter '{value}' (expected KEY=VALUE)"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,4 +710,55 @@ mod tests {
             _ => panic!("expected Commands::Exec"),
         }
     }
+
+    #[test]
+    fn parse_logs_time_window() {
+        let cli =
+            Cli::try_parse_from(["locald", "logs", "web", "--since", "10m", "-n", "50"]).unwrap();
+        let Commands::Logs {
+            since: Some(since),
+            tail,
+            ..
+        } = cli.command
+        else {
+            unreachable!("expected Commands::Logs with --since");
+        };
+        let expected = chrono::Local::now().timestamp() - 600;
+        assert!((since - expected).abs() <= 1);
+        assert_eq!(tail, Some(50));
+
+        assert_eq!(parse_time("2024-05-01T12:00:00Z").unwrap(), 1_714_564_800);
+        assert!(parse_time("2024-05-01 14:00").is_ok());
+        assert!(parse_time("10y").is_err());
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn parse_logs_structured_filters() {
+        let cli = Cli::try_parse_from([
+            "locald",
+            "logs",
+            "--level",
+            "WARN",
+            "--where",
+            "user_id=42",
+            "--where",
+            "path=/a=b",
+        ])
+        .unwrap();
+        let Commands::Logs { level, fields, .. } = cli.command else {
+            unreachable!("expected Commands::Logs");
+        };
+        assert_eq!(level, Some(LogLevel::Warn));
+        assert_eq!(
+            fields,
+            [
+                ("user_id".to_string(), "42".to_string()),
+                ("path".to_string(), "/a=b".to_string())
+            ]
+        );
+
+        assert!(Cli::try_parse_from(["locald", "logs", "--where", "user_id"]).is_err());
+        assert!(Cli::try_parse_from(["locald", "logs", "--level", "loud"]).is_err());
+    }
 }
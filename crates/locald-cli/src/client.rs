@@ -3,7 +3,9 @@ use crossterm::style::{Color, Stylize};
 use locald_core::{
     IpcRequest, IpcResponse,
     ipc::{LogEntry, LogStream},
+    log_format::{LogLevel, StructuredLog},
 };
+use std::collections::BTreeMap;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::os::unix::net::UnixStream;
 
@@ -35,7 +37,18 @@ pub fn send_request(request: &IpcRequest) -> Result<IpcResponse> {
     Ok(response)
 }
 
-pub fn stream_logs(service: Option<String>, follow: bool) -> Result<()> {
+/// Narrows down which log entries `stream_logs` prints.
+#[derive(Debug, Default)]
+pub struct LogQuery {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub tail: Option<usize>,
+    pub grep: Option<String>,
+    pub level: Option<LogLevel>,
+    pub fields: BTreeMap<String, String>,
+}
+
+pub fn stream_logs(service: Option<String>, follow: bool, query: LogQuery) -> Result<()> {
     let socket_path = locald_utils::ipc::socket_path()?;
     let mut stream = UnixStream::connect(socket_path)?;
     let mode = if follow {
@@ -43,7 +56,16 @@ pub fn stream_logs(service: Option<String>, follow: bool) -> Result<()> {
     } else {
         locald_core::ipc::LogMode::Snapshot
     };
-    let request = IpcRequest::Logs { service, mode };
+    let request = IpcRequest::Logs {
+        service,
+        mode,
+        since: query.since,
+        until: query.until,
+        tail: query.tail,
+        grep: query.grep,
+        level: query.level,
+        fields: query.fields,
+    };
     let request_bytes = serde_json::to_vec(&request)?;
     stream.write_all(&request_bytes)?;
 
@@ -66,18 +88,59 @@ pub fn stream_logs(service: Option<String>, follow: bool) -> Result<()> {
                 "OUT".with(Color::Green)
             };
 
+            let message = entry
+                .structured
+                .as_ref()
+                .map_or_else(|| entry.message.clone(), format_structured);
+
             println!(
                 "{} {} {} | {}",
                 timestamp.with(Color::DarkGrey),
                 entry.service.cyan().bold(),
                 stream_style,
-                entry.message
+                message
             );
+        } else if let Ok(IpcResponse::Error(msg)) = serde_json::from_str::<IpcResponse>(&line) {
+            anyhow::bail!(msg);
         }
     }
     Ok(())
 }
 
+/// Renders a JSON or logfmt line as `LEVEL message key=value ...`.
+fn format_structured(log: &StructuredLog) -> String {
+    let mut parts = Vec::new();
+    if let Some(level) = log.level {
+        let color = match level {
+            LogLevel::Trace | LogLevel::Debug => Color::DarkGrey,
+            LogLevel::Info => Color::Blue,
+            LogLevel::Warn => Color::Yellow,
+            LogLevel::Error | LogLevel::Fatal => Color::Red,
+        };
+        parts.push(
+            format!("{:<5}", level.to_string().to_uppercase())
+                .with(color)
+                .to_string(),
+        );
+    }
+    if let Some(message) = &log.message {
+        parts.push(message.clone());
+    }
+    for key in log.fields.keys() {
+        let value = log.field(key).unwrap_or_default();
+        let value = if value.contains(char::is_whitespace) {
+            format!("{value:?}")
+        } else {
+            value
+        };
+        parts.push(format!(
+            "{}{value}",
+            format!("{key}=").with(Color::DarkGrey)
+        ));
+    }
+    parts.join(" ")
+}
+
 pub fn stream_boot_events(request: &IpcRequest) -> Result<()> {
     let socket_path = locald_utils::ipc::socket_path()?;
     let mut stream = UnixStream::connect(socket_path)?;
@@ -99,11 +162,18 @@ pub fn stream_boot_events(request: &IpcRequest) -> Result<()> {
         } else if let Ok(response) = serde_json::from_str::<IpcResponse>(&line) {
             // It might be the final response (Ok or Error)
             match response {
-                IpcResponse::Ok => return Ok(()),
-                IpcResponse::Error(msg) => anyhow::bail!(msg),
+                IpcResponse::Ok => {
+                    renderer.finish(None);
+                    return Ok(());
+                }
+                IpcResponse::Error(msg) => {
+                    renderer.finish(Some(&msg));
+                    anyhow::bail!(msg)
+                }
                 _ => {} // Ignore other responses?
             }
         }
     }
+    renderer.finish(None);
     Ok(())
 }
@@ -0,0 +1,105 @@
+use crate::{client, style, utils};
+use anyhow::Result;
+use crossterm::style::Stylize;
+use locald_core::{IpcRequest, IpcResponse};
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn snapshot(service: &str, name: Option<String>) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::DbSnapshot {
+        service: service.clone(),
+        name,
+    }) {
+        Ok(IpcResponse::Snapshot(info)) => {
+            println!(
+                "{} Saved snapshot {} of {} ({})",
+                style::CHECK,
+                info.name.as_str().bold(),
+                service.as_str().bold(),
+                format_size(info.size_bytes)
+            );
+            println!(
+                "  Restore it with: locald db restore {service} {}",
+                info.name
+            );
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to snapshot {service}: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn restore(service: &str, name: &str) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::DbRestore {
+        service: service.clone(),
+        name: name.to_string(),
+    }) {
+        Ok(IpcResponse::Ok) => {
+            println!(
+                "{} Restored {} from snapshot {}",
+                style::CHECK,
+                service.as_str().bold(),
+                name.bold()
+            );
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to restore {service}: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn list(service: Option<&str>) -> Result<()> {
+    match client::send_request(&IpcRequest::DbSnapshots {
+        service: service.map(utils::full_name),
+    }) {
+        Ok(IpcResponse::Snapshots(snapshots)) => {
+            if snapshots.is_empty() {
+                println!("No snapshots found.");
+                return Ok(());
+            }
+
+            println!(
+                "{:<25} {:<25} {:<20} {:>10}",
+                "SERVICE", "NAME", "CREATED", "SIZE"
+            );
+            for snapshot in snapshots {
+                let created = chrono::DateTime::from_timestamp(snapshot.created_at, 0).map_or_else(
+                    || "-".to_string(),
+                    |t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    },
+                );
+                println!(
+                    "{:<25} {:<25} {:<20} {:>10}",
+                    snapshot.service,
+                    snapshot.name,
+                    created,
+                    format_size(snapshot.size_bytes)
+                );
+            }
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to list snapshots: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
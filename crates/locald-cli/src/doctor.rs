@@ -5,6 +5,7 @@ use crossterm::tty::IsTty;
 use locald_utils::privileged::{AcquireConfig, CleanupMode, DoctorReport, Severity, Status};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 
 struct DoctorCompactTheme;
 
@@ -580,6 +581,24 @@ fn render_optional_integrations() -> OptionalIntegrationsRollup {
     #[cfg(not(unix))]
     {}
 
+    if let Some(dns) = check_dns() {
+        if let Some(path) = &dns.pointed_from {
+            println!("- Local DNS (optional): {}", "available".green());
+            println!("  - Impact: wildcard and custom project domains resolve via locald");
+            println!("  - Checked: {} points at {}", path.display(), dns.addr());
+        } else {
+            rollup.unavailable.push("Local DNS".to_string());
+            println!("- Local DNS (optional): {}", "unavailable".yellow());
+            println!("  - Dependency: system resolver pointed at {}", dns.addr());
+            println!("  - Impact: lookups don't reach the DNS server; /etc/hosts is used instead");
+            println!(
+                "  - If you need this: configure your resolver to use {}",
+                dns.addr()
+            );
+            println!("  - Checked: {}", dns.checked());
+        }
+    }
+
     rollup
 }
 
@@ -779,6 +798,58 @@ fn render_optional_integrations_cliclack(verbose: bool) -> OptionalIntegrationsR
     #[cfg(not(unix))]
     {}
 
+    if let Some(dns) = check_dns() {
+        if let Some(path) = &dns.pointed_from {
+            available_count += 1;
+            lines.push(IntegrationLine {
+                name: "Local DNS".to_string(),
+                headline: "Local DNS (optional): available".to_string(),
+                status: IntegrationStatus::Available,
+                details: {
+                    let mut details = Vec::new();
+                    details.push(
+                        "Impact: wildcard and custom project domains resolve via locald"
+                            .to_string(),
+                    );
+                    if verbose {
+                        details.push(format!(
+                            "Checked: {} points at {}",
+                            path.display(),
+                            dns.addr()
+                        ));
+                    }
+                    details
+                },
+            });
+        } else {
+            unavailable_count += 1;
+            lines.push(IntegrationLine {
+                name: "Local DNS".to_string(),
+                headline: "Local DNS (optional): unavailable".to_string(),
+                status: IntegrationStatus::Unavailable,
+                details: {
+                    let mut details = Vec::new();
+                    details.push(
+                        "Impact: lookups don't reach the DNS server; /etc/hosts is used instead"
+                            .to_string(),
+                    );
+                    details.push(format!(
+                        "If you need this: configure your resolver to use {}",
+                        dns.addr()
+                    ));
+                    if verbose {
+                        details.push(format!(
+                            "Dependency: system resolver pointed at {}",
+                            dns.addr()
+                        ));
+                        details.push(format!("Checked: {}", dns.checked()));
+                    }
+                    details
+                },
+            });
+        }
+    }
+
     let unknown_count = lines
         .iter()
         .filter(|l| l.status == IntegrationStatus::Unknown)
@@ -816,3 +887,113 @@ fn render_optional_integrations_cliclack(verbose: bool) -> OptionalIntegrationsR
 
     rollup
 }
+
+/// Where the system resolver was found pointing at locald's DNS server, if anywhere.
+#[derive(Debug)]
+struct DnsCheck {
+    port: u16,
+    pointed_from: Option<PathBuf>,
+    searched: Vec<PathBuf>,
+}
+
+impl DnsCheck {
+    fn addr(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    fn checked(&self) -> String {
+        if self.searched.is_empty() {
+            return "no resolver configuration found".to_string();
+        }
+        self.searched
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Looks through the resolver configuration for a pointer to locald's DNS server.
+/// Returns `None` when `[dns]` isn't enabled.
+fn check_dns() -> Option<DnsCheck> {
+    let loader = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .ok()?
+        .block_on(locald_server::config_loader::ConfigLoader::load())
+        .ok()?;
+    let dns = loader.global.dns;
+    if !dns.enabled {
+        return None;
+    }
+
+    let searched: Vec<PathBuf> = resolver_config_files()
+        .into_iter()
+        .filter(|p| p.is_file())
+        .collect();
+    let pointed_from = searched
+        .iter()
+        .find(|path| {
+            std::fs::read_to_string(path).is_ok_and(|content| points_at_locald(&content, dns.port))
+        })
+        .cloned();
+
+    Some(DnsCheck {
+        port: dns.port,
+        pointed_from,
+        searched,
+    })
+}
+
+/// The files that decide where the system sends DNS lookups.
+fn resolver_config_files() -> Vec<PathBuf> {
+    #[cfg(target_os = "macos")]
+    let (files, dirs): (&[&str], &[&str]) = (&[], &["/etc/resolver"]);
+
+    #[cfg(not(target_os = "macos"))]
+    let (files, dirs): (&[&str], &[&str]) = (
+        &["/etc/resolv.conf", "/etc/systemd/resolved.conf"],
+        &[
+            "/etc/systemd/resolved.conf.d",
+            "/etc/NetworkManager/dnsmasq.d",
+            "/etc/dnsmasq.d",
+        ],
+    );
+
+    let mut paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+    for dir in dirs {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            let mut found: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            found.sort();
+            paths.extend(found);
+        }
+    }
+    paths
+}
+
+/// Whether a resolv.conf, `/etc/resolver` file, systemd-resolved or dnsmasq config
+/// sends lookups to `127.0.0.1:port`.
+fn points_at_locald(content: &str, port: u16) -> bool {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#') && !l.starts_with(';'))
+        .collect();
+
+    // systemd-resolved (`DNS=127.0.0.1:5354`) and dnsmasq (`server=/test/127.0.0.1#5354`)
+    let resolved = format!("127.0.0.1:{port}");
+    let dnsmasq = format!("127.0.0.1#{port}");
+    if lines
+        .iter()
+        .any(|l| l.contains(&resolved) || l.contains(&dnsmasq))
+    {
+        return true;
+    }
+
+    // resolv.conf and macOS `/etc/resolver` files, which give the port separately
+    let port_line = format!("port {port}");
+    let loopback = lines
+        .iter()
+        .any(|l| l.split_whitespace().eq(["nameserver", "127.0.0.1"]));
+    loopback && (port == 53 || lines.iter().any(|l| *l == port_line))
+}
@@ -0,0 +1,126 @@
+use crate::{client, style, utils};
+use anyhow::Result;
+use crossterm::style::Stylize;
+use locald_core::config::FaultConfig;
+use locald_core::{IpcRequest, IpcResponse};
+
+/// Fault rules given on the command line.
+pub struct Rules {
+    pub latency: Option<u64>,
+    pub jitter: Option<u64>,
+    pub error_rate: Option<u8>,
+    pub error_status: Option<u16>,
+    pub reset_rate: Option<u8>,
+    pub bandwidth: Option<u64>,
+}
+
+impl Rules {
+    /// The rules to send to the daemon, or `None` to use those in `locald.toml`.
+    pub fn into_config(self) -> Option<FaultConfig> {
+        let given = self.latency.is_some()
+            || self.jitter.is_some()
+            || self.error_rate.is_some()
+            || self.error_status.is_some()
+            || self.reset_rate.is_some()
+            || self.bandwidth.is_some();
+        if !given {
+            return None;
+        }
+        let defaults = FaultConfig::default();
+        Some(FaultConfig {
+            enabled: true,
+            latency_ms: self.latency.unwrap_or(defaults.latency_ms),
+            jitter_ms: self.jitter.unwrap_or(defaults.jitter_ms),
+            error_rate: self.error_rate.unwrap_or(defaults.error_rate),
+            error_status: self.error_status.unwrap_or(defaults.error_status),
+            reset_rate: self.reset_rate.unwrap_or(defaults.reset_rate),
+            bandwidth: self.bandwidth,
+        })
+    }
+}
+
+pub fn set(service: &str, enabled: Option<bool>, rules: Option<FaultConfig>) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::SetFaults {
+        service: service.clone(),
+        enabled,
+        rules,
+    }) {
+        Ok(IpcResponse::Ok) => {
+            let action = match enabled {
+                Some(true) => "Injecting faults into requests to",
+                Some(false) => "Stopped injecting faults into requests to",
+                None => "Using the fault rules in locald.toml for",
+            };
+            println!("{} {action} {}", style::CHECK, service.as_str().bold());
+            if let Some(rules) = rules {
+                println!("  {}", describe(&rules));
+            }
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to update faults: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    match client::send_request(&IpcRequest::Faults) {
+        Ok(IpcResponse::Faults(faults)) => {
+            if faults.is_empty() {
+                println!("No services have fault rules.");
+                println!("  Add some with: locald proxy fault on <service> --latency 500");
+                return Ok(());
+            }
+            println!("{:<30} {:<8} RULES", "SERVICE", "STATE");
+            for service in &faults {
+                let state = match (service.active, service.overridden) {
+                    (true, false) => "on".to_string(),
+                    (true, true) => "on*".to_string(),
+                    (false, false) => "off".to_string(),
+                    (false, true) => "off*".to_string(),
+                };
+                println!(
+                    "{:<30} {:<8} {}",
+                    service.service,
+                    state,
+                    describe(&service.rules)
+                );
+            }
+            if faults.iter().any(|f| f.overridden) {
+                println!("\n* changed at runtime; `locald proxy fault reset <service>` undoes it");
+            }
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to list faults: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+fn describe(rules: &FaultConfig) -> String {
+    let mut parts = Vec::new();
+    if rules.jitter_ms > 0 {
+        parts.push(format!(
+            "latency {}-{}ms",
+            rules.latency_ms,
+            rules.latency_ms.saturating_add(rules.jitter_ms)
+        ));
+    } else if rules.latency_ms > 0 {
+        parts.push(format!("latency {}ms", rules.latency_ms));
+    }
+    if rules.error_rate > 0 {
+        parts.push(format!("{}% {}", rules.error_rate, rules.error_status));
+    }
+    if rules.reset_rate > 0 {
+        parts.push(format!("{}% resets", rules.reset_rate));
+    }
+    if let Some(bandwidth) = rules.bandwidth {
+        parts.push(format!("{bandwidth} B/s"));
+    }
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
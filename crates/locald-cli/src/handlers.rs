@@ -8,15 +8,17 @@ use crate::build;
 #[cfg(feature = "experimental-plugins")]
 use crate::cli::PluginCommands;
 use crate::cli::{
-    AddServiceType, AdminCommands, AiCommands, Cli, Commands, ConfigCommands, DebugCommands,
-    RegistryCommands, ServerCommands, ServiceCommands, SurfaceCommands,
+    AddServiceType, AdminCommands, AiCommands, Cli, Commands, ConfigCommands, DbCommands,
+    DebugCommands, FaultCommands, ImportCommands, ProxyCommands, RegistryCommands,
+    RequestsCommands, ServerCommands, ServiceCommands, SurfaceCommands,
 };
 #[cfg(feature = "experimental-containers")]
 use crate::container;
 #[cfg(feature = "experimental-plugins")]
 use crate::plugin;
 use crate::{
-    client, debug, doctor, history, init, monitor, run, service, style, trust, try_cmd, utils,
+    client, db, debug, doctor, faults, history, import, init, monitor, requests, run, service,
+    style, trust, try_cmd, utils, validate,
 };
 
 pub fn run(cli: Cli) -> Result<()> {
@@ -68,6 +70,10 @@ pub fn run(cli: Cli) -> Result<()> {
                     utils::ensure_daemon_running()?;
                     service::add_postgres(name, version.clone())?;
                 }
+                AddServiceType::Redis { name, version } => {
+                    utils::ensure_daemon_running()?;
+                    service::add_redis(name, version.clone())?;
+                }
                 AddServiceType::Container {
                     image,
                     name,
@@ -125,6 +131,54 @@ pub fn run(cli: Cli) -> Result<()> {
                 }
             }
         },
+        Commands::Db { command } => {
+            utils::ensure_daemon_running()?;
+            match command {
+                DbCommands::Snapshot { service, name } => db::snapshot(service, name.clone())?,
+                DbCommands::Restore { service, name } => db::restore(service, name)?,
+                DbCommands::Snapshots { service } => db::list(service.as_deref())?,
+            }
+        }
+        Commands::Requests { command } => {
+            utils::ensure_daemon_running()?;
+            match command {
+                RequestsCommands::On { service } => requests::capture(service, true)?,
+                RequestsCommands::Off { service } => requests::capture(service, false)?,
+                RequestsCommands::List { service } => requests::list(service)?,
+                RequestsCommands::Show { service, id } => requests::show(service, *id)?,
+                RequestsCommands::Replay { service, id } => requests::replay(service, *id)?,
+                RequestsCommands::Clear { service } => requests::clear(service)?,
+            }
+        }
+        Commands::Proxy {
+            command: ProxyCommands::Fault { command },
+        } => {
+            utils::ensure_daemon_running()?;
+            match command {
+                FaultCommands::On {
+                    service,
+                    latency,
+                    jitter,
+                    error_rate,
+                    error_status,
+                    reset_rate,
+                    bandwidth,
+                } => {
+                    let rules = faults::Rules {
+                        latency: *latency,
+                        jitter: *jitter,
+                        error_rate: *error_rate,
+                        error_status: *error_status,
+                        reset_rate: *reset_rate,
+                        bandwidth: *bandwidth,
+                    };
+                    faults::set(service, Some(true), rules.into_config())?;
+                }
+                FaultCommands::Off { service } => faults::set(service, Some(false), None)?,
+                FaultCommands::Reset { service } => faults::set(service, None, None)?,
+                FaultCommands::List => faults::list()?,
+            }
+        }
         Commands::Monitor => {
             utils::ensure_daemon_running()?;
             monitor::run()?;
@@ -170,7 +224,18 @@ pub fn run(cli: Cli) -> Result<()> {
                 println!("{} locald restarted successfully.", style::CHECK);
             }
         },
-        Commands::Up { path, verbose } => {
+        Commands::Up {
+            path,
+            verbose,
+            profile,
+        } => {
+            // Report config problems before touching the daemon
+            let target_path = match path {
+                Some(p) => p.clone(),
+                None => std::env::current_dir()?,
+            };
+            validate::check_before_up(&target_path, profile.as_deref())?;
+
             let current_version = env!("LOCALD_BUILD_VERSION");
 
             // Check if already running and check version
@@ -234,13 +299,6 @@ pub fn run(cli: Cli) -> Result<()> {
                 s.stop("locald server started");
             }
 
-            // Resolve path and check for config
-            let target_path = if let Some(p) = path {
-                p.clone()
-            } else {
-                std::env::current_dir()?
-            };
-
             let config_exists = target_path.join("locald.toml").exists();
 
             // If no path was explicitly provided and no config exists, we are done.
@@ -260,6 +318,12 @@ pub fn run(cli: Cli) -> Result<()> {
                 match client::stream_boot_events(&IpcRequest::Start {
                     project_path: abs_path.clone(),
                     verbose: *verbose,
+                    profile: profile.clone(),
+                    env: Some(
+                        locald_server::config_loader::ConfigLoader::process_env()
+                            .into_iter()
+                            .collect(),
+                    ),
                 }) {
                     Ok(()) => {
                         cliclack::outro("Project registered")?;
@@ -369,25 +433,77 @@ pub fn run(cli: Cli) -> Result<()> {
                                 .map(|p| p.to_string())
                                 .unwrap_or_else(|| "-".to_string());
                             let url = service.url.unwrap_or_else(|| "-".to_string());
-                            let status_style = match service.status {
-                                locald_core::state::ServiceState::Running => {
+                            // A task that ran to completion is stopped on purpose
+                            let finished_task = service.task_run.as_ref().filter(|_| {
+                                service.status == locald_core::state::ServiceState::Stopped
+                            });
+                            let status_style = if service.crash_looping {
+                                crossterm::style::Color::Red
+                            } else if let Some(run) = finished_task {
+                                if run.succeeded() {
                                     crossterm::style::Color::Green
-                                }
-                                locald_core::state::ServiceState::Stopped => {
+                                } else {
                                     crossterm::style::Color::Red
                                 }
-                                locald_core::state::ServiceState::Building => {
-                                    crossterm::style::Color::Blue
+                            } else {
+                                match service.status {
+                                    locald_core::state::ServiceState::Running => {
+                                        crossterm::style::Color::Green
+                                    }
+                                    locald_core::state::ServiceState::Stopped => {
+                                        crossterm::style::Color::Red
+                                    }
+                                    locald_core::state::ServiceState::Building => {
+                                        crossterm::style::Color::Blue
+                                    }
+                                }
+                            };
+                            let status_str = if service.crash_looping {
+                                "CrashLoop".to_string()
+                            } else if let Some(run) = finished_task {
+                                if run.succeeded() {
+                                    "Completed".to_string()
+                                } else {
+                                    "Failed".to_string()
                                 }
+                            } else {
+                                format!("{:?}", service.status)
                             };
                             println!(
                                 "{:<20} {:<10} {:<10} {:<30}",
                                 service.name,
-                                format!("{:?}", service.status).with(status_style),
+                                status_str.with(status_style),
                                 port_str,
                                 url
                             );
 
+                            if let Some(run) = &service.task_run {
+                                let exit = run.exit_code.map_or_else(
+                                    || "unexpectedly".to_string(),
+                                    |code| format!("with code {code}"),
+                                );
+                                #[allow(clippy::cast_precision_loss)]
+                                let secs = run.duration_ms as f64 / 1000.0;
+                                println!("  {} exited {exit} after {secs:.1}s", "TASK:".bold());
+                            }
+
+                            if service.crash_count > 0 {
+                                let exit = service.last_exit_code.map_or_else(
+                                    || "unexpectedly".to_string(),
+                                    |code| format!("with code {code}"),
+                                );
+                                let label = if service.crash_looping {
+                                    "CRASH-LOOPING:"
+                                } else {
+                                    "CRASHED:"
+                                };
+                                println!(
+                                    "  {} exited {exit} ({} consecutive crashes)",
+                                    label.red().bold(),
+                                    service.crash_count
+                                );
+                            }
+
                             if !service.warnings.is_empty() {
                                 println!(
                                     "  {} {}",
@@ -402,7 +518,16 @@ pub fn run(cli: Cli) -> Result<()> {
                 Err(e) => utils::handle_ipc_error(&e),
             }
         }
-        Commands::Logs { service, follow } => {
+        Commands::Logs {
+            service,
+            follow,
+            since,
+            until,
+            tail,
+            grep,
+            level,
+            fields,
+        } => {
             utils::ensure_daemon_running()?;
             let service_name = if let Some(name) = service {
                 if name.contains(':') {
@@ -428,7 +553,15 @@ pub fn run(cli: Cli) -> Result<()> {
                 None
             };
 
-            if let Err(e) = client::stream_logs(service_name, *follow) {
+            let query = client::LogQuery {
+                since: *since,
+                until: *until,
+                tail: *tail,
+                grep: grep.clone(),
+                level: *level,
+                fields: fields.iter().cloned().collect(),
+            };
+            if let Err(e) = client::stream_logs(service_name, *follow, query) {
                 utils::handle_ipc_error(&e);
             }
         }
@@ -688,6 +821,50 @@ pub fn run(cli: Cli) -> Result<()> {
                         loader.global.server.fallback_ports,
                         loader.explain_global("server.fallback_ports")
                     );
+                    println!(
+                        "startup_concurrency = {}  (from {})",
+                        loader.global.server.startup_concurrency,
+                        loader.explain_global("server.startup_concurrency")
+                    );
+                    println!();
+                    println!("[logs]");
+                    println!(
+                        "max_size_mb = {}  (from {})",
+                        loader.global.logs.max_size_mb,
+                        loader.explain_global("logs.max_size_mb")
+                    );
+                    println!(
+                        "max_age_days = {}  (from {})",
+                        loader.global.logs.max_age_days,
+                        loader.explain_global("logs.max_age_days")
+                    );
+                    println!();
+                    println!("[dns]");
+                    println!(
+                        "enabled = {}  (from {})",
+                        loader.global.dns.enabled,
+                        loader.explain_global("dns.enabled")
+                    );
+                    println!(
+                        "port = {}  (from {})",
+                        loader.global.dns.port,
+                        loader.explain_global("dns.port")
+                    );
+                    if let Some(upstream) = loader.global.dns.upstream {
+                        println!(
+                            "upstream = \"{upstream}\"  (from {})",
+                            loader.explain_global("dns.upstream")
+                        );
+                    }
+                    if !loader.global.acme.domains.is_empty() {
+                        println!();
+                        println!("[acme]");
+                        println!(
+                            "domains = {:?}  (from {})",
+                            loader.global.acme.domains,
+                            loader.explain_global("acme.domains")
+                        );
+                    }
 
                     if let Ok(report) = rt.block_on(loader.load_service_provenance_report(&cwd)) {
                         for (service_name, service) in report.services {
@@ -776,6 +953,22 @@ pub fn run(cli: Cli) -> Result<()> {
                     println!("{}", toml::to_string_pretty(&loader.global)?);
                 }
             }
+            ConfigCommands::Validate {
+                path,
+                profile,
+                json,
+            } => {
+                let code = validate::run(path, profile.as_deref(), *json)?;
+                std::process::exit(code);
+            }
+        },
+        Commands::Import { command } => match command {
+            ImportCommands::Compose { file, force } => {
+                if let Err(e) = import::compose(file.as_deref(), *force) {
+                    eprintln!("Error: {e:#}");
+                    std::process::exit(1);
+                }
+            }
         },
         Commands::Doctor { json, verbose } => {
             let code = doctor::run(*json, *verbose)?;
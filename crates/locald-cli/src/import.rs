@@ -0,0 +1,69 @@
+use crate::style;
+use anyhow::{Context, Result};
+use locald_server::compose::{self, COMPOSE_FILES};
+use std::path::{Path, PathBuf};
+
+/// `locald import compose`: writes `locald.toml` next to the compose file and lists
+/// what couldn't be translated.
+pub fn compose(file: Option<&Path>, force: bool) -> Result<()> {
+    let file = match file {
+        Some(file) => file.to_path_buf(),
+        None => find_compose_file()?,
+    };
+    let file = std::fs::canonicalize(&file)
+        .with_context(|| format!("Failed to find {}", file.display()))?;
+    let dir = file.parent().unwrap_or_else(|| Path::new("."));
+    let config_path = dir.join("locald.toml");
+    if config_path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to overwrite it",
+            config_path.display()
+        );
+    }
+
+    let import = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(compose::import_compose_file(&file))?;
+    std::fs::write(&config_path, import.to_toml()?)
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+
+    println!(
+        "{} Wrote {} with {} from {}",
+        style::CHECK,
+        config_path.display(),
+        match import.config.services.len() {
+            1 => "1 service".to_string(),
+            n => format!("{n} services"),
+        },
+        file.file_name().map_or_else(
+            || file.display().to_string(),
+            |n| n.to_string_lossy().into_owned()
+        )
+    );
+
+    if !import.notes.is_empty() {
+        println!("\n{} Not translated:", style::WARN);
+        for note in &import.notes {
+            println!("  {} {note}", style::DOT);
+        }
+    }
+    println!("\nReview locald.toml, then run `locald config validate` and `locald up`.");
+
+    Ok(())
+}
+
+fn find_compose_file() -> Result<PathBuf> {
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    COMPOSE_FILES
+        .iter()
+        .map(|name| cwd.join(name))
+        .find(|path| path.exists())
+        .with_context(|| {
+            format!(
+                "No compose file found in {} (looked for {})",
+                cwd.display(),
+                COMPOSE_FILES.join(", ")
+            )
+        })
+}
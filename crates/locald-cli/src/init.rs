@@ -4,7 +4,7 @@ use locald_core::config::{
     CommonServiceConfig, ExecServiceConfig, LocaldConfig, ProjectConfig, ServiceConfig,
     TypedServiceConfig,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub fn run() -> Result<()> {
     let cwd = std::env::current_dir()?;
@@ -84,12 +84,21 @@ pub fn run() -> Result<()> {
                 depends_on: Vec::new(),
                 health_check: None,
                 stop_signal: None,
+                restart: None,
+                expose: None,
+                domains: Vec::new(),
+                faults: None,
+                headers: None,
+                cors: None,
+                protocol: None,
+                tls: false,
             },
             command: Some(command),
             workdir,
             image: None,
             container_port: None,
             build: None,
+            watch: None,
         }));
 
         services.insert(service_name, service_config);
@@ -111,6 +120,8 @@ pub fn run() -> Result<()> {
             constellation: None,
         },
         services,
+        routes: BTreeMap::new(),
+        profiles: BTreeMap::new(),
     };
 
     let toml_string = toml::to_string_pretty(&config)?;
@@ -37,16 +37,20 @@ mod client;
 #[cfg(feature = "experimental-containers")]
 mod container;
 mod crash;
+mod db;
 mod debug;
 mod doctor;
+mod faults;
 mod handlers;
 mod hints;
 mod history;
+mod import;
 mod init;
 mod monitor;
 #[cfg(feature = "experimental-plugins")]
 mod plugin;
 mod progress;
+mod requests;
 mod run;
 mod service;
 mod style;
@@ -54,6 +58,7 @@ mod surface_manifest;
 mod trust;
 mod try_cmd;
 mod utils;
+mod validate;
 
 // Force rebuild 3
 fn main() {
@@ -75,13 +80,18 @@ fn run_main(cli: cli::Cli) -> Result<()> {
         utils::setup_sandbox(sandbox_name)?;
     }
 
-    // Skip verification for admin setup, as it's used to fix the shim
+    // Skip verification for admin setup, as it's used to fix the shim, and for
+    // commands that only read files (so they work in CI)
     if !matches!(
         cli.command,
         cli::Commands::Admin {
             command: cli::AdminCommands::Setup
         } | cli::Commands::Doctor { .. }
             | cli::Commands::Surface { .. }
+            | cli::Commands::Config {
+                command: cli::ConfigCommands::Validate { .. }
+            }
+            | cli::Commands::Import { .. }
     ) {
         utils::verify_shim();
     }
@@ -1,16 +1,22 @@
-use cliclack::{ProgressBar, spinner};
+use cliclack::{MultiProgress, ProgressBar, multi_progress, spinner};
 use locald_core::ipc::BootEvent;
 use std::collections::HashMap;
 
+/// Renders `BootEvent`s as a group of spinners.
+///
+/// The daemon starts independent services concurrently, so several steps can be
+/// in flight at once. Each step gets its own spinner, keyed by step id.
 pub struct ProgressRenderer {
-    active_spinner: Option<(String, ProgressBar)>,
+    multi: Option<MultiProgress>,
+    active_spinners: HashMap<String, ProgressBar>,
     logs: HashMap<String, Vec<String>>,
 }
 
 impl ProgressRenderer {
     pub fn new() -> Self {
         Self {
-            active_spinner: None,
+            multi: None,
+            active_spinners: HashMap::new(),
             logs: HashMap::new(),
         }
     }
@@ -18,52 +24,36 @@ impl ProgressRenderer {
     pub fn handle_event(&mut self, event: BootEvent) {
         match event {
             BootEvent::StepStarted { id, description } => {
-                // If there's an existing spinner, stop it? Or is this a nested step?
-                // Manager is sequential. So if we get a new start, the old one should be done.
-                // But just in case:
-                if let Some((old_id, s)) = self.active_spinner.take() {
-                    s.stop(format!("{} (interrupted)", old_id));
+                // A step that starts again (e.g. a restart during boot) replaces its old spinner
+                if let Some(s) = self.active_spinners.remove(&id) {
+                    s.stop(format!("{} (interrupted)", id));
                 }
 
-                let s = spinner();
+                let multi = self
+                    .multi
+                    .get_or_insert_with(|| multi_progress("Starting project"));
+                let s = multi.add(spinner());
                 s.start(description);
-                self.active_spinner = Some((id.clone(), s));
+                self.active_spinners.insert(id.clone(), s);
                 self.logs.insert(id, Vec::new());
             }
             BootEvent::StepProgress { id, message } => {
-                if let Some((current_id, s)) = &self.active_spinner {
-                    if current_id == &id {
-                        s.start(message); // Update message
-                    }
+                if let Some(s) = self.active_spinners.get(&id) {
+                    s.start(message); // Update message
                 }
             }
             BootEvent::StepFinished { id, result } => {
-                if let Some((current_id, s)) = self.active_spinner.take() {
-                    if current_id == id {
-                        match result {
-                            Ok(()) => {
-                                s.stop(format!("{} registered", id));
-                            }
-                            Err(e) => {
-                                s.error(format!("{} failed: {}", id, e));
-                            }
-                        }
-                    } else {
-                        // Mismatch?
-                        // Maybe we just log it
-                        match result {
-                            Ok(()) => cliclack::log::info(format!("{} registered", id)).ok(),
-                            Err(e) => cliclack::log::error(format!("{} failed: {}", id, e)).ok(),
-                        };
-                        // Put the spinner back
-                        self.active_spinner = Some((current_id, s));
+                if let Some(s) = self.active_spinners.remove(&id) {
+                    match result {
+                        Ok(()) => s.stop(format!("{} registered", id)),
+                        Err(e) => s.error(format!("{} failed: {}", id, e)),
                     }
                 } else {
-                    // No active spinner, just log
+                    // No spinner for this step, just log
                     match result {
-                        Ok(()) => cliclack::log::info(format!("{} registered", id)).ok(),
-                        Err(e) => cliclack::log::error(format!("{} failed: {}", id, e)).ok(),
-                    };
+                        Ok(()) => self.println(format!("{} registered", id)),
+                        Err(e) => self.println(format!("{} failed: {}", id, e)),
+                    }
                 }
                 self.logs.remove(&id);
             }
@@ -72,29 +62,33 @@ impl ProgressRenderer {
                 line,
                 stream: _,
             } => {
-                // If we have an active spinner, we can't easily print "above" it without breaking it in some terminals.
-                // cliclack doesn't support "log while spinner is active" easily without clearing/redrawing.
-                // But `cliclack::log` might handle it?
-                // Let's try just printing.
-                // Or we can accumulate logs and only show on error?
-                // The user wants to see logs.
-
-                // For now, let's just print.
-                // If it messes up the spinner, we might need a better strategy.
-                // But `locald up` usually doesn't show logs unless verbose?
-                // The `manager` broadcasts logs.
-
-                // If we are in a spinner, we can try to use `s.println` if it existed.
-                // `cliclack` doesn't expose the underlying console term easily.
+                // Logs from concurrently starting services are interleaved, so prefix each
+                // line with its service. Printing through the group keeps the spinners intact.
+                self.println(format!("[{}] {}", id, line.trim_end()));
+            }
+        }
+    }
 
-                // Use println! directly to avoid cliclack's spacer lines for logs
-                // We need to clear the current line if a spinner is active, but we don't have access to it easily.
-                // However, since we are in verbose mode, maybe we don't care about the spinner artifacts as much as the log readability.
-                // Or we can try to use `\r` to overwrite?
+    /// Closes the spinner group once the daemon has sent its final response.
+    pub fn finish(&mut self, error: Option<&str>) {
+        for (id, s) in self.active_spinners.drain() {
+            s.stop(format!("{} (interrupted)", id));
+        }
 
-                // Actually, if we just print, it might be fine.
-                println!("[{}] {}", id, line.trim_end());
+        if let Some(multi) = self.multi.take() {
+            match error {
+                Some(e) => multi.error(e),
+                None => multi.stop(),
             }
         }
     }
+
+    fn println(&self, message: String) {
+        if let Some(multi) = &self.multi {
+            multi.println(message);
+        } else {
+            // Use println! directly to avoid cliclack's spacer lines for logs
+            println!("{message}");
+        }
+    }
 }
@@ -0,0 +1,210 @@
+use crate::{client, style, utils};
+use anyhow::Result;
+use crossterm::style::Stylize;
+use locald_core::ipc::{CapturedBody, CapturedRequest};
+use locald_core::{IpcRequest, IpcResponse};
+
+pub fn capture(service: &str, enabled: bool) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::CaptureRequests {
+        service: service.clone(),
+        enabled,
+    }) {
+        Ok(IpcResponse::Ok) => {
+            if enabled {
+                println!(
+                    "{} Capturing requests to {}",
+                    style::CHECK,
+                    service.as_str().bold()
+                );
+                println!("  View them with: locald requests list {service}");
+            } else {
+                println!(
+                    "{} Stopped capturing requests to {}",
+                    style::CHECK,
+                    service.as_str().bold()
+                );
+            }
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to update capture: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn list(service: &str) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::Requests {
+        service: service.clone(),
+    }) {
+        Ok(IpcResponse::Requests(capture)) => {
+            if capture.requests.is_empty() {
+                println!("No requests captured for {service}.");
+                if !capture.enabled {
+                    println!("  Start capturing with: locald requests on {service}");
+                }
+                return Ok(());
+            }
+
+            println!(
+                "{:>6} {:<10} {:<8} {:<40} {:>6} {:>8}",
+                "ID", "TIME", "METHOD", "PATH", "STATUS", "DURATION"
+            );
+            for request in &capture.requests {
+                let (status, duration) = request.response.as_ref().map_or_else(
+                    || ("-".to_string(), "-".to_string()),
+                    |r| (r.status.to_string(), format!("{}ms", r.duration_ms)),
+                );
+                println!(
+                    "{:>6} {:<10} {:<8} {:<40} {:>6} {:>8}",
+                    request.id,
+                    format_time(request.timestamp),
+                    request.method,
+                    request.path,
+                    status,
+                    duration
+                );
+            }
+            if !capture.enabled {
+                println!("\nCapture is off. Turn it on with: locald requests on {service}");
+            }
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to list requests: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn show(service: &str, id: u64) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::Requests {
+        service: service.clone(),
+    }) {
+        Ok(IpcResponse::Requests(capture)) => {
+            let Some(request) = capture.requests.iter().find(|r| r.id == id) else {
+                anyhow::bail!("No captured request {id} for {service}");
+            };
+            print_request(request);
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to get request: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn replay(service: &str, id: u64) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::ReplayRequest {
+        service: service.clone(),
+        id,
+    }) {
+        Ok(IpcResponse::Request(request)) => {
+            println!(
+                "{} Replayed request {id} to {} as request {}",
+                style::CHECK,
+                service.as_str().bold(),
+                request.id
+            );
+            println!();
+            print_request(&request);
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to replay request {id}: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+pub fn clear(service: &str) -> Result<()> {
+    let service = utils::full_name(service);
+    match client::send_request(&IpcRequest::ClearRequests {
+        service: service.clone(),
+    }) {
+        Ok(IpcResponse::Ok) => {
+            println!(
+                "{} Cleared captured requests for {}",
+                style::CHECK,
+                service.as_str().bold()
+            );
+        }
+        Ok(IpcResponse::Error(msg)) => anyhow::bail!("Failed to clear requests: {msg}"),
+        Ok(r) => println!("Unexpected response: {r:?}"),
+        Err(e) => utils::handle_ipc_error(&e),
+    }
+    Ok(())
+}
+
+fn format_time(timestamp_ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp_ms).map_or_else(
+        || "-".to_string(),
+        |t| {
+            t.with_timezone(&chrono::Local)
+                .format("%H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+fn print_request(request: &CapturedRequest) {
+    let replay = request
+        .replay_of
+        .map(|id| format!(" (replay of {id})"))
+        .unwrap_or_default();
+    println!(
+        "{} {} {}{}",
+        format!("#{}", request.id).bold(),
+        request.method.as_str().bold(),
+        request.path,
+        replay.dark_grey()
+    );
+    println!("Host: {}", request.host);
+    println!("Time: {}", format_time(request.timestamp));
+    println!();
+    print_headers(&request.headers);
+    print_body(&request.body);
+
+    println!();
+    if let Some(response) = &request.response {
+        println!(
+            "{} {} {}",
+            "Response".bold(),
+            response.status.to_string().bold(),
+            format!("({}ms)", response.duration_ms).dark_grey()
+        );
+        print_headers(&response.headers);
+        print_body(&response.body);
+    } else if let Some(error) = &request.error {
+        println!("{} {error}", style::CROSS);
+    } else {
+        println!("{}", "No response yet".dark_grey());
+    }
+}
+
+fn print_headers(headers: &[(String, String)]) {
+    for (name, value) in headers {
+        println!("  {}: {value}", name.as_str().cyan());
+    }
+}
+
+fn print_body(body: &CapturedBody) {
+    if body.size == 0 {
+        return;
+    }
+    println!();
+    match body.text() {
+        Some(text) => println!("{text}"),
+        None => println!(
+            "{}",
+            format!("<{} bytes of binary data>", body.size).dark_grey()
+        ),
+    }
+    if body.truncated {
+        println!(
+            "{}",
+            format!("<truncated; {} bytes total>", body.size).dark_grey()
+        );
+    }
+}
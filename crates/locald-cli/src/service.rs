@@ -1,9 +1,10 @@
 use anyhow::{Context, Result};
 use locald_core::config::{
     CommonServiceConfig, ContainerServiceConfig, ExecServiceConfig, LocaldConfig,
-    PostgresServiceConfig, ProjectConfig, ServiceConfig, SiteServiceConfig, TypedServiceConfig,
+    PostgresServiceConfig, ProjectConfig, RedisServiceConfig, ServiceConfig, SiteServiceConfig,
+    TypedServiceConfig,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 fn load_or_create_config() -> Result<(std::path::PathBuf, LocaldConfig)> {
     let cwd = std::env::current_dir()?;
@@ -27,6 +28,8 @@ fn load_or_create_config() -> Result<(std::path::PathBuf, LocaldConfig)> {
                 constellation: None,
             },
             services: HashMap::new(),
+            routes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
         }
     };
 
@@ -44,6 +47,12 @@ fn start_project() -> Result<()> {
     crate::client::stream_boot_events(&locald_core::IpcRequest::Start {
         project_path: cwd,
         verbose: false,
+        profile: None,
+        env: Some(
+            locald_server::config_loader::ConfigLoader::process_env()
+                .into_iter()
+                .collect(),
+        ),
     })?;
     println!("Project started successfully.");
     Ok(())
@@ -60,12 +69,21 @@ pub fn add_exec(command: String, name: Option<String>, port: Option<u16>) -> Res
             depends_on: Vec::new(),
             health_check: None,
             stop_signal: None,
+            restart: None,
+            expose: None,
+            domains: Vec::new(),
+            faults: None,
+            headers: None,
+            cors: None,
+            protocol: None,
+            tls: false,
         },
         command: Some(command),
         workdir: None,
         image: None,
         container_port: None,
         build: None,
+        watch: None,
     }));
 
     config.services.insert(service_name.clone(), service_config);
@@ -94,6 +112,14 @@ pub fn add_container(
                 depends_on: Vec::new(),
                 health_check: None,
                 stop_signal: None,
+                restart: None,
+                expose: None,
+                domains: Vec::new(),
+                faults: None,
+                headers: None,
+                cors: None,
+                protocol: None,
+                tls: false,
             },
             image,
             command,
@@ -121,8 +147,17 @@ pub fn add_postgres(name: &str, version: Option<String>) -> Result<()> {
                 depends_on: Vec::new(),
                 health_check: None,
                 stop_signal: None,
+                restart: None,
+                expose: None,
+                domains: Vec::new(),
+                faults: None,
+                headers: None,
+                cors: None,
+                protocol: None,
+                tls: false,
             },
             version,
+            ..PostgresServiceConfig::default()
         }));
 
     config.services.insert(name.to_string(), service_config);
@@ -134,6 +169,37 @@ pub fn add_postgres(name: &str, version: Option<String>) -> Result<()> {
     Ok(())
 }
 
+pub fn add_redis(name: &str, version: Option<String>) -> Result<()> {
+    let (config_path, mut config) = load_or_create_config()?;
+
+    let service_config = ServiceConfig::Typed(TypedServiceConfig::Redis(RedisServiceConfig {
+        common: CommonServiceConfig {
+            port: None,
+            env: HashMap::new(),
+            depends_on: Vec::new(),
+            health_check: None,
+            stop_signal: None,
+            restart: None,
+            expose: None,
+            domains: Vec::new(),
+            faults: None,
+            headers: None,
+            cors: None,
+            protocol: None,
+            tls: false,
+        },
+        version,
+    }));
+
+    config.services.insert(name.to_string(), service_config);
+    save_config(&config_path, &config)?;
+
+    println!("Updated locald.toml with redis service '{name}'");
+    start_project()?;
+
+    Ok(())
+}
+
 pub fn add_site(
     path: &std::path::Path,
     name: Option<String>,
@@ -150,6 +216,14 @@ pub fn add_site(
             depends_on: Vec::new(),
             health_check: None,
             stop_signal: None,
+            restart: None,
+            expose: None,
+            domains: Vec::new(),
+            faults: None,
+            headers: None,
+            cors: None,
+            protocol: None,
+            tls: false,
         },
         path: path.to_string_lossy().to_string(),
         build: build.unwrap_or_default(),
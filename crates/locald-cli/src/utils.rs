@@ -1,7 +1,7 @@
 use crate::style;
 use anyhow::{Context, Result};
 use crossterm::style::Stylize;
-use locald_core::IpcRequest;
+use locald_core::{IpcRequest, LocaldConfig};
 
 pub fn handle_ipc_error(e: &anyhow::Error) {
     let msg = e.to_string();
@@ -284,3 +284,22 @@ pub fn verify_shim() {
         }
     }
 }
+
+/// Qualifies a bare service name with the project in the current directory.
+pub fn full_name(name: &str) -> String {
+    if name.contains(':') {
+        return name.to_string();
+    }
+
+    let config_path = match std::env::current_dir() {
+        Ok(cwd) => cwd.join("locald.toml"),
+        Err(_) => return name.to_string(),
+    };
+    std::fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|content| toml::from_str::<LocaldConfig>(&content).ok())
+        .map_or_else(
+            || name.to_string(),
+            |c| format!("{}:{}", c.project.name, name),
+        )
+}
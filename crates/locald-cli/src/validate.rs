@@ -0,0 +1,151 @@
+use crate::style;
+use anyhow::Result;
+use crossterm::style::Stylize;
+use locald_server::validate::{self, Diagnostic, Severity};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// `locald config validate`. Returns the exit code: 1 if there are errors.
+pub fn run(path: &Path, profile: Option<&str>, json: bool) -> Result<i32> {
+    let diagnostics = collect(path, profile)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        print!("{}", render(&diagnostics));
+        let errors = count(&diagnostics, Severity::Error);
+        let warnings = count(&diagnostics, Severity::Warning);
+        if diagnostics.is_empty() {
+            println!("{} locald.toml is valid", style::CHECK);
+        } else if errors == 0 {
+            println!(
+                "{} locald.toml is valid, with {}",
+                style::WARN,
+                plural(warnings, "warning")
+            );
+        } else {
+            println!(
+                "{} {} and {} in locald.toml",
+                style::CROSS,
+                plural(errors, "error"),
+                plural(warnings, "warning")
+            );
+        }
+    }
+
+    Ok(i32::from(validate::has_errors(&diagnostics)))
+}
+
+/// Validates the project before `locald up` registers it, so mistakes are reported
+/// all at once, with their place in the file, instead of one by one by the daemon.
+/// Warnings are printed but don't stop anything.
+pub fn check_before_up(path: &Path, profile: Option<&str>) -> Result<()> {
+    if !path.join("locald.toml").exists() {
+        return Ok(());
+    }
+    let diagnostics = collect(path, profile)?;
+    eprint!("{}", render(&diagnostics));
+    if validate::has_errors(&diagnostics) {
+        anyhow::bail!(
+            "locald.toml has {}; fix {} and run `locald up` again",
+            plural(count(&diagnostics, Severity::Error), "error"),
+            if count(&diagnostics, Severity::Error) == 1 {
+                "it"
+            } else {
+                "them"
+            }
+        );
+    }
+    Ok(())
+}
+
+fn collect(path: &Path, profile: Option<&str>) -> Result<Vec<Diagnostic>> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(validate::validate_project(path, profile))
+}
+
+fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == severity)
+        .count()
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{n} {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+/// Renders diagnostics the way compilers do: the message, where it is, and the line
+/// with the problem underlined.
+fn render(diagnostics: &[Diagnostic]) -> String {
+    let mut sources = std::collections::HashMap::new();
+    let mut out = String::new();
+
+    for diagnostic in diagnostics {
+        let label = match diagnostic.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        let _ = writeln!(out, "{label}: {}", diagnostic.message.clone().bold());
+
+        let file = diagnostic
+            .file
+            .strip_prefix(".")
+            .unwrap_or(&diagnostic.file)
+            .display();
+        let Some(span) = diagnostic.span else {
+            match &diagnostic.key {
+                Some(key) => {
+                    let _ = writeln!(out, "  {} {file} ({key})", "-->".blue());
+                }
+                None => {
+                    let _ = writeln!(out, "  {} {file}", "-->".blue());
+                }
+            }
+            out.push('\n');
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "  {} {file}:{}:{}",
+            "-->".blue(),
+            span.start.line,
+            span.start.column
+        );
+
+        let source = sources
+            .entry(diagnostic.file.clone())
+            .or_insert_with(|| std::fs::read_to_string(&diagnostic.file).unwrap_or_default());
+        if let Some(line) = source.lines().nth(span.start.line - 1) {
+            let number = span.start.line.to_string();
+            let gutter = " ".repeat(number.len());
+            let width = line.chars().count();
+            let end = if span.end.line == span.start.line {
+                span.end.column.min(width + 1)
+            } else {
+                width + 1
+            };
+            let underline = "^".repeat(end.saturating_sub(span.start.column).max(1));
+            let _ = writeln!(out, " {gutter} {}", "|".blue());
+            let _ = writeln!(out, " {} {} {line}", number.blue(), "|".blue());
+            let _ = writeln!(
+                out,
+                " {gutter} {} {}{}",
+                "|".blue(),
+                " ".repeat(span.start.column - 1),
+                match diagnostic.severity {
+                    Severity::Error => underline.red(),
+                    Severity::Warning => underline.yellow(),
+                }
+            );
+        }
+        out.push('\n');
+    }
+    out
+}
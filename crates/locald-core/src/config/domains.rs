@@ -0,0 +1,62 @@
+//! Extra domains for a service.
+//!
+//! Each service gets one domain under its project's domain. `domains` adds aliases,
+//! and a leading `*.` matches any single label, so every tenant of a multi-tenant app
+//! reaches the same service with its original Host header:
+//!
+//! ```toml
+//! [services.app]
+//! command = "npm start"
+//! domains = ["*.myapp.localhost", "tenant.test"]
+//! ```
+
+pub use locald_utils::domain::{domain_matches, wildcard_base};
+
+/// Checks that a domain is a hostname, optionally starting with a `*.` wildcard label.
+///
+/// The name (below the wildcard) must have at least two labels and not be a public
+/// suffix, since locald answers DNS queries and issues certificates for it: `com` or
+/// `*.com` would claim every `.com` name.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the domain is invalid.
+pub fn validate_domain(domain: &str) -> Result<(), String> {
+    let name = wildcard_base(domain).unwrap_or(domain);
+    let valid = !name.is_empty()
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        return Err(format!(
+            "domain '{domain}' must be a hostname, optionally starting with '*.' (e.g. '*.myapp.localhost')"
+        ));
+    }
+    if locald_utils::domain::is_public_suffix(name) {
+        return Err(format!(
+            "domain '{domain}' covers a whole top-level domain or public suffix; use a name below it (e.g. 'myapp.localhost')"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_domain() {
+        assert!(validate_domain("*.myapp.localhost").is_ok());
+        assert!(validate_domain("tenant.test").is_ok());
+        assert!(validate_domain("a.*.test").is_err());
+        assert!(validate_domain("*").is_err());
+        assert!(validate_domain("bad_name.test").is_err());
+        assert!(validate_domain("com").is_err());
+        assert!(validate_domain("*.com").is_err());
+        assert!(validate_domain("*.localhost").is_err());
+        assert!(validate_domain("*.co.uk").is_err());
+        assert!(validate_domain("myapp.localhost").is_ok());
+    }
+}
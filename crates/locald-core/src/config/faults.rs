@@ -0,0 +1,148 @@
+//! Fault injection in the proxy, for testing how clients cope with a slow or flaky
+//! service without changing the service itself.
+//!
+//! ```toml
+//! [services.api.faults]
+//! latency_ms = 300      # every request waits 300ms...
+//! jitter_ms = 200       # ...plus up to 200ms more
+//! error_rate = 10       # 10% of requests get a 503 instead of reaching the service
+//! reset_rate = 2        # 2% of connections are closed without a response
+//! bandwidth = 20_000    # responses trickle out at 20 KB/s
+//! ```
+//!
+//! `locald proxy fault on|off` overrides these at runtime.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Faults the proxy injects into requests to a service.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct FaultConfig {
+    /// Whether the faults are injected when the service is loaded. Defaults to true;
+    /// set it to false to keep rules around for `locald proxy fault on`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Delay added before each request is forwarded, in milliseconds.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// A random extra delay of up to this many milliseconds.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Percentage (0-100) of requests answered with `error_status` instead of being
+    /// forwarded.
+    #[serde(default)]
+    pub error_rate: u8,
+    /// The status of injected errors. Defaults to 503.
+    #[serde(default = "default_error_status")]
+    pub error_status: u16,
+    /// Percentage (0-100) of requests whose connection is closed without a response.
+    #[serde(default)]
+    pub reset_rate: u8,
+    /// Limits response bodies to this many bytes per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<u64>,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            latency_ms: 0,
+            jitter_ms: 0,
+            error_rate: 0,
+            error_status: default_error_status(),
+            reset_rate: 0,
+            bandwidth: None,
+        }
+    }
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+const fn default_error_status() -> u16 {
+    503
+}
+
+impl FaultConfig {
+    /// Checks that the percentages and status code are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("error_rate", self.error_rate),
+            ("reset_rate", self.reset_rate),
+        ] {
+            if rate > 100 {
+                return Err(format!(
+                    "{name} is a percentage and must be 0-100, not {rate}"
+                ));
+            }
+        }
+        if !(400..=599).contains(&self.error_status) {
+            return Err(format!(
+                "error_status must be an HTTP error status (400-599), not {}",
+                self.error_status
+            ));
+        }
+        if self.bandwidth == Some(0) {
+            return Err("bandwidth must be at least 1 byte per second".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether any fault would be injected.
+    #[must_use]
+    pub const fn is_noop(&self) -> bool {
+        self.latency_ms == 0
+            && self.jitter_ms == 0
+            && self.error_rate == 0
+            && self.reset_rate == 0
+            && self.bandwidth.is_none()
+    }
+}
+
+/// The faults in effect for a service, as reported by `locald proxy fault list`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ServiceFaults {
+    /// The service (e.g., "project:api").
+    pub service: String,
+    /// Whether the rules are currently being injected.
+    pub active: bool,
+    /// Whether the state was changed at runtime rather than coming from `locald.toml`.
+    pub overridden: bool,
+    /// The rules.
+    pub rules: FaultConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_config_validation() {
+        let Ok(config) = toml::from_str::<FaultConfig>("latency_ms = 300\nerror_rate = 10") else {
+            unreachable!("faults should parse");
+        };
+        assert!(config.enabled);
+        assert_eq!(config.error_status, 503);
+        assert!(config.validate().is_ok());
+        assert!(!config.is_noop());
+
+        let too_many = FaultConfig {
+            reset_rate: 150,
+            ..FaultConfig::default()
+        };
+        assert!(too_many.validate().is_err());
+
+        let not_an_error = FaultConfig {
+            error_status: 200,
+            ..FaultConfig::default()
+        };
+        assert!(not_an_error.validate().is_err());
+        assert!(FaultConfig::default().is_noop());
+    }
+}
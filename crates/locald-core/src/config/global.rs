@@ -1,10 +1,20 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema, PartialEq, Eq)]
 pub struct GlobalConfig {
     #[serde(default)]
     pub server: ServerConfig,
+
+    #[serde(default)]
+    pub logs: LogsConfig,
+
+    #[serde(default)]
+    pub dns: DnsConfig,
+
+    #[serde(default)]
+    pub acme: AcmeConfig,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -18,6 +28,15 @@ pub struct ServerConfig {
     /// Defaults to true to ensure it works out of the box.
     #[serde(default = "default_true")]
     pub fallback_ports: bool,
+
+    /// Maximum number of services started at the same time within a dependency layer.
+    /// Services in the same layer don't depend on each other, so they can boot in parallel.
+    #[serde(default = "default_startup_concurrency")]
+    pub startup_concurrency: usize,
+}
+
+impl ServerConfig {
+    pub const DEFAULT_STARTUP_CONCURRENCY: usize = 4;
 }
 
 impl Default for ServerConfig {
@@ -25,10 +44,115 @@ impl Default for ServerConfig {
         Self {
             privileged_ports: true,
             fallback_ports: true,
+            startup_concurrency: Self::DEFAULT_STARTUP_CONCURRENCY,
+        }
+    }
+}
+
+/// Limits for the on-disk log store.
+///
+/// Each service's logs are kept in rotating segments; the oldest segments are
+/// deleted first once either limit is exceeded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct LogsConfig {
+    /// Maximum size of a single service's logs, in megabytes.
+    #[serde(default = "default_log_max_size_mb")]
+    pub max_size_mb: u64,
+
+    /// Logs older than this many days are deleted.
+    #[serde(default = "default_log_max_age_days")]
+    pub max_age_days: u64,
+}
+
+impl LogsConfig {
+    pub const DEFAULT_MAX_SIZE_MB: u64 = 50;
+    pub const DEFAULT_MAX_AGE_DAYS: u64 = 7;
+
+    #[must_use]
+    pub const fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+
+    #[must_use]
+    pub const fn max_age(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.max_age_days * 24 * 60 * 60)
+    }
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: Self::DEFAULT_MAX_SIZE_MB,
+            max_age_days: Self::DEFAULT_MAX_AGE_DAYS,
         }
     }
 }
 
+/// The built-in DNS server for project domains.
+///
+/// It answers for `*.localhost`, each project's `domain` and every subdomain of those
+/// with the loopback address, and forwards all other lookups upstream. Unlike the
+/// `/etc/hosts` section it handles wildcards and needs no sync when projects change,
+/// but the system resolver has to be pointed at it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DnsConfig {
+    /// Whether to run the DNS server.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The UDP port to listen on, on `127.0.0.1`.
+    #[serde(default = "default_dns_port")]
+    pub port: u16,
+
+    /// Where to forward lookups for other domains.
+    /// Defaults to the first nameserver in `/etc/resolv.conf`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<SocketAddr>,
+}
+
+impl DnsConfig {
+    pub const DEFAULT_PORT: u16 = 5354;
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: Self::DEFAULT_PORT,
+            upstream: None,
+        }
+    }
+}
+
+/// The local ACME server used by tools like `certbot` or Caddy.
+///
+/// It always issues certificates for `localhost` and `*.localhost`. Any other name
+/// has to be listed here, since the local CA is trusted by the system and a
+/// certificate for a real domain would let its holder impersonate that site.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct AcmeConfig {
+    /// Extra domains to issue certificates for, along with their subdomains.
+    /// Single labels and public suffixes (`com`, `co.uk`) are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+}
+
 const fn default_true() -> bool {
     true
 }
+
+const fn default_startup_concurrency() -> usize {
+    ServerConfig::DEFAULT_STARTUP_CONCURRENCY
+}
+
+const fn default_log_max_size_mb() -> u64 {
+    LogsConfig::DEFAULT_MAX_SIZE_MB
+}
+
+const fn default_log_max_age_days() -> u64 {
+    LogsConfig::DEFAULT_MAX_AGE_DAYS
+}
+
+const fn default_dns_port() -> u16 {
+    DnsConfig::DEFAULT_PORT
+}
@@ -0,0 +1,244 @@
+//! Header rules and CORS policies the proxy applies to a service's traffic.
+//!
+//! ```toml
+//! [services.api.headers]
+//! forwarded = true                       # X-Forwarded-For, -Host and -Proto
+//! request_id = true                      # X-Request-Id, unless the client sent one
+//! request.set = { "X-User" = "dev" }
+//! response.remove = ["Server"]
+//!
+//! [services.api.cors]
+//! origins = ["https://web.shop.localhost"]
+//! credentials = true
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Headers the proxy adds, replaces or removes on the way to and from a service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct HeaderRules {
+    /// Add `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto` to requests.
+    #[serde(default)]
+    pub forwarded: bool,
+    /// Give each request an `X-Request-Id`, unless it already has one. The response
+    /// carries the same id.
+    #[serde(default)]
+    pub request_id: bool,
+    /// Changes to requests before they reach the service.
+    #[serde(default, skip_serializing_if = "HeaderChanges::is_empty")]
+    pub request: HeaderChanges,
+    /// Changes to the service's responses.
+    #[serde(default, skip_serializing_if = "HeaderChanges::is_empty")]
+    pub response: HeaderChanges,
+}
+
+/// Headers to set or remove.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct HeaderChanges {
+    /// Headers to set, replacing any existing values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, String>,
+    /// Headers to remove. Removal happens before `set`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+impl HeaderChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty()
+    }
+
+    fn validate(&self, section: &str) -> Result<(), String> {
+        for name in self.set.keys().chain(&self.remove) {
+            validate_name(name).map_err(|e| format!("{section}: {e}"))?;
+        }
+        for (name, value) in &self.set {
+            if value.chars().any(|c| c.is_control() && c != '\t') {
+                return Err(format!(
+                    "{section}: the value of {name} can't contain control characters"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HeaderRules {
+    /// Checks that header names and values can be sent over HTTP.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        self.request.validate("request")?;
+        self.response.validate("response")
+    }
+}
+
+/// Answers CORS preflights and adds `Access-Control-*` headers to responses, so a
+/// service doesn't need its own dev-only CORS handling.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CorsConfig {
+    /// Origins allowed to call the service (e.g. `https://web.shop.localhost`).
+    /// `*` allows any origin.
+    #[serde(default = "default_origins")]
+    pub origins: Vec<String>,
+    /// Methods allowed in cross-origin requests.
+    #[serde(default = "default_methods")]
+    pub methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests. If empty, whatever the
+    /// browser asks for is allowed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<String>,
+    /// Response headers scripts may read, beyond the CORS-safelisted ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    /// Allow cookies and other credentials. The allowed origin is then echoed back
+    /// rather than answered with `*`.
+    #[serde(default)]
+    pub credentials: bool,
+    /// How long browsers may cache a preflight answer, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            origins: default_origins(),
+            methods: default_methods(),
+            headers: Vec::new(),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+fn default_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+impl CorsConfig {
+    /// Whether requests from `origin` are allowed.
+    #[must_use]
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// Checks that origins look like origins and header names are valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for origin in &self.origins {
+            let host = origin
+                .strip_prefix("http://")
+                .or_else(|| origin.strip_prefix("https://"));
+            let valid = origin == "*"
+                || host
+                    .is_some_and(|host| !host.is_empty() && !host.contains(['/', '?', '#', ' ']));
+            if !valid {
+                return Err(format!(
+                    "origin '{origin}' should be '*' or a scheme and host (e.g. https://web.localhost)"
+                ));
+            }
+        }
+        for method in &self.methods {
+            if method.is_empty() || !method.bytes().all(|b| b.is_ascii_alphabetic()) {
+                return Err(format!("'{method}' is not an HTTP method"));
+            }
+        }
+        for name in self.headers.iter().chain(&self.expose_headers) {
+            validate_name(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `name` is a valid HTTP header name.
+fn validate_name(name: &str) -> Result<(), String> {
+    const SEPARATORS: &[u8] = b"\"(),/:;<=>?@[\\]{}";
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !SEPARATORS.contains(&b));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{name}' is not a valid header name"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_rules_validation() {
+        let Ok(rules) = toml::from_str::<HeaderRules>(
+            r#"
+forwarded = true
+request.set = { "X-User" = "dev" }
+response.remove = ["Server"]
+"#,
+        ) else {
+            unreachable!("header rules should parse");
+        };
+        assert!(rules.forwarded);
+        assert!(!rules.request_id);
+        assert!(rules.validate().is_ok());
+
+        let bad_name = HeaderRules {
+            request: HeaderChanges {
+                remove: vec!["X Bad".to_string()],
+                ..HeaderChanges::default()
+            },
+            ..HeaderRules::default()
+        };
+        assert!(bad_name.validate().is_err());
+
+        let bad_value = HeaderRules {
+            response: HeaderChanges {
+                set: BTreeMap::from([("X-Note".to_string(), "a\r\nb".to_string())]),
+                ..HeaderChanges::default()
+            },
+            ..HeaderRules::default()
+        };
+        assert!(bad_value.validate().is_err());
+    }
+
+    #[test]
+    fn test_cors_origins() {
+        let any = CorsConfig::default();
+        assert!(any.validate().is_ok());
+        assert!(any.allows_origin("http://web.localhost"));
+
+        let Ok(listed) = toml::from_str::<CorsConfig>(r#"origins = ["https://web.localhost"]"#)
+        else {
+            unreachable!("cors config should parse");
+        };
+        assert!(listed.validate().is_ok());
+        assert!(listed.allows_origin("https://web.localhost"));
+        assert!(!listed.allows_origin("https://evil.example"));
+
+        let with_path = CorsConfig {
+            origins: vec!["https://web.localhost/app".to_string()],
+            ..CorsConfig::default()
+        };
+        assert!(with_path.validate().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! `${...}` references in service `env` values, `command`, `workdir` and health checks.
+//!
+//! ```toml
+//! [services.api]
+//! command = "api --db ${services.db.url} --root ${env.HOME}/data"
+//! env = { PUBLIC_URL = "https://${project.domain}", LOG = "${env.LOG_LEVEL:-info}" }
+//! ```
+//!
+//! - `${services.NAME.FIELD}`: a field of a service in the project (see
+//!   [`SERVICE_FIELDS`]). A Postgres service also has `user`, `password`, `database`,
+//!   and `databases.DB.url` and `databases.DB.name` for each of its databases.
+//! - `${projects.PROJECT.services.NAME.FIELD}`: the same, for a service of another
+//!   project in the same workspace.
+//! - `${project.FIELD}`: the project's `name`, `domain` or `workspace`.
+//! - `${env.NAME}`: a variable from the environment locald runs in. `${env.NAME:-default}`
+//!   falls back to `default` if the variable is unset or empty.
+//!
+//! Anything else in `${...}` without a dot (`${HOME}`) is left alone for the shell.
+
+/// A reference to a value, resolved when a service starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    /// A field of a running service. `project` is `None` for the same project.
+    Service {
+        project: Option<String>,
+        service: String,
+        field: String,
+    },
+    /// A field of the project (`name`, `domain` or `workspace`).
+    Project(String),
+    /// A variable from locald's environment.
+    Env {
+        name: String,
+        default: Option<String>,
+    },
+}
+
+/// A piece of a template: literal text or a reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    Reference(Reference),
+}
+
+const PROJECT_FIELDS: &[&str] = &["name", "domain", "workspace"];
+
+/// The fields every service has.
+///
+/// - `port`, `host`: where the service listens.
+/// - `url`: its direct address, with a scheme for its protocol (`postgres://`,
+///   `redis://`, otherwise `http://`).
+/// - `domain`: the domain the proxy serves it on (e.g. `api.shop.localhost`).
+/// - `public_url`: its URL through the proxy, over HTTPS unless only HTTP is served.
+/// - `https_url`: its URL through the proxy over HTTPS.
+/// - `internal_url`: `http://localhost:PORT`, bypassing the proxy.
+pub const SERVICE_FIELDS: &[&str] = &[
+    "port",
+    "host",
+    "url",
+    "domain",
+    "public_url",
+    "https_url",
+    "internal_url",
+];
+
+/// The fields only Postgres services have, besides `databases.DB.*`.
+pub const POSTGRES_FIELDS: &[&str] = &["user", "password", "database"];
+
+/// Splits `template` into text and references.
+///
+/// # Errors
+///
+/// Returns a description of the first reference that isn't understood.
+pub fn parse(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("${") {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            text.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let body = &after[..end];
+        let whole = &rest[start..start + end + 3];
+        rest = &after[end + 1..];
+
+        // `${NAME}` and `${NAME:-default}` without a dot belong to the shell
+        let name_len = body
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(body.len());
+        if !body[name_len..].starts_with('.') {
+            text.push_str(whole);
+            continue;
+        }
+
+        let reference = parse_reference(body).map_err(|e| format!("{whole}: {e}"))?;
+        if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        segments.push(Segment::Reference(reference));
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+fn parse_reference(body: &str) -> Result<Reference, String> {
+    let (namespace, rest) = body.split_once('.').unwrap_or((body, ""));
+    match namespace {
+        "env" => {
+            let (name, default) = match rest.split_once(":-") {
+                Some((name, default)) => (name, Some(default.to_string())),
+                None => (rest, None),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("'{name}' is not an environment variable name"));
+            }
+            Ok(Reference::Env {
+                name: name.to_string(),
+                default,
+            })
+        }
+        "project" => {
+            if PROJECT_FIELDS.contains(&rest) {
+                Ok(Reference::Project(rest.to_string()))
+            } else {
+                Err(format!(
+                    "unknown project field '{rest}' (expected {})",
+                    PROJECT_FIELDS.join(", ")
+                ))
+            }
+        }
+        "services" => parse_service(None, rest),
+        "projects" => {
+            let (project, rest) = rest.split_once('.').unwrap_or((rest, ""));
+            let Some(rest) = rest.strip_prefix("services.") else {
+                return Err("expected projects.PROJECT.services.NAME.FIELD".to_string());
+            };
+            if project.is_empty() {
+                return Err("missing project name".to_string());
+            }
+            parse_service(Some(project), rest)
+        }
+        _ => Err(format!(
+            "unknown reference '{namespace}' (expected services, projects, project or env)"
+        )),
+    }
+}
+
+fn parse_service(project: Option<&str>, rest: &str) -> Result<Reference, String> {
+    let Some((service, field)) = rest
+        .split_once('.')
+        .filter(|(s, f)| !s.is_empty() && !f.is_empty())
+    else {
+        return Err("expected a service name and field, such as services.db.url".to_string());
+    };
+    let known = SERVICE_FIELDS.contains(&field)
+        || POSTGRES_FIELDS.contains(&field)
+        || field
+            .strip_prefix("databases.")
+            .and_then(|database| database.rsplit_once('.'))
+            .is_some_and(|(db, f)| !db.is_empty() && matches!(f, "url" | "name"));
+    if !known {
+        return Err(format!(
+            "unknown service field '{field}' (expected one of {}, or {} for Postgres)",
+            SERVICE_FIELDS.join(", "),
+            POSTGRES_FIELDS.join(", ")
+        ));
+    }
+    Ok(Reference::Service {
+        project: project.map(str::to_string),
+        service: service.to_string(),
+        field: field.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_references() {
+        let Ok(segments) = parse("postgres://${services.db.host}:${services.db.port}/app") else {
+            unreachable!("the template is valid");
+        };
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0], Segment::Text("postgres://".to_string()));
+        assert_eq!(segments[2], Segment::Text(":".to_string()));
+        assert_eq!(
+            segments[4],
+            Segment::Text("/app".to_string()),
+            "text after the last reference is kept"
+        );
+
+        let Ok(segments) = parse("${projects.auth.services.api.url} ${env.LEVEL:-info}") else {
+            unreachable!("the template is valid");
+        };
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Reference(Reference::Service {
+                    project: Some("auth".to_string()),
+                    service: "api".to_string(),
+                    field: "url".to_string(),
+                }),
+                Segment::Text(" ".to_string()),
+                Segment::Reference(Reference::Env {
+                    name: "LEVEL".to_string(),
+                    default: Some("info".to_string()),
+                }),
+            ]
+        );
+
+        // Shell variables are left alone
+        assert_eq!(
+            parse("echo ${HOME} ${PORT:-3000}"),
+            Ok(vec![Segment::Text(
+                "echo ${HOME} ${PORT:-3000}".to_string()
+            )])
+        );
+
+        assert!(parse("${services.web.public_url}").is_ok());
+        assert!(parse("${services.db.password}").is_ok());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let Err(e) = parse("${services.db.prot}") else {
+            unreachable!("prot is not a field");
+        };
+        assert!(
+            e.starts_with("${services.db.prot}: unknown service field 'prot'"),
+            "{e}"
+        );
+        assert!(parse("${project.owner}").is_err());
+        assert!(parse("${serivces.db.url}").is_err());
+        assert!(parse("${services.db}").is_err());
+        assert!(parse("${projects.auth.api.url}").is_err());
+        assert!(parse("${env.NOT-A-NAME}").is_err());
+    }
+}
@@ -1,5 +1,21 @@
 pub mod global;
-pub use global::GlobalConfig;
+pub use global::{AcmeConfig, DnsConfig, GlobalConfig, LogsConfig, ServerConfig};
+
+pub mod domains;
+
+pub mod faults;
+pub use faults::{FaultConfig, ServiceFaults};
+
+pub mod headers;
+pub use headers::{CorsConfig, HeaderChanges, HeaderRules};
+
+pub mod interpolate;
+
+pub mod profiles;
+pub use profiles::ProfileConfig;
+
+pub mod routes;
+pub use routes::{RouteMatch, RouteTarget};
 
 pub mod env_provenance;
 pub use env_provenance::{
@@ -14,7 +30,7 @@ pub use env_provenance::{
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Root configuration for a locald project.
 ///
@@ -33,6 +49,12 @@ pub struct LocaldConfig {
     /// Service definitions for the project.
     #[serde(default)]
     pub services: HashMap<String, ServiceConfig>,
+    /// Path-based routes on the project's domain (e.g. `"/api/*" = "api"`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub routes: BTreeMap<String, RouteTarget>,
+    /// Named profiles selected with `locald up --profile <name>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 /// Configuration specific to the project identity.
@@ -89,8 +111,12 @@ pub enum TypedServiceConfig {
     Exec(ExecServiceConfig),
     /// A managed Postgres database service.
     Postgres(PostgresServiceConfig),
+    /// A managed Redis service.
+    Redis(RedisServiceConfig),
     /// A background worker service.
     Worker(WorkerServiceConfig),
+    /// A one-shot command that runs to completion, such as a migration.
+    Task(TaskServiceConfig),
     /// A container-based service.
     Container(ContainerServiceConfig),
     /// A managed site service.
@@ -144,6 +170,56 @@ pub struct WorkerServiceConfig {
     /// Working directory for the command. Defaults to the project root.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workdir: Option<String>,
+    /// Restart the worker when files in the project change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchConfig>,
+}
+
+/// Configuration for a one-shot task, such as a migration or seed script.
+///
+/// A task runs to completion before its dependents start. It is only run again when
+/// its configuration or one of its `inputs` changes.
+///
+/// # Example
+/// ```toml
+/// [services.migrate]
+/// type = "task"
+/// command = "bin/rails db:migrate"
+/// inputs = ["db/migrate/**"]
+/// depends_on = ["db"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TaskServiceConfig {
+    /// Common configuration shared by all services.
+    #[serde(flatten)]
+    pub common: CommonServiceConfig,
+
+    /// The command to run.
+    pub command: String,
+    /// Working directory for the command. Defaults to the project root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<String>,
+    /// Glob patterns (relative to the working directory) for files the task reads.
+    /// The task is run again when any matching file changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// How long the task may run, in seconds. Defaults to 600.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl TaskServiceConfig {
+    /// The default time a task may run, in seconds.
+    pub const DEFAULT_TIMEOUT: u64 = 600;
+
+    #[must_use]
+    pub const fn timeout(&self) -> std::time::Duration {
+        let secs = match self.timeout {
+            Some(secs) => secs,
+            None => Self::DEFAULT_TIMEOUT,
+        };
+        std::time::Duration::from_secs(secs)
+    }
 }
 
 /// Common configuration fields shared by all service types.
@@ -162,9 +238,9 @@ pub struct CommonServiceConfig {
     /// Environment variables to pass to the service.
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub env: HashMap<String, String>,
-    /// List of services that must be started before this one.
+    /// List of services that must be ready before this one is started.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub depends_on: Vec<String>,
+    pub depends_on: Vec<Dependency>,
     /// Optional command to run to check if the service is healthy.
     /// If not provided, locald will attempt to infer a health check (Docker, Notify, or TCP).
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -172,6 +248,300 @@ pub struct CommonServiceConfig {
     /// The signal to send to stop the service. Defaults to "SIGTERM".
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stop_signal: Option<String>,
+    /// What to do when the service exits on its own. Defaults to "never".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartConfig>,
+    /// Stable ports that forward to the service, whatever port it is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expose: Option<ExposeConfig>,
+    /// Extra domains that reach the service. A leading `*.` matches any subdomain
+    /// one level down (e.g. `*.myapp.localhost`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// Latency, errors, resets and bandwidth limits the proxy injects for testing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faults: Option<FaultConfig>,
+    /// Headers the proxy sets or removes on requests and responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HeaderRules>,
+    /// CORS preflights and headers the proxy handles on the service's behalf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    /// The protocol the service speaks on its port. Defaults to "http" (HTTP/1.1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<Protocol>,
+    /// Issue the service a certificate for its domains and pass its paths in
+    /// `LOCALD_TLS_CERT`, `LOCALD_TLS_KEY` and `LOCALD_TLS_CA`, for services that
+    /// terminate TLS themselves.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tls: bool,
+}
+
+/// A dependency on another service in the same project.
+///
+/// The short form waits for the dependency to become healthy.
+///
+/// # Example
+/// ```toml
+/// depends_on = ["cache", { service = "db", condition = "healthy", timeout = 60 }]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Dependency {
+    /// The name of the service to wait for.
+    Service(String),
+    /// A dependency with an explicit readiness condition.
+    Detailed(DependencyConfig),
+}
+
+/// A dependency with an explicit readiness condition.
+///
+/// # Example
+/// ```toml
+/// { service = "migrate", condition = "completed" }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DependencyConfig {
+    /// The name of the service to wait for.
+    pub service: String,
+    /// What the dependency must reach before this service is started. Defaults to "healthy".
+    #[serde(default)]
+    pub condition: DependencyCondition,
+    /// How long to wait for the condition, in seconds. Defaults to 30.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// The state a dependency must reach before its dependents are started.
+///
+/// # Example
+/// ```toml
+/// condition = "started"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyCondition {
+    /// The dependency's process has been started.
+    Started,
+    /// The dependency has passed its health check.
+    #[default]
+    Healthy,
+    /// The dependency has run to completion and exited successfully.
+    Completed,
+}
+
+impl std::fmt::Display for DependencyCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Started => write!(f, "started"),
+            Self::Healthy => write!(f, "healthy"),
+            Self::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+impl Dependency {
+    /// The default time to wait for a dependency, in seconds.
+    pub const DEFAULT_TIMEOUT: u64 = 30;
+
+    #[must_use]
+    pub fn service(&self) -> &str {
+        match self {
+            Self::Service(name) => name,
+            Self::Detailed(config) => &config.service,
+        }
+    }
+
+    #[must_use]
+    pub const fn condition(&self) -> DependencyCondition {
+        match self {
+            Self::Service(_) => DependencyCondition::Healthy,
+            Self::Detailed(config) => config.condition,
+        }
+    }
+
+    #[must_use]
+    pub const fn timeout(&self) -> std::time::Duration {
+        let secs = match self {
+            Self::Detailed(DependencyConfig {
+                timeout: Some(secs),
+                ..
+            }) => *secs,
+            Self::Service(_) | Self::Detailed(_) => Self::DEFAULT_TIMEOUT,
+        };
+        std::time::Duration::from_secs(secs)
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(name: &str) -> Self {
+        Self::Service(name.to_string())
+    }
+}
+
+/// Configuration for restarting a service that exits on its own.
+///
+/// # Example
+/// ```toml
+/// restart = "on-failure"
+/// # OR
+/// restart = { mode = "always", max_retries = 10, backoff = 2, max_backoff = 60 }
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RestartConfig {
+    /// Just the restart mode, with default retry and backoff settings.
+    Mode(RestartMode),
+    /// A full restart policy.
+    Policy(RestartPolicy),
+}
+
+/// When a service should be restarted after it exits.
+///
+/// # Example
+/// ```toml
+/// restart = "on-failure"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Never restart the service.
+    #[default]
+    Never,
+    /// Restart the service only if it exits with a non-zero status.
+    OnFailure,
+    /// Restart the service whenever it exits.
+    Always,
+}
+
+/// A restart policy with retry limits and exponential backoff.
+///
+/// # Example
+/// ```toml
+/// restart = { mode = "on-failure", max_retries = 5, backoff = 1 }
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// When to restart the service.
+    #[serde(default)]
+    pub mode: RestartMode,
+    /// How many consecutive restarts to attempt before giving up. Defaults to 5.
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// The delay before the first restart in seconds. Doubles after every consecutive crash. Defaults to 1.
+    #[serde(default)]
+    pub backoff: Option<u64>,
+    /// The maximum delay between restarts in seconds. Defaults to 60.
+    #[serde(default)]
+    pub max_backoff: Option<u64>,
+}
+
+impl RestartConfig {
+    /// The default number of consecutive restarts before giving up.
+    pub const DEFAULT_MAX_RETRIES: u32 = 5;
+    /// The default delay before the first restart, in seconds.
+    pub const DEFAULT_BACKOFF: u64 = 1;
+    /// The default upper bound on the restart delay, in seconds.
+    pub const DEFAULT_MAX_BACKOFF: u64 = 60;
+
+    #[must_use]
+    pub const fn mode(&self) -> RestartMode {
+        match self {
+            Self::Mode(mode) => *mode,
+            Self::Policy(policy) => policy.mode,
+        }
+    }
+
+    #[must_use]
+    pub const fn max_retries(&self) -> u32 {
+        match self {
+            Self::Policy(RestartPolicy {
+                max_retries: Some(n),
+                ..
+            }) => *n,
+            Self::Mode(_) | Self::Policy(_) => Self::DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// Whether a service that exited with `exit_code` should be restarted.
+    ///
+    /// An unknown exit code (e.g. the process was killed by a signal) counts as a failure.
+    #[must_use]
+    pub const fn should_restart(&self, exit_code: Option<i32>) -> bool {
+        match self.mode() {
+            RestartMode::Never => false,
+            RestartMode::OnFailure => !matches!(exit_code, Some(0)),
+            RestartMode::Always => true,
+        }
+    }
+
+    /// The delay before restart number `attempt` (starting at 0).
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let (initial, max) = match self {
+            Self::Mode(_) => (Self::DEFAULT_BACKOFF, Self::DEFAULT_MAX_BACKOFF),
+            Self::Policy(policy) => (
+                policy.backoff.unwrap_or(Self::DEFAULT_BACKOFF),
+                policy.max_backoff.unwrap_or(Self::DEFAULT_MAX_BACKOFF),
+            ),
+        };
+        let delay = initial.saturating_mul(2u64.saturating_pow(attempt));
+        std::time::Duration::from_secs(delay.min(max))
+    }
+}
+
+/// Stable ports the proxy listens on and forwards to the service's current port.
+///
+/// Clients such as database GUIs can then keep one address across restarts.
+///
+/// # Example
+/// ```toml
+/// expose = { tcp = 5432 }
+/// ```
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ExposeConfig {
+    /// A TCP port to forward.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp: Option<u16>,
+    /// A UDP port to forward.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp: Option<u16>,
+}
+
+/// The protocol the proxy uses to talk to a service.
+///
+/// # Example
+/// ```toml
+/// protocol = "grpc"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// HTTP/1.1.
+    #[default]
+    Http,
+    /// HTTP/2 without TLS ("prior knowledge").
+    H2c,
+    /// gRPC, which is HTTP/2 with trailers and streaming bodies.
+    Grpc,
+}
+
+impl Protocol {
+    /// Whether the service is reached over HTTP/2.
+    #[must_use]
+    pub const fn is_http2(self) -> bool {
+        matches!(self, Self::H2c | Self::Grpc)
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http => write!(f, "http"),
+            Self::H2c => write!(f, "h2c"),
+            Self::Grpc => write!(f, "grpc"),
+        }
+    }
 }
 
 /// Configuration for service health checks.
@@ -262,6 +632,61 @@ pub struct ExecServiceConfig {
     /// Configuration for building the service using CNB.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub build: Option<BuildConfig>,
+    /// Restart the service when files in the project change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchConfig>,
+}
+
+/// Configuration for restarting a service when its source files change.
+///
+/// Patterns are globs relative to the service's working directory.
+///
+/// # Example
+/// ```toml
+/// [services.api.watch]
+/// include = ["src/**/*.rs", "Cargo.toml"]
+/// exclude = ["**/*.snap"]
+/// debounce = 300
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct WatchConfig {
+    /// Globs of files to watch. If empty, every file under the working directory is watched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs of files to ignore, even if they match `include`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// How long to wait for changes to settle before restarting, in milliseconds. Defaults to 500.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce: Option<u64>,
+    /// Whether to skip paths ignored by `.gitignore`. Defaults to true.
+    #[serde(default = "default_true")]
+    pub gitignore: bool,
+}
+
+impl WatchConfig {
+    /// The default debounce interval, in milliseconds.
+    pub const DEFAULT_DEBOUNCE: u64 = 500;
+
+    #[must_use]
+    pub fn debounce(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.debounce.unwrap_or(Self::DEFAULT_DEBOUNCE))
+    }
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            debounce: None,
+            gitignore: true,
+        }
+    }
+}
+
+const fn default_true() -> bool {
+    true
 }
 
 /// Configuration for building a service using Cloud Native Buildpacks.
@@ -292,6 +717,10 @@ fn default_builder() -> String {
 /// ```toml
 /// type = "postgres"
 /// version = "15"
+/// databases = ["app", "analytics"]
+/// extensions = ["pgcrypto", "uuid-ossp"]
+/// init_scripts = ["db/schema.sql"]
+/// settings = { max_connections = 200 }
 /// ```
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct PostgresServiceConfig {
@@ -302,6 +731,78 @@ pub struct PostgresServiceConfig {
     /// The version of Postgres to use. Defaults to stable.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+
+    /// Databases to create if they don't exist. The first one is the default database
+    /// (the one `url` points to); without any, that's `postgres`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<String>,
+
+    /// Extensions to enable in each database.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+
+    /// SQL files (relative to the project root) to run against the default database
+    /// when the cluster is first created.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub init_scripts: Vec<String>,
+
+    /// Server settings passed to `postgres` on every start (e.g. `max_connections`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, PostgresSetting>,
+}
+
+impl PostgresServiceConfig {
+    /// The database that `url` points to.
+    #[must_use]
+    pub fn default_database(&self) -> &str {
+        self.databases.first().map_or("postgres", String::as_str)
+    }
+}
+
+/// The value of a Postgres server setting.
+///
+/// # Example
+/// ```toml
+/// settings = { max_connections = 200, shared_buffers = "256MB", fsync = false }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PostgresSetting {
+    /// A boolean setting (`on`/`off`).
+    Bool(bool),
+    /// A numeric setting.
+    Integer(i64),
+    /// Any other setting, passed through as-is (e.g. `"256MB"`).
+    String(String),
+}
+
+impl std::fmt::Display for PostgresSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(true) => f.write_str("on"),
+            Self::Bool(false) => f.write_str("off"),
+            Self::Integer(n) => write!(f, "{n}"),
+            Self::String(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Configuration for a managed Redis service.
+///
+/// # Example
+/// ```toml
+/// type = "redis"
+/// version = "7.4.2"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct RedisServiceConfig {
+    /// Common configuration shared by all services.
+    #[serde(flatten)]
+    pub common: CommonServiceConfig,
+
+    /// The version of Redis to use. Defaults to 7.4.2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 /// Configuration for a managed site service.
@@ -334,7 +835,9 @@ impl ServiceConfig {
         match self {
             Self::Typed(TypedServiceConfig::Exec(c)) | Self::Legacy(c) => &c.common,
             Self::Typed(TypedServiceConfig::Postgres(c)) => &c.common,
+            Self::Typed(TypedServiceConfig::Redis(c)) => &c.common,
             Self::Typed(TypedServiceConfig::Worker(c)) => &c.common,
+            Self::Typed(TypedServiceConfig::Task(c)) => &c.common,
             Self::Typed(TypedServiceConfig::Container(c)) => &c.common,
             Self::Typed(TypedServiceConfig::Site(c)) => &c.common,
         }
@@ -348,13 +851,87 @@ impl ServiceConfig {
         &self.common().env
     }
 
-    pub const fn depends_on(&self) -> &Vec<String> {
+    pub const fn depends_on(&self) -> &Vec<Dependency> {
         &self.common().depends_on
     }
 
     pub const fn health_check(&self) -> Option<&HealthCheckConfig> {
         self.common().health_check.as_ref()
     }
+
+    pub const fn restart(&self) -> Option<&RestartConfig> {
+        self.common().restart.as_ref()
+    }
+
+    /// The options besides `env` that may contain `${...}` references, by key.
+    pub fn interpolated_fields_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        let (command, workdir, common) = match self {
+            Self::Typed(TypedServiceConfig::Exec(c)) | Self::Legacy(c) => {
+                (c.command.as_mut(), c.workdir.as_mut(), &mut c.common)
+            }
+            Self::Typed(TypedServiceConfig::Worker(c)) => {
+                (Some(&mut c.command), c.workdir.as_mut(), &mut c.common)
+            }
+            Self::Typed(TypedServiceConfig::Task(c)) => {
+                (Some(&mut c.command), c.workdir.as_mut(), &mut c.common)
+            }
+            Self::Typed(TypedServiceConfig::Container(c)) => {
+                (c.command.as_mut(), c.workdir.as_mut(), &mut c.common)
+            }
+            Self::Typed(TypedServiceConfig::Postgres(c)) => (None, None, &mut c.common),
+            Self::Typed(TypedServiceConfig::Redis(c)) => (None, None, &mut c.common),
+            Self::Typed(TypedServiceConfig::Site(c)) => (None, None, &mut c.common),
+        };
+
+        let mut fields = Vec::new();
+        fields.extend(command.map(|command| ("command", command)));
+        fields.extend(workdir.map(|workdir| ("workdir", workdir)));
+        match &mut common.health_check {
+            Some(HealthCheckConfig::Command(check)) => fields.push(("health_check", check)),
+            Some(HealthCheckConfig::Probe(probe)) => {
+                fields.extend(probe.path.as_mut().map(|path| ("health_check.path", path)));
+                fields.extend(
+                    probe
+                        .command
+                        .as_mut()
+                        .map(|check| ("health_check.command", check)),
+                );
+            }
+            None => {}
+        }
+        fields
+    }
+
+    /// The file watch configuration, for service types that support it.
+    pub const fn watch(&self) -> Option<&WatchConfig> {
+        match self {
+            Self::Typed(TypedServiceConfig::Exec(c)) | Self::Legacy(c) => c.watch.as_ref(),
+            Self::Typed(TypedServiceConfig::Worker(c)) => c.watch.as_ref(),
+            Self::Typed(
+                TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
+                | TypedServiceConfig::Task(_)
+                | TypedServiceConfig::Container(_)
+                | TypedServiceConfig::Site(_),
+            ) => None,
+        }
+    }
+
+    /// The task configuration, if this is a one-shot task.
+    pub const fn task(&self) -> Option<&TaskServiceConfig> {
+        match self {
+            Self::Typed(TypedServiceConfig::Task(c)) => Some(c),
+            Self::Typed(
+                TypedServiceConfig::Exec(_)
+                | TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
+                | TypedServiceConfig::Worker(_)
+                | TypedServiceConfig::Container(_)
+                | TypedServiceConfig::Site(_),
+            )
+            | Self::Legacy(_) => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -370,12 +947,21 @@ mod tests {
                 depends_on: Vec::new(),
                 health_check: None,
                 stop_signal: None,
+                restart: None,
+                expose: None,
+                domains: Vec::new(),
+                faults: None,
+                headers: None,
+                cors: None,
+                protocol: None,
+                tls: false,
             },
             command: Some("echo hello".to_string()),
             workdir: None,
             image: None,
             container_port: None,
             build: None,
+            watch: None,
         });
 
         let config = LocaldConfig {
@@ -386,6 +972,8 @@ mod tests {
                 constellation: None,
             },
             services: HashMap::from([("web".to_string(), service_config)]),
+            routes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
         };
 
         let toml_string = toml::to_string_pretty(&config).unwrap();
@@ -451,6 +1039,141 @@ health_check = "test -f ready"
             panic!("Expected Probe config");
         }
     }
+
+    #[test]
+    fn test_restart_config() {
+        let toml = r#"
+[project]
+name = "restart-test"
+
+[services.web]
+command = "cmd"
+restart = "on-failure"
+
+[services.worker]
+type = "worker"
+command = "cmd"
+restart = { mode = "always", max_retries = 3, backoff = 2, max_backoff = 10 }
+"#;
+        let config: LocaldConfig = toml::from_str(toml).unwrap();
+
+        let web = config.services["web"].restart().unwrap();
+        assert_eq!(web.mode(), RestartMode::OnFailure);
+        assert_eq!(web.max_retries(), RestartConfig::DEFAULT_MAX_RETRIES);
+        assert!(web.should_restart(Some(1)));
+        assert!(web.should_restart(None));
+        assert!(!web.should_restart(Some(0)));
+
+        let worker = config.services["worker"].restart().unwrap();
+        assert_eq!(worker.mode(), RestartMode::Always);
+        assert_eq!(worker.max_retries(), 3);
+        assert!(worker.should_restart(Some(0)));
+        assert_eq!(worker.backoff(0).as_secs(), 2);
+        assert_eq!(worker.backoff(1).as_secs(), 4);
+        assert_eq!(worker.backoff(5).as_secs(), 10);
+    }
+
+    #[test]
+    fn test_depends_on_forms() {
+        let toml = r#"
+[project]
+name = "deps-test"
+
+[services.api]
+command = "cmd"
+depends_on = [
+    "cache",
+    { service = "db", condition = "healthy", timeout = 60 },
+    { service = "migrate", condition = "completed" },
+]
+"#;
+        let config: LocaldConfig = toml::from_str(toml).unwrap();
+        let deps = config.services["api"].depends_on();
+
+        assert_eq!(deps[0].service(), "cache");
+        assert_eq!(deps[0].condition(), DependencyCondition::Healthy);
+        assert_eq!(deps[0].timeout().as_secs(), Dependency::DEFAULT_TIMEOUT);
+
+        assert_eq!(deps[1].service(), "db");
+        assert_eq!(deps[1].timeout().as_secs(), 60);
+
+        assert_eq!(deps[2].service(), "migrate");
+        assert_eq!(deps[2].condition(), DependencyCondition::Completed);
+    }
+
+    #[test]
+    fn test_watch_config() {
+        let toml = r#"
+[project]
+name = "watch-test"
+
+[services.api]
+command = "cargo run"
+watch = { include = ["src/**"], debounce = 200 }
+
+[services.worker]
+type = "worker"
+command = "node worker.js"
+
+[services.worker.watch]
+exclude = ["*.log"]
+gitignore = false
+"#;
+        let config: LocaldConfig = toml::from_str(toml).unwrap();
+
+        let api = config.services["api"].watch().unwrap();
+        assert_eq!(api.include, vec!["src/**".to_string()]);
+        assert_eq!(api.debounce().as_millis(), 200);
+        assert!(api.gitignore);
+
+        let worker = config.services["worker"].watch().unwrap();
+        assert_eq!(worker.exclude, vec!["*.log".to_string()]);
+        assert_eq!(worker.debounce().as_millis(), 500);
+        assert!(!worker.gitignore);
+    }
+
+    #[test]
+    fn test_postgres_provisioning() {
+        let toml = r#"
+[project]
+name = "pg-test"
+
+[services.db]
+type = "postgres"
+databases = ["app", "analytics"]
+extensions = ["pgcrypto"]
+settings = { max_connections = 200, shared_buffers = "256MB", fsync = false }
+"#;
+        let config: LocaldConfig = toml::from_str(toml).unwrap();
+        let ServiceConfig::Typed(TypedServiceConfig::Postgres(pg)) = &config.services["db"] else {
+            panic!("expected a postgres service");
+        };
+
+        assert_eq!(pg.default_database(), "app");
+        let settings: Vec<_> = pg
+            .settings
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        assert_eq!(
+            settings,
+            ["fsync=off", "max_connections=200", "shared_buffers=256MB"]
+        );
+        assert_eq!(
+            PostgresServiceConfig::default().default_database(),
+            "postgres"
+        );
+    }
+}
+
+impl ProjectConfig {
+    /// The project's root domain: `domain`, or `{name}.localhost`.
+    #[must_use]
+    pub fn root_domain(&self) -> String {
+        self.domain
+            .clone()
+            .unwrap_or_else(|| format!("{}.localhost", self.name))
+    }
 }
 
 impl Default for ProjectConfig {
@@ -0,0 +1,240 @@
+//! Named profiles that switch which services run and override their settings.
+//!
+//! ```toml
+//! [profiles.frontend]
+//! services = ["web"]                     # web and whatever it depends on
+//!
+//! [profiles.ci]
+//! disable = ["docs"]
+//!
+//! [profiles.ci.overrides.api]
+//! env = { LOG_LEVEL = "warn" }           # merged into the service's env
+//! restart = "never"                      # everything else replaces the value
+//! ```
+//!
+//! A profile is picked with `locald up --profile <name>`.
+
+use super::{CommonServiceConfig, LocaldConfig, ServiceConfig};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A named variation of the project's services.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ProfileConfig {
+    /// Run only these services, plus the services they depend on. If empty, every
+    /// service runs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    /// Services not to run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// Common service options to change, by service. `env` is merged into the
+    /// service's environment; other options replace the service's value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, Map<String, Value>>,
+}
+
+impl LocaldConfig {
+    /// Applies the profile called `name`: overrides service options, then drops the
+    /// services it leaves out, along with routes pointing at them.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the profile doesn't exist, names an
+    /// unknown service or option, or leaves out a service that a remaining service
+    /// depends on.
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(if known.is_empty() {
+                format!("Unknown profile '{name}': the project defines no profiles")
+            } else {
+                format!("Unknown profile '{name}' (available: {})", known.join(", "))
+            });
+        };
+        let context = |e: String| format!("Profile '{name}': {e}");
+
+        for service in profile
+            .services
+            .iter()
+            .chain(&profile.disable)
+            .chain(profile.overrides.keys())
+        {
+            if !self.services.contains_key(service) {
+                return Err(context(format!("unknown service '{service}'")));
+            }
+        }
+
+        for (service, changes) in &profile.overrides {
+            if let Some(config) = self.services.get_mut(service) {
+                *config = apply_overrides(config, changes)
+                    .map_err(|e| context(format!("service '{service}': {e}")))?;
+            }
+        }
+
+        let kept = self.kept_services(&profile).map_err(context)?;
+        self.services.retain(|service, _| kept.contains(service));
+        self.routes
+            .retain(|_, target| kept.contains(target.service()));
+        Ok(())
+    }
+
+    /// The services `profile` runs: those it lists and their dependencies (or every
+    /// service), minus the disabled ones.
+    fn kept_services(&self, profile: &ProfileConfig) -> Result<BTreeSet<String>, String> {
+        let mut kept = BTreeSet::new();
+        if profile.services.is_empty() {
+            kept.extend(self.services.keys().cloned());
+        } else {
+            let mut pending = profile.services.clone();
+            while let Some(service) = pending.pop() {
+                let Some(config) = self.services.get(&service) else {
+                    continue;
+                };
+                if kept.insert(service) {
+                    pending.extend(config.depends_on().iter().map(|d| d.service().to_string()));
+                }
+            }
+        }
+        for service in &profile.disable {
+            kept.remove(service);
+        }
+
+        for service in &kept {
+            for dependency in self.services[service].depends_on() {
+                let dependency = dependency.service();
+                if self.services.contains_key(dependency) && !kept.contains(dependency) {
+                    return Err(format!(
+                        "service '{service}' depends on '{dependency}', which the profile disables"
+                    ));
+                }
+            }
+        }
+        Ok(kept)
+    }
+}
+
+/// Returns `config` with `changes` to its common options applied.
+fn apply_overrides(
+    config: &ServiceConfig,
+    changes: &Map<String, Value>,
+) -> Result<ServiceConfig, String> {
+    let schema = schemars::schema_for!(CommonServiceConfig);
+    let options = schema.get("properties").and_then(Value::as_object);
+
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(config) else {
+        return Err("can't be overridden".to_string());
+    };
+    for (key, value) in changes {
+        if !options.is_some_and(|options| options.contains_key(key)) {
+            return Err(format!("'{key}' is not a common service option"));
+        }
+        match (fields.get_mut(key), value) {
+            (Some(Value::Object(env)), Value::Object(extra)) if key == "env" => {
+                env.extend(extra.clone());
+            }
+            _ => {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    serde_json::from_value(Value::Object(fields)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[project]
+name = "shop"
+
+[services.db]
+type = "postgres"
+
+[services.api]
+command = "api"
+depends_on = ["db"]
+env = { LOG_LEVEL = "debug", REGION = "local" }
+
+[services.web]
+command = "web"
+depends_on = ["api"]
+
+[services.docs]
+command = "docs"
+
+[routes]
+"/docs/*" = "docs"
+
+[profiles.frontend]
+services = ["web"]
+
+[profiles.ci]
+disable = ["docs"]
+
+[profiles.ci.overrides.api]
+port = 4000
+env = { LOG_LEVEL = "warn" }
+
+[profiles.broken]
+disable = ["db"]
+"#;
+
+    fn config() -> LocaldConfig {
+        let Ok(config) = toml::from_str(CONFIG) else {
+            unreachable!("the config should parse");
+        };
+        config
+    }
+
+    fn names(config: &LocaldConfig) -> BTreeSet<&str> {
+        config.services.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_profile_selects_services() {
+        let mut frontend = config();
+        assert!(frontend.apply_profile("frontend").is_ok());
+        assert_eq!(names(&frontend), BTreeSet::from(["api", "db", "web"]));
+        assert!(frontend.routes.is_empty());
+
+        let mut ci = config();
+        assert!(ci.apply_profile("ci").is_ok());
+        assert_eq!(names(&ci), BTreeSet::from(["api", "db", "web"]));
+        let Some(api) = ci.services.get("api") else {
+            unreachable!("api is kept");
+        };
+        assert_eq!(api.port(), Some(4000));
+        assert_eq!(api.env().get("LOG_LEVEL").map(String::as_str), Some("warn"));
+        assert_eq!(api.env().get("REGION").map(String::as_str), Some("local"));
+        assert!(matches!(api, ServiceConfig::Legacy(c) if c.command.as_deref() == Some("api")));
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        let Err(e) = config().apply_profile("staging") else {
+            unreachable!("there is no staging profile");
+        };
+        assert!(e.contains("broken, ci, frontend"), "{e}");
+
+        let Err(e) = config().apply_profile("broken") else {
+            unreachable!("api depends on db");
+        };
+        assert!(e.contains("'api' depends on 'db'"), "{e}");
+
+        let mut config = config();
+        let mut typo = ProfileConfig::default();
+        typo.overrides.insert(
+            "api".to_string(),
+            Map::from_iter([("prot".to_string(), Value::from(1))]),
+        );
+        config.profiles.insert("typo".to_string(), typo);
+        let Err(e) = config.apply_profile("typo") else {
+            unreachable!("prot is not an option");
+        };
+        assert!(e.contains("'prot'"), "{e}");
+    }
+}
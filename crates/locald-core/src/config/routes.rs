@@ -0,0 +1,185 @@
+//! Path-based routing on a project's domain.
+//!
+//! By default each service gets its own subdomain. A `[routes]` table lets one domain
+//! front several services instead, so a frontend and its API can share an origin:
+//!
+//! ```toml
+//! [routes]
+//! "/api/*" = "api"                                  # /api/users -> api at /users
+//! "/auth/*" = { service = "auth", strip_prefix = false }
+//! "/*" = "web"
+//! ```
+//!
+//! Patterns ending in `/*` match that prefix and everything below it; other patterns
+//! match one path exactly. The most specific pattern wins.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The service a route pattern sends requests to.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RouteTarget {
+    /// Just the service name. The matched prefix is stripped.
+    Service(String),
+    /// A service with routing options.
+    Detailed {
+        /// The name of the service.
+        service: String,
+        /// Whether to remove the matched prefix before forwarding. Defaults to true.
+        #[serde(default = "default_strip_prefix")]
+        strip_prefix: bool,
+    },
+}
+
+const fn default_strip_prefix() -> bool {
+    true
+}
+
+impl RouteTarget {
+    #[must_use]
+    pub fn service(&self) -> &str {
+        match self {
+            Self::Service(service) | Self::Detailed { service, .. } => service,
+        }
+    }
+
+    #[must_use]
+    pub const fn strip_prefix(&self) -> bool {
+        match self {
+            Self::Service(_) => true,
+            Self::Detailed { strip_prefix, .. } => *strip_prefix,
+        }
+    }
+}
+
+/// A request path matched against a `[routes]` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteMatch<'a> {
+    /// The pattern that matched (e.g. `/api/*`).
+    pub pattern: &'a str,
+    /// The service to send the request to.
+    pub service: &'a str,
+    /// The path (and query) to forward, with the prefix stripped if configured.
+    pub path: String,
+}
+
+/// Checks that a route pattern is an absolute path, optionally ending in `/*`.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the pattern is invalid.
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("route '{pattern}' must start with '/'"));
+    }
+    let wildcard = pattern.find('*');
+    if wildcard.is_some_and(|i| i != pattern.len() - 1 || !pattern.ends_with("/*")) {
+        return Err(format!(
+            "route '{pattern}' may only use '*' as its last segment (e.g. '/api/*')"
+        ));
+    }
+    Ok(())
+}
+
+/// Finds the most specific route for `path_and_query` (e.g. `/api/users?page=2`).
+#[must_use]
+pub fn match_route<'a>(
+    routes: &'a BTreeMap<String, RouteTarget>,
+    path_and_query: &str,
+) -> Option<RouteMatch<'a>> {
+    let (path, query) = path_and_query
+        .split_once('?')
+        .map_or((path_and_query, None), |(p, q)| (p, Some(q)));
+
+    routes
+        .iter()
+        .filter_map(|(pattern, target)| {
+            let prefix = pattern.strip_suffix("/*");
+            let matched = prefix.map_or_else(
+                || path == pattern,
+                |prefix| {
+                    path == prefix
+                        || path
+                            .strip_prefix(prefix)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                },
+            );
+            // Exact patterns beat a prefix pattern of the same length
+            matched.then_some((prefix.map_or(pattern.len() + 1, str::len), pattern, target))
+        })
+        .max_by_key(|(specificity, _, _)| *specificity)
+        .map(|(_, pattern, target)| {
+            let mut forwarded = match pattern.strip_suffix("/*") {
+                Some(prefix) if target.strip_prefix() => {
+                    let rest = &path[prefix.len()..];
+                    if rest.is_empty() {
+                        "/".to_string()
+                    } else {
+                        rest.to_string()
+                    }
+                }
+                Some(_) | None => path.to_string(),
+            };
+            if let Some(query) = query {
+                forwarded.push('?');
+                forwarded.push_str(query);
+            }
+            RouteMatch {
+                pattern,
+                service: target.service(),
+                path: forwarded,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_route_prefers_specific_patterns() {
+        let routes = BTreeMap::from([
+            ("/*".to_string(), RouteTarget::Service("web".to_string())),
+            (
+                "/api/*".to_string(),
+                RouteTarget::Service("api".to_string()),
+            ),
+            (
+                "/auth/*".to_string(),
+                RouteTarget::Detailed {
+                    service: "auth".to_string(),
+                    strip_prefix: false,
+                },
+            ),
+            (
+                "/api/health".to_string(),
+                RouteTarget::Service("health".to_string()),
+            ),
+        ]);
+
+        let route = |path| match_route(&routes, path).map(|m| (m.service, m.path));
+        assert_eq!(
+            route("/api/users?page=2"),
+            Some(("api", "/users?page=2".to_string()))
+        );
+        assert_eq!(route("/api"), Some(("api", "/".to_string())));
+        assert_eq!(
+            route("/api/health"),
+            Some(("health", "/api/health".to_string()))
+        );
+        assert_eq!(
+            route("/auth/login"),
+            Some(("auth", "/auth/login".to_string()))
+        );
+        assert_eq!(route("/apiary"), Some(("web", "/apiary".to_string())));
+        assert_eq!(route("/"), Some(("web", "/".to_string())));
+
+        assert!(match_route(&BTreeMap::new(), "/").is_none());
+        assert!(validate_pattern("/api/*").is_ok());
+        assert!(validate_pattern("api/*").is_err());
+        assert!(validate_pattern("/api*").is_err());
+        assert!(validate_pattern("/*/users").is_err());
+    }
+}
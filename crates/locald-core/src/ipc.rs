@@ -1,6 +1,9 @@
-use crate::state::{HealthSource, HealthStatus, ServiceState};
+use crate::config::{FaultConfig, ServiceFaults};
+use crate::log_format::{LogLevel, StructuredLog};
+use crate::state::{HealthSource, HealthStatus, ServiceState, TaskRun};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Represents the stream a log message came from.
@@ -43,7 +46,7 @@ impl std::fmt::Display for LogStream {
 ///     domain: Some("web.local".to_string()),
 ///     health_status: HealthStatus::Healthy,
 ///     health_source: HealthSource::Http,
-///     path: None,
+///     ..ServiceStatus::new("web", ServiceState::Running)
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -78,6 +81,21 @@ pub struct ServiceStatus {
     /// Any warnings associated with the service (e.g. port mismatch).
     #[serde(default)]
     pub warnings: Vec<String>,
+    /// The number of consecutive times the service has exited on its own.
+    #[serde(default)]
+    pub crash_count: u32,
+    /// The exit code of the last unexpected exit, if known.
+    #[serde(default)]
+    pub last_exit_code: Option<i32>,
+    /// Whether the service keeps crashing shortly after being started.
+    #[serde(default)]
+    pub crash_looping: bool,
+    /// The last run of a one-shot task, if this service is a task that has run.
+    #[serde(default)]
+    pub task_run: Option<TaskRun>,
+    /// Routes on the project's domain that lead to this service (e.g. "shop.localhost/api/*").
+    #[serde(default)]
+    pub routes: Vec<String>,
 }
 
 /// A log entry from a service.
@@ -91,6 +109,7 @@ pub struct ServiceStatus {
 ///     service: "web".to_string(),
 ///     stream: LogStream::Stdout,
 ///     message: "Server started".to_string(),
+///     structured: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -103,6 +122,9 @@ pub struct LogEntry {
     pub stream: LogStream,
     /// The log message content.
     pub message: String,
+    /// The parsed line, if the service logs JSON or logfmt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<StructuredLog>,
 }
 
 /// Metrics for a service.
@@ -118,6 +140,110 @@ pub struct ServiceMetrics {
     pub timestamp: i64,
 }
 
+/// A saved copy of a stateful service's data (see `locald db snapshot`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotInfo {
+    /// The service the snapshot belongs to (e.g., "project:db").
+    pub service: String,
+    /// The name of the snapshot.
+    pub name: String,
+    /// When the snapshot was taken (Unix epoch seconds).
+    pub created_at: i64,
+    /// The size of the captured data in bytes.
+    pub size_bytes: u64,
+}
+
+/// An HTTP request the proxy captured on its way to a service (see `locald requests`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CapturedRequest {
+    /// Identifies the request within its service's capture buffer.
+    pub id: u64,
+    /// The service the request was sent to (e.g., "project:web").
+    pub service: String,
+    /// When the request arrived (Unix epoch milliseconds).
+    pub timestamp: i64,
+    /// The HTTP method.
+    pub method: String,
+    /// The Host header the client sent.
+    pub host: String,
+    /// The path and query forwarded to the service.
+    pub path: String,
+    /// The request headers, in order.
+    pub headers: Vec<(String, String)>,
+    /// The request body.
+    pub body: CapturedBody,
+    /// The service's response, once it has answered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<CapturedResponse>,
+    /// Why no response was recorded (e.g. the service couldn't be reached).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The captured request this one replayed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<u64>,
+}
+
+/// A service's response to a [`CapturedRequest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CapturedResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response headers, in order.
+    pub headers: Vec<(String, String)>,
+    /// The response body.
+    pub body: CapturedBody,
+    /// Time from forwarding the request to receiving the response headers.
+    pub duration_ms: u64,
+}
+
+/// A request or response body, up to the capture limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CapturedBody {
+    /// The captured bytes, base64-encoded.
+    pub data: String,
+    /// The full size of the body in bytes.
+    pub size: u64,
+    /// Whether the body was larger than the capture limit, so `data` is incomplete.
+    pub truncated: bool,
+}
+
+impl CapturedBody {
+    /// Encodes captured bytes. `size` is the full size of the body.
+    #[must_use]
+    pub fn new(data: &[u8], size: u64) -> Self {
+        use base64::Engine;
+        Self {
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+            size,
+            truncated: size > data.len() as u64,
+        }
+    }
+
+    /// The captured bytes.
+    #[must_use]
+    pub fn bytes(&self) -> Vec<u8> {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.data)
+            .unwrap_or_default()
+    }
+
+    /// The captured bytes as text, if they are valid UTF-8.
+    #[must_use]
+    pub fn text(&self) -> Option<String> {
+        String::from_utf8(self.bytes()).ok()
+    }
+}
+
+/// The requests captured for a service.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RequestCapture {
+    /// Whether the proxy is currently capturing requests to the service.
+    pub enabled: bool,
+    /// The captured requests, oldest first.
+    pub requests: Vec<CapturedRequest>,
+}
+
 /// The mode for log streaming.
 ///
 /// # Example
@@ -144,6 +270,12 @@ pub enum LogMode {
 /// let req = IpcRequest::Logs {
 ///     service: Some("web".to_string()),
 ///     mode: LogMode::Follow,
+///     since: None,
+///     until: None,
+///     tail: Some(100),
+///     grep: None,
+///     level: None,
+///     fields: Default::default(),
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -165,6 +297,14 @@ pub enum IpcRequest {
         /// Enable verbose output for build steps.
         #[serde(default)]
         verbose: bool,
+        /// The profile to apply to the project's config (`[profiles.<name>]`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+        /// The client's environment, which `${env.NAME}` references in the project's
+        /// config resolve against (with `.env` over it). Without it, the daemon's own
+        /// environment is used.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        env: Option<BTreeMap<String, String>>,
     },
     /// Stop a service by name.
     ///
@@ -203,6 +343,24 @@ pub enum IpcRequest {
         /// The mode for log streaming (Follow or Snapshot).
         #[serde(default)]
         mode: LogMode,
+        /// Only entries at or after this time (Unix epoch seconds).
+        #[serde(default)]
+        since: Option<i64>,
+        /// Only entries at or before this time (Unix epoch seconds).
+        #[serde(default)]
+        until: Option<i64>,
+        /// Only the last N historical entries.
+        #[serde(default)]
+        tail: Option<usize>,
+        /// Only entries whose message matches this regular expression.
+        #[serde(default)]
+        grep: Option<String>,
+        /// Only structured entries at or above this level.
+        #[serde(default)]
+        level: Option<LogLevel>,
+        /// Only structured entries whose fields have these values (`--where key=value`).
+        #[serde(default)]
+        fields: BTreeMap<String, String>,
     },
     /// Get the AI context (current state).
     ///
@@ -238,6 +396,72 @@ pub enum IpcRequest {
     ///
     /// **Response:** `IpcResponse::ServiceEnv(HashMap<String, String>)`
     GetServiceEnv { name: String },
+    /// Snapshot the data of a database service.
+    ///
+    /// **Response:** `IpcResponse::Snapshot(SnapshotInfo)` or `IpcResponse::Error`
+    DbSnapshot {
+        /// The service to snapshot.
+        service: String,
+        /// The snapshot name (defaults to a timestamp).
+        name: Option<String>,
+    },
+    /// Restore a database service from a snapshot.
+    ///
+    /// **Response:** `IpcResponse::Ok` or `IpcResponse::Error`
+    DbRestore {
+        /// The service to restore.
+        service: String,
+        /// The snapshot to restore.
+        name: String,
+    },
+    /// List database snapshots.
+    ///
+    /// **Response:** `IpcResponse::Snapshots(Vec<SnapshotInfo>)`
+    DbSnapshots {
+        /// Optional service name filter.
+        service: Option<String>,
+    },
+    /// List the HTTP requests the proxy captured for a service.
+    ///
+    /// **Response:** `IpcResponse::Requests(RequestCapture)`
+    Requests { service: String },
+    /// Turn request capture on or off for a service.
+    ///
+    /// **Response:** `IpcResponse::Ok`
+    CaptureRequests {
+        /// The service to capture requests for.
+        service: String,
+        /// Whether to capture.
+        enabled: bool,
+    },
+    /// Discard the HTTP requests captured for a service.
+    ///
+    /// **Response:** `IpcResponse::Ok`
+    ClearRequests { service: String },
+    /// Send a captured request to its service again.
+    ///
+    /// **Response:** `IpcResponse::Request(CapturedRequest)` or `IpcResponse::Error`
+    ReplayRequest {
+        /// The service the request was captured for.
+        service: String,
+        /// The id of the captured request.
+        id: u64,
+    },
+    /// List the services the proxy has fault rules for.
+    ///
+    /// **Response:** `IpcResponse::Faults(Vec<ServiceFaults>)`
+    Faults,
+    /// Switch fault injection on or off for a service.
+    ///
+    /// **Response:** `IpcResponse::Ok` or `IpcResponse::Error`
+    SetFaults {
+        /// The service to inject faults into.
+        service: String,
+        /// Whether to inject faults; `None` goes back to `locald.toml`.
+        enabled: Option<bool>,
+        /// Rules replacing those in `locald.toml`.
+        rules: Option<FaultConfig>,
+    },
     /// Run an ephemeral container.
     ///
     /// **Response:** `IpcResponse::Ok` (detached) or Stream of `Event::Log` (attached)
@@ -284,6 +508,16 @@ pub enum IpcResponse {
     RegistryCleaned(usize),
     /// Response to GetServiceEnv request.
     ServiceEnv(std::collections::HashMap<String, String>),
+    /// Response to DbSnapshot request.
+    Snapshot(SnapshotInfo),
+    /// Response to DbSnapshots request.
+    Snapshots(Vec<SnapshotInfo>),
+    /// Response to Requests request.
+    Requests(RequestCapture),
+    /// Response to ReplayRequest request.
+    Request(Box<CapturedRequest>),
+    /// Response to Faults request.
+    Faults(Vec<ServiceFaults>),
 }
 
 /// Events broadcasted by the Server.
@@ -297,6 +531,7 @@ pub enum IpcResponse {
 ///     service: "web".to_string(),
 ///     stream: LogStream::Stdout,
 ///     message: "hello".to_string(),
+///     structured: None,
 /// });
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -357,6 +592,11 @@ impl ServiceStatus {
             workspace: None,
             constellation: None,
             warnings: Vec::new(),
+            crash_count: 0,
+            last_exit_code: None,
+            crash_looping: false,
+            task_run: None,
+            routes: Vec::new(),
         }
     }
 }
@@ -20,6 +20,7 @@
 //!
 //! *   [`config`]: Defines the `locald.toml` schema and global configuration.
 //! *   [`ipc`]: Defines the request/response protocol between CLI and Server.
+//! *   [`log_format`]: Parses structured (JSON and logfmt) log lines.
 //! *   [`state`]: Defines the runtime state of services (Running, Stopped, etc.).
 //! *   [`registry`]: Manages the list of known projects.
 //!
@@ -66,6 +67,7 @@ pub mod config;
 #[doc(inline)]
 pub use config::LocaldConfig;
 pub mod ipc;
+pub mod log_format;
 #[doc(inline)]
 pub use ipc::{IpcRequest, IpcResponse};
 pub mod hosts;
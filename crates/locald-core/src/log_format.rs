@@ -0,0 +1,391 @@
+//! Detection and parsing of structured (JSON and logfmt) log lines.
+//!
+//! Services that log JSON or logfmt get their lines parsed into a [`StructuredLog`],
+//! so `locald logs --level warn --where user_id=42` and the dashboard can work with
+//! the level, message and fields instead of the raw text. A [`FormatDetector`] decides
+//! each service's format from its first lines and parses the rest in that format only.
+//!
+//! # Example
+//! ```rust
+//! use locald_core::log_format::{LogFormat, LogLevel, StructuredLog};
+//!
+//! let log = StructuredLog::parse(r#"{"level":"warn","msg":"slow query","ms":812}"#).unwrap();
+//! assert_eq!(log.format, LogFormat::Json);
+//! assert_eq!(log.level, Some(LogLevel::Warn));
+//! assert_eq!(log.message.as_deref(), Some("slow query"));
+//! assert_eq!(log.field("ms").as_deref(), Some("812"));
+//!
+//! assert!(StructuredLog::parse("Listening on port 3000").is_none());
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Keys holding the level, checked in order.
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity", "log.level", "levelname"];
+/// Keys holding the human-readable message, checked in order.
+const MESSAGE_KEYS: &[&str] = &["msg", "message", "event"];
+/// Keys holding the time the service logged the line, checked in order.
+const TIMESTAMP_KEYS: &[&str] = &["time", "timestamp", "ts", "@timestamp"];
+
+/// A log line that isn't made of at least this many `key=value` pairs isn't logfmt.
+const MIN_LOGFMT_PAIRS: usize = 2;
+
+/// A service whose first this many lines aren't structured is taken to log plain text.
+const DETECTION_LINES: usize = 20;
+
+/// The format a structured log line was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Logfmt,
+}
+
+/// The severity of a structured log line, from least to most severe.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// Maps the numeric levels used by pino and bunyan (10 = trace ... 60 = fatal).
+    const fn from_number(n: i64) -> Self {
+        match n {
+            ..=10 => Self::Trace,
+            11..=20 => Self::Debug,
+            21..=30 => Self::Info,
+            31..=40 => Self::Warn,
+            41..=50 => Self::Error,
+            51.. => Self::Fatal,
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => s.parse().ok(),
+            Value::Number(n) => n.as_i64().map(Self::from_number),
+            Value::Null | Value::Bool(_) | Value::Array(_) | Value::Object(_) => None,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" | "dbg" => Ok(Self::Debug),
+            "info" | "information" | "notice" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" | "err" => Ok(Self::Error),
+            "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" | "emergency" => {
+                Ok(Self::Fatal)
+            }
+            _ => Err(format!(
+                "unknown log level '{s}' (expected trace, debug, info, warn, error or fatal)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Fatal => "fatal",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The parsed form of a JSON or logfmt log line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct StructuredLog {
+    /// The format the line was written in.
+    pub format: LogFormat,
+    /// The level, if the line had a recognised one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<LogLevel>,
+    /// The message (`msg`, `message` or `event`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The time the service put in the line, as written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Every other key in the line.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl StructuredLog {
+    /// Parses a JSON object or logfmt line. Returns `None` for anything else.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let format = if is_json_object(line) {
+            LogFormat::Json
+        } else {
+            LogFormat::Logfmt
+        };
+        Self::parse_as(line, format)
+    }
+
+    /// Parses a line written in `format`. Returns `None` if it isn't.
+    #[must_use]
+    pub fn parse_as(line: &str, format: LogFormat) -> Option<Self> {
+        match format {
+            LogFormat::Json => {
+                if !is_json_object(line) {
+                    return None;
+                }
+                let object = serde_json::from_str::<Map<String, Value>>(line.trim()).ok()?;
+                Some(Self::from_fields(LogFormat::Json, object.into_iter()))
+            }
+            LogFormat::Logfmt => {
+                let pairs = parse_logfmt(line.trim())?;
+                Some(Self::from_fields(
+                    LogFormat::Logfmt,
+                    pairs.into_iter().map(|(k, v)| (k, Value::String(v))),
+                ))
+            }
+        }
+    }
+
+    fn from_fields(format: LogFormat, pairs: impl Iterator<Item = (String, Value)>) -> Self {
+        let mut fields: BTreeMap<String, Value> = pairs.collect();
+        let level = take_first(&mut fields, LEVEL_KEYS).and_then(|v| LogLevel::from_value(&v));
+        let message = take_first(&mut fields, MESSAGE_KEYS).map(|v| display_value(&v));
+        let timestamp = take_first(&mut fields, TIMESTAMP_KEYS).map(|v| display_value(&v));
+        Self {
+            format,
+            level,
+            message,
+            timestamp,
+            fields,
+        }
+    }
+
+    /// Looks up a field by key, falling back to a dotted path into nested objects
+    /// (`req.method`). Non-string values are rendered as JSON.
+    #[must_use]
+    pub fn field(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.fields.get(key) {
+            return Some(display_value(value));
+        }
+
+        let mut parts = key.split('.');
+        let mut value = self.fields.get(parts.next()?)?;
+        for part in parts {
+            value = value.as_object()?.get(part)?;
+        }
+        Some(display_value(value))
+    }
+}
+
+/// Where a [`FormatDetector`] stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Detection {
+    /// Still looking, after this many plain lines.
+    Pending(usize),
+    Structured(LogFormat),
+    Plain,
+}
+
+/// Decides which format one service logs in from its first lines, then parses the rest
+/// in that format only.
+///
+/// Deciding once per service means a plain-text service's occasional `a=1 b=2` line
+/// isn't taken for logfmt, and a JSON service's banner or stack trace lines stay text.
+/// The first structured line decides the format; a service with none among its first
+/// [`DETECTION_LINES`] lines is taken to log plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatDetector {
+    detection: Detection,
+}
+
+impl Default for FormatDetector {
+    fn default() -> Self {
+        Self {
+            detection: Detection::Pending(0),
+        }
+    }
+}
+
+impl FormatDetector {
+    /// The service's format, once it's known to log structured lines.
+    #[must_use]
+    pub const fn format(&self) -> Option<LogFormat> {
+        match self.detection {
+            Detection::Structured(format) => Some(format),
+            Detection::Pending(_) | Detection::Plain => None,
+        }
+    }
+
+    /// Parses the service's next line.
+    pub fn parse(&mut self, line: &str) -> Option<StructuredLog> {
+        match self.detection {
+            Detection::Structured(format) => StructuredLog::parse_as(line, format),
+            Detection::Plain => None,
+            Detection::Pending(_) if line.trim().is_empty() => None,
+            Detection::Pending(plain_lines) => {
+                let log = StructuredLog::parse(line);
+                self.detection = match &log {
+                    Some(log) => Detection::Structured(log.format),
+                    None if plain_lines + 1 >= DETECTION_LINES => Detection::Plain,
+                    None => Detection::Pending(plain_lines + 1),
+                };
+                log
+            }
+        }
+    }
+}
+
+fn is_json_object(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('{') && line.ends_with('}')
+}
+
+fn take_first(fields: &mut BTreeMap<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|key| fields.remove(*key))
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Object(_) => {
+            value.to_string()
+        }
+    }
+}
+
+/// Splits a logfmt line (`level=info msg="request done" status=200`) into pairs.
+///
+/// Returns `None` unless the whole line is made of `key=value` pairs, so ordinary
+/// text that happens to contain an `=` isn't mistaken for logfmt.
+fn parse_logfmt(line: &str) -> Option<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '@' | '/')) {
+                return None;
+            }
+            key.push(c);
+        }
+        if key.is_empty() || chars.next() != Some('=') {
+            return None;
+        }
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+            if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                return None;
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+        pairs.push((key, value));
+    }
+
+    (pairs.len() >= MIN_LOGFMT_PAIRS).then_some(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_structured_lines() {
+        let Some(json) = StructuredLog::parse(
+            r#"{"level":50,"time":1714564800000,"msg":"payment failed","user_id":42,"req":{"method":"POST"}}"#,
+        ) else {
+            unreachable!("JSON line should parse");
+        };
+        assert_eq!(json.level, Some(LogLevel::Error));
+        assert_eq!(json.message.as_deref(), Some("payment failed"));
+        assert_eq!(json.timestamp.as_deref(), Some("1714564800000"));
+        assert_eq!(json.field("user_id").as_deref(), Some("42"));
+        assert_eq!(json.field("req.method").as_deref(), Some("POST"));
+        assert!(!json.fields.contains_key("msg"));
+
+        let Some(logfmt) = StructuredLog::parse(
+            r#"time=2024-05-01T12:00:00Z level=WARNING msg="disk \"almost\" full" pct=91"#,
+        ) else {
+            unreachable!("logfmt line should parse");
+        };
+        assert_eq!(logfmt.format, LogFormat::Logfmt);
+        assert_eq!(logfmt.level, Some(LogLevel::Warn));
+        assert_eq!(logfmt.message.as_deref(), Some(r#"disk "almost" full"#));
+        assert_eq!(logfmt.field("pct").as_deref(), Some("91"));
+
+        for plain in [
+            "Listening on http://localhost:3000",
+            "x=1",
+            "retrying with timeout=30 seconds",
+            "{not json}",
+            r#"a=1 b="unterminated"#,
+        ] {
+            assert!(StructuredLog::parse(plain).is_none(), "{plain}");
+        }
+    }
+
+    #[test]
+    fn test_format_detected_once_per_service() {
+        // A JSON service: the banner before the first JSON line doesn't count against
+        // it, and logfmt-looking lines afterwards stay text
+        let mut json = FormatDetector::default();
+        assert!(json.parse("> api@1.0.0 start").is_none());
+        assert!(
+            json.parse(r#"{"level":"info","msg":"listening"}"#)
+                .is_some()
+        );
+        assert_eq!(json.format(), Some(LogFormat::Json));
+        assert!(json.parse("level=warn msg=slow").is_none());
+        assert!(json.parse(r#"{"level":"warn","msg":"slow"}"#).is_some());
+
+        // A plain-text service: once decided, `key=value` text isn't taken for logfmt
+        let mut plain = FormatDetector::default();
+        for _ in 0..DETECTION_LINES {
+            assert!(plain.parse("Compiled successfully").is_none());
+        }
+        assert!(plain.parse("user=alice action=login").is_none());
+        assert_eq!(plain.format(), None);
+
+        let mut logfmt = FormatDetector::default();
+        assert!(logfmt.parse("level=info msg=ready port=3000").is_some());
+        assert_eq!(logfmt.format(), Some(LogFormat::Logfmt));
+        assert!(logfmt.parse(r#"{"level":"info"}"#).is_none());
+    }
+}
@@ -1,5 +1,45 @@
 use async_trait::async_trait;
 
+use crate::config::{CorsConfig, FaultConfig, HeaderRules, Protocol};
+
+/// Where the proxy should send a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRoute {
+    /// The name of the service handling the request.
+    pub service: String,
+    /// The port the service is listening on.
+    pub port: u16,
+    /// The path (and query) to forward, after any prefix stripping.
+    pub path: String,
+}
+
+/// The transport of an exposed port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExposeProtocol {
+    Tcp,
+    Udp,
+}
+
+impl std::fmt::Display for ExposeProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "TCP"),
+            Self::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
+/// A stable port a service asked for with `expose`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExposedPort {
+    /// The name of the service to forward to.
+    pub service: String,
+    /// Whether the port is TCP or UDP.
+    pub protocol: ExposeProtocol,
+    /// The port to listen on.
+    pub port: u16,
+}
+
 /// Abstraction layer between the "World State" (Manager) and the "Gateway" (Proxy).
 ///
 /// This trait allows the Proxy to resolve service locations (ports) without knowing
@@ -16,6 +56,65 @@ pub trait ServiceResolver: Send + Sync + std::fmt::Debug {
     /// or `None` if no service matches.
     async fn resolve_service_by_domain(&self, domain: &str) -> Option<(String, u16)>;
 
+    /// Find the service for a request to `domain` at `path` (including the query string).
+    ///
+    /// Projects can send different paths on their domain to different services with a
+    /// `[routes]` table. The default ignores the path and forwards it unchanged to the
+    /// service found by [`Self::resolve_service_by_domain`].
+    async fn resolve_route(&self, domain: &str, path: &str) -> Option<ResolvedRoute> {
+        self.resolve_service_by_domain(domain)
+            .await
+            .map(|(service, port)| ResolvedRoute {
+                service,
+                port,
+                path: path.to_string(),
+            })
+    }
+
+    /// The port a service is currently listening on, by name (e.g. "shop:db").
+    async fn resolve_service_port(&self, _service: &str) -> Option<u16> {
+        None
+    }
+
+    /// The stable ports services asked for with `expose`.
+    async fn exposed_ports(&self) -> Vec<ExposedPort> {
+        Vec::new()
+    }
+
+    /// The root domains of the loaded projects (e.g. `shop.localhost`, `shop.test`).
+    ///
+    /// These and every name below them resolve to the loopback address.
+    async fn local_domains(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The wildcard domains (e.g. `*.myapp.localhost`) services answer on.
+    ///
+    /// The proxy issues one wildcard certificate for each.
+    async fn wildcard_domains(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The fault rules currently being injected into requests to a service, if any.
+    async fn service_faults(&self, _service: &str) -> Option<FaultConfig> {
+        None
+    }
+
+    /// The header rules the proxy applies to a service's requests and responses.
+    async fn service_headers(&self, _service: &str) -> Option<HeaderRules> {
+        None
+    }
+
+    /// The CORS policy the proxy enforces for a service, if any.
+    async fn service_cors(&self, _service: &str) -> Option<CorsConfig> {
+        None
+    }
+
+    /// The protocol the proxy should speak to a service.
+    async fn service_protocol(&self, _service: &str) -> Protocol {
+        Protocol::Http
+    }
+
     /// Update the port the HTTP proxy is bound to.
     ///
     /// This allows the Manager to know where the Proxy is listening, which is
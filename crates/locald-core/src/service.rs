@@ -19,6 +19,10 @@ pub struct RuntimeState {
 pub enum ServiceCommand {
     /// Reset the service to its initial state (e.g., wipe data).
     Reset,
+    /// Copy the service's data into the given (not yet existing) directory.
+    Snapshot(PathBuf),
+    /// Replace the service's data with a copy made by `Snapshot`.
+    Restore(PathBuf),
     /// Custom command (e.g., "run-migration").
     Custom(String, Vec<String>),
 }
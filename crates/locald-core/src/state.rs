@@ -1,6 +1,7 @@
 use crate::config::LocaldConfig;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
@@ -67,6 +68,26 @@ impl std::fmt::Display for ServiceState {
     }
 }
 
+/// The outcome of the last run of a one-shot task.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct TaskRun {
+    /// A hash of the task's configuration and input files at the time it ran.
+    pub fingerprint: String,
+    /// The exit code of the task, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// When the run finished (Unix epoch seconds).
+    pub finished_at: i64,
+    /// How long the run took, in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl TaskRun {
+    #[must_use]
+    pub const fn succeeded(&self) -> bool {
+        matches!(self.exit_code, Some(0))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PersistedServiceState {
     pub name: String,
@@ -80,6 +101,15 @@ pub struct PersistedServiceState {
     pub health_status: HealthStatus,
     #[serde(default)]
     pub health_source: HealthSource,
+    #[serde(default)]
+    pub task_run: Option<TaskRun>,
+    /// The profile the service's project was started with.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// The variables from the environment `locald up` was run in that the project's
+    /// `${env.NAME}` references use, so a restored service resolves them the same way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_env: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -0,0 +1,156 @@
+//! JSON Web Signatures (RFC 7515) as ACME uses them: flattened JSON, signed by the
+//! account key, which is either embedded (`jwk`) or referenced by account URL (`kid`).
+
+use super::Problem;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A request body: `{"protected": ..., "payload": ..., "signature": ...}`.
+#[derive(Debug, Deserialize)]
+pub(super) struct Jws {
+    protected: String,
+    payload: String,
+    signature: String,
+}
+
+/// The protected header of a request.
+#[derive(Debug, Deserialize)]
+pub(super) struct Protected {
+    pub(super) alg: String,
+    pub(super) nonce: Option<String>,
+    pub(super) url: String,
+    pub(super) jwk: Option<Jwk>,
+    pub(super) kid: Option<String>,
+}
+
+/// A public key (RFC 7517). Only the members that identify the key are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kty")]
+pub(super) enum Jwk {
+    #[serde(rename = "EC")]
+    Ec { crv: String, x: String, y: String },
+    #[serde(rename = "RSA")]
+    Rsa { n: String, e: String },
+    #[serde(rename = "OKP")]
+    Okp { crv: String, x: String },
+}
+
+impl Jwk {
+    /// The key's thumbprint (RFC 7638), which identifies its account.
+    #[must_use]
+    pub(super) fn thumbprint(&self) -> String {
+        // The required members, in lexicographic order, without whitespace
+        let canonical = match self {
+            Self::Ec { crv, x, y } => {
+                format!(r#"{{"crv":"{crv}","kty":"EC","x":"{x}","y":"{y}"}}"#)
+            }
+            Self::Rsa { n, e } => format!(r#"{{"e":"{e}","kty":"RSA","n":"{n}"}}"#),
+            Self::Okp { crv, x } => format!(r#"{{"crv":"{crv}","kty":"OKP","x":"{x}"}}"#),
+        };
+        URL_SAFE_NO_PAD.encode(Sha256::digest(canonical))
+    }
+
+    /// Checks `signature` over `message`, made with this key and `alg`.
+    fn verify(&self, alg: &str, message: &[u8], signature: &[u8]) -> Result<(), Problem> {
+        let verified = match (self, alg) {
+            (Self::Ec { crv, x, y }, "ES256" | "ES384") => {
+                let algorithm = match (crv.as_str(), alg) {
+                    ("P-256", "ES256") => &signature::ECDSA_P256_SHA256_FIXED,
+                    ("P-384", "ES384") => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => return Err(unsupported(alg)),
+                };
+                // An uncompressed point: 0x04, then x and y
+                let mut point = vec![4];
+                point.extend(decode(x)?);
+                point.extend(decode(y)?);
+                UnparsedPublicKey::new(algorithm, point).verify(message, signature)
+            }
+            (Self::Rsa { n, e }, "RS256") => RsaPublicKeyComponents {
+                n: decode(n)?,
+                e: decode(e)?,
+            }
+            .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature),
+            (Self::Okp { crv, x }, "EdDSA") if crv == "Ed25519" => {
+                UnparsedPublicKey::new(&signature::ED25519, decode(x)?).verify(message, signature)
+            }
+            _ => return Err(unsupported(alg)),
+        };
+        verified.map_err(|_| Problem::malformed("The request signature is invalid"))
+    }
+}
+
+impl Jws {
+    /// Decodes the protected header, without checking the signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `malformed` if the header isn't base64url-encoded JSON.
+    pub(super) fn protected(&self) -> Result<Protected, Problem> {
+        serde_json::from_slice(&decode(&self.protected)?)
+            .map_err(|e| Problem::malformed(format!("Invalid protected header: {e}")))
+    }
+
+    /// Checks the signature against `key` and returns the payload. A POST-as-GET
+    /// request has an empty payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns a problem if the signature doesn't verify or uses an unsupported
+    /// algorithm.
+    pub(super) fn verify(&self, key: &Jwk, alg: &str) -> Result<Vec<u8>, Problem> {
+        let message = format!("{}.{}", self.protected, self.payload);
+        key.verify(alg, message.as_bytes(), &decode(&self.signature)?)?;
+        decode(&self.payload)
+    }
+}
+
+/// Decodes base64url without padding, as all of JOSE uses.
+pub(super) fn decode(value: &str) -> Result<Vec<u8>, Problem> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| Problem::malformed("Expected base64url without padding"))
+}
+
+fn unsupported(alg: &str) -> Problem {
+    Problem::new(
+        "badSignatureAlgorithm",
+        format!("Unsupported key or algorithm {alg}; use ES256, ES384, RS256 or EdDSA"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbprint() {
+        // The example from RFC 7638, section 3.1
+        let key = Jwk::Rsa {
+            n: "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_string(),
+            e: "AQAB".to_string(),
+        };
+        assert_eq!(
+            key.thumbprint(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported_algorithms() {
+        let key = Jwk::Okp {
+            crv: "Ed25519".to_string(),
+            x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".to_string(),
+        };
+        let Err(problem) = key.verify("ES256", b"message", b"signature") else {
+            unreachable!("an Ed25519 key can't make ES256 signatures");
+        };
+        assert_eq!(problem.kind, "badSignatureAlgorithm");
+        let Err(problem) = key.verify("EdDSA", b"message", &[0; 64]) else {
+            unreachable!("the signature is made up");
+        };
+        assert_eq!(problem.kind, "malformed");
+    }
+}
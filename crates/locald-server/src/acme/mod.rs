@@ -0,0 +1,844 @@
+//! A local ACME server (RFC 8555), so tools that obtain their own certificates (Caddy,
+//! Traefik, cert-manager) can run unchanged under locald and still be trusted through
+//! the locald root CA.
+//!
+//! The directory is `/api/acme/directory` on the proxy, e.g.
+//! `https://locald.localhost/api/acme/directory`. Names locald controls (anything under
+//! `.localhost`, and the domains listed in the global `[acme] domains`) are authorized
+//! up front, so orders are ready to finalize as soon as they're created. Other names are
+//! rejected. Since the root CA is trusted system-wide, the server only answers clients on
+//! this machine.
+//!
+//! Accounts are kept in `<data dir>/acme/accounts.json`; orders and certificates only
+//! live in memory.
+
+mod jws;
+
+use self::jws::{Jwk, Jws};
+use crate::headers::Https;
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, FromRequestParts, OriginalUri, Path, Request, State};
+use axum::http::request::Parts;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, StatusCode};
+use locald_utils::cert::{CertManager, SigningRequest};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+use tracing::{info, warn};
+
+/// Where the API router is mounted, plus where this router is mounted in it.
+const PREFIX: &str = "/api/acme";
+/// Nonces handed out but not used yet, beyond which the oldest are forgotten.
+const MAX_NONCES: usize = 1024;
+/// How long an order can be finalized and its certificate downloaded.
+const ORDER_DAYS: i64 = 7;
+
+/// Where ACME accounts are stored.
+pub(crate) fn accounts_path() -> PathBuf {
+    directories::ProjectDirs::from("com", "locald", "locald").map_or_else(
+        || PathBuf::from(".locald/acme/accounts.json"),
+        |d| d.data_dir().join("acme").join("accounts.json"),
+    )
+}
+
+/// An ACME error (RFC 8555, section 6.7), sent as `application/problem+json`.
+#[derive(Debug)]
+pub(crate) struct Problem {
+    kind: &'static str,
+    detail: String,
+    status: StatusCode,
+}
+
+impl Problem {
+    fn new(kind: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+            status: StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn malformed(detail: impl Into<String>) -> Self {
+        Self::new("malformed", detail)
+    }
+
+    fn unauthorized(detail: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            ..Self::new("unauthorized", detail)
+        }
+    }
+
+    fn not_found(what: &str) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            ..Self::malformed(format!("No such {what}"))
+        }
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "type": format!("urn:ietf:params:acme:error:{}", self.kind),
+            "detail": self.detail,
+            "status": self.status.as_u16(),
+        });
+        let mut res = (self.status, body.to_string()).into_response();
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        res
+    }
+}
+
+/// A successful answer.
+struct Reply {
+    status: StatusCode,
+    location: Option<String>,
+    /// The parent resource, for `Link: <...>;rel="up"`.
+    up: Option<String>,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: StatusCode, value: &Value) -> Self {
+        Self {
+            status,
+            location: None,
+            up: None,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn at(mut self, location: String) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    fn up(mut self, up: String) -> Self {
+        self.up = Some(up);
+        self
+    }
+}
+
+/// Where a request was sent, for building URLs and checking the signed `url`.
+struct Endpoint {
+    /// e.g. `https://locald.localhost/api/acme`
+    base: String,
+    url: String,
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Endpoint {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        let scheme = if parts.extensions.get::<Https>().is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let uri = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map_or(&parts.uri, |uri| &uri.0);
+        let host = crate::headers::authority(uri, &parts.headers).unwrap_or("locald.localhost");
+        let path = uri.path();
+        Ok(Self {
+            base: format!("{scheme}://{host}{PREFIX}"),
+            url: format!("{scheme}://{host}{path}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, Deserialize)]
+struct Account {
+    key: Jwk,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    contact: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Order {
+    account: String,
+    names: Vec<String>,
+    expires: DateTime<Utc>,
+    /// The certificate, PEM encoded, once the order is finalized.
+    certificate: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    nonces: HashSet<String>,
+    nonce_order: VecDeque<String>,
+    accounts: HashMap<String, Account>,
+    orders: HashMap<String, Order>,
+}
+
+/// A request whose signature has been checked.
+struct Signed {
+    /// The account id, for requests signed with `kid`.
+    account: Option<String>,
+    key: Jwk,
+    payload: Vec<u8>,
+}
+
+impl Signed {
+    fn account(&self) -> Result<&str, Problem> {
+        self.account
+            .as_deref()
+            .ok_or_else(|| Problem::malformed("Sign this request with the account URL (kid)"))
+    }
+
+    fn payload<T: DeserializeOwned>(&self) -> Result<T, Problem> {
+        serde_json::from_slice(&self.payload)
+            .map_err(|e| Problem::malformed(format!("Invalid payload: {e}")))
+    }
+}
+
+/// The ACME server's accounts, orders and nonces.
+#[derive(Debug, Clone)]
+pub struct Acme {
+    domains: Arc<[String]>,
+    certs: Option<Arc<CertManager>>,
+    accounts_path: PathBuf,
+    store: Arc<StdMutex<Store>>,
+}
+
+impl Acme {
+    /// Creates the server, loading accounts from `accounts_path`. Besides `.localhost`
+    /// names, certificates are issued for `domains` and their subdomains. They're signed
+    /// by `certs`; without it, finalizing orders fails.
+    pub async fn load(
+        domains: &[String],
+        certs: Option<Arc<CertManager>>,
+        accounts_path: PathBuf,
+    ) -> Self {
+        let accounts =
+            tokio::fs::read(&accounts_path)
+                .await
+                .ok()
+                .map_or_else(HashMap::new, |data| {
+                    serde_json::from_slice(&data).unwrap_or_else(|e| {
+                        warn!("Ignoring unreadable {}: {e}", accounts_path.display());
+                        HashMap::new()
+                    })
+                });
+        let domains = domains
+            .iter()
+            .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+            .filter(|domain| {
+                let public = locald_utils::domain::is_public_suffix(domain);
+                if public {
+                    warn!(
+                        "Ignoring ACME domain {domain}: it's a top-level domain or public suffix"
+                    );
+                }
+                !public
+            })
+            .collect();
+        Self {
+            domains,
+            certs,
+            accounts_path,
+            store: Arc::new(StdMutex::new(Store {
+                accounts,
+                ..Store::default()
+            })),
+        }
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/directory", get(handle_directory))
+            .route("/new-nonce", get(handle_new_nonce))
+            .route("/new-account", post(handle_new_account))
+            .route("/acct/:id", post(handle_account))
+            .route("/new-order", post(handle_new_order))
+            .route("/order/:id", post(handle_order))
+            .route("/order/:id/finalize", post(handle_finalize))
+            .route("/authz/:order/:index", post(handle_authorization))
+            .route("/chall/:order/:index", post(handle_challenge))
+            .route("/cert/:id", post(handle_certificate))
+            .route("/revoke-cert", post(handle_revoke))
+            .layer(middleware::from_fn(require_loopback))
+            .with_state(self)
+    }
+
+    fn store(&self) -> std::sync::MutexGuard<'_, Store> {
+        self.store
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn new_nonce(&self) -> String {
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let mut store = self.store();
+        store.nonces.insert(nonce.clone());
+        store.nonce_order.push_back(nonce.clone());
+        if store.nonce_order.len() > MAX_NONCES
+            && let Some(oldest) = store.nonce_order.pop_front()
+        {
+            store.nonces.remove(&oldest);
+        }
+        drop(store);
+        nonce
+    }
+
+    /// Adds the headers every answer carries: a fresh nonce and the directory link.
+    fn respond(&self, endpoint: &Endpoint, result: Result<Reply, Problem>) -> Response {
+        let mut res = match result {
+            Ok(reply) => {
+                let mut res = Response::new(Body::from(reply.body));
+                *res.status_mut() = reply.status;
+                let headers = res.headers_mut();
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(reply.content_type),
+                );
+                if let Some(location) = reply.location.and_then(|l| HeaderValue::from_str(&l).ok())
+                {
+                    headers.insert(header::LOCATION, location);
+                }
+                if let Some(up) = reply
+                    .up
+                    .and_then(|up| HeaderValue::from_str(&format!("<{up}>;rel=\"up\"")).ok())
+                {
+                    headers.append(header::LINK, up);
+                }
+                res
+            }
+            Err(problem) => problem.into_response(),
+        };
+        let headers = res.headers_mut();
+        if let Ok(nonce) = HeaderValue::from_str(&self.new_nonce()) {
+            headers.insert("replay-nonce", nonce);
+        }
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if let Ok(index) =
+            HeaderValue::from_str(&format!("<{}/directory>;rel=\"index\"", endpoint.base))
+        {
+            headers.append(header::LINK, index);
+        }
+        res
+    }
+
+    /// Checks a request's nonce, URL and signature.
+    fn verify(&self, endpoint: &Endpoint, body: &[u8]) -> Result<Signed, Problem> {
+        let jws: Jws = serde_json::from_slice(body)
+            .map_err(|e| Problem::malformed(format!("Expected a flattened JWS: {e}")))?;
+        let protected = jws.protected()?;
+
+        let nonce = protected
+            .nonce
+            .as_deref()
+            .ok_or_else(|| Problem::new("badNonce", "The request has no nonce"))?;
+        if !self.store().nonces.remove(nonce) {
+            return Err(Problem::new("badNonce", "Unknown or already used nonce"));
+        }
+        if protected.url != endpoint.url {
+            return Err(Problem::unauthorized(format!(
+                "The request was signed for {}, but sent to {}",
+                protected.url, endpoint.url
+            )));
+        }
+
+        let (account, key) = match (protected.jwk, protected.kid) {
+            (Some(jwk), None) => (None, jwk),
+            (None, Some(kid)) => {
+                let id = kid
+                    .strip_prefix(&format!("{}/acct/", endpoint.base))
+                    .unwrap_or_default();
+                let key = self
+                    .store()
+                    .accounts
+                    .get(id)
+                    .map(|account| account.key.clone())
+                    .ok_or_else(|| {
+                        Problem::new("accountDoesNotExist", format!("Unknown account {kid}"))
+                    })?;
+                (Some(id.to_string()), key)
+            }
+            _ => {
+                return Err(Problem::malformed(
+                    "The protected header needs exactly one of jwk and kid",
+                ));
+            }
+        };
+        let payload = jws.verify(&key, &protected.alg)?;
+        Ok(Signed {
+            account,
+            key,
+            payload,
+        })
+    }
+
+    async fn save_accounts(&self) {
+        let accounts = self.store().accounts.clone();
+        let data = match serde_json::to_vec_pretty(&accounts) {
+            Ok(data) => data,
+            Err(e) => return warn!("Failed to serialize ACME accounts: {e}"),
+        };
+        if let Some(dir) = self.accounts_path.parent() {
+            let _ = tokio::fs::create_dir_all(dir).await;
+        }
+        if let Err(e) = tokio::fs::write(&self.accounts_path, data).await {
+            warn!("Failed to save {}: {e}", self.accounts_path.display());
+        }
+    }
+
+    fn account_json(account: &Account, status: &str) -> Value {
+        json!({
+            "status": status,
+            "contact": account.contact,
+        })
+    }
+
+    async fn new_account(&self, endpoint: &Endpoint, body: &[u8]) -> Result<Reply, Problem> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct NewAccount {
+            #[serde(default)]
+            contact: Vec<String>,
+            #[serde(default)]
+            only_return_existing: bool,
+        }
+
+        let signed = self.verify(endpoint, body)?;
+        let request: NewAccount = signed.payload()?;
+        let id = signed.key.thumbprint();
+        let location = format!("{}/acct/{id}", endpoint.base);
+
+        let existing = self.store().accounts.get(&id).cloned();
+        if let Some(account) = existing {
+            return Ok(
+                Reply::json(StatusCode::OK, &Self::account_json(&account, "valid")).at(location),
+            );
+        }
+        if request.only_return_existing {
+            return Err(Problem::new(
+                "accountDoesNotExist",
+                "No account exists for this key",
+            ));
+        }
+
+        let account = Account {
+            key: signed.key,
+            contact: request.contact,
+        };
+        let json = Self::account_json(&account, "valid");
+        self.store().accounts.insert(id.clone(), account);
+        self.save_accounts().await;
+        info!("Registered ACME account {id}");
+        Ok(Reply::json(StatusCode::CREATED, &json).at(location))
+    }
+
+    async fn account(&self, endpoint: &Endpoint, id: &str, body: &[u8]) -> Result<Reply, Problem> {
+        #[derive(Deserialize, Default)]
+        struct Update {
+            contact: Option<Vec<String>>,
+            status: Option<String>,
+        }
+
+        let signed = self.verify(endpoint, body)?;
+        if signed.account()? != id {
+            return Err(Problem::unauthorized(
+                "The request is signed by another account",
+            ));
+        }
+        let update: Update = if signed.payload.is_empty() {
+            Update::default()
+        } else {
+            signed.payload()?
+        };
+
+        let reply = {
+            let mut store = self.store();
+            if update.status.as_deref() == Some("deactivated") {
+                let account = store.accounts.remove(id);
+                store.orders.retain(|_, order| order.account != id);
+                account.map(|account| Self::account_json(&account, "deactivated"))
+            } else {
+                store.accounts.get_mut(id).map(|account| {
+                    if let Some(contact) = update.contact {
+                        account.contact = contact;
+                    }
+                    Self::account_json(account, "valid")
+                })
+            }
+        };
+
+        let json = reply.ok_or_else(|| Problem::not_found("account"))?;
+        self.save_accounts().await;
+        Ok(Reply::json(StatusCode::OK, &json))
+    }
+
+    fn new_order(&self, endpoint: &Endpoint, body: &[u8]) -> Result<Reply, Problem> {
+        #[derive(Deserialize)]
+        struct Identifier {
+            #[serde(rename = "type")]
+            kind: String,
+            value: String,
+        }
+        #[derive(Deserialize)]
+        struct NewOrder {
+            identifiers: Vec<Identifier>,
+        }
+
+        let signed = self.verify(endpoint, body)?;
+        let account = signed.account()?.to_string();
+        let request: NewOrder = signed.payload()?;
+        if request.identifiers.is_empty() {
+            return Err(Problem::malformed("The order has no identifiers"));
+        }
+
+        let mut names = Vec::new();
+        for identifier in request.identifiers {
+            if identifier.kind != "dns" {
+                return Err(Problem::new(
+                    "unsupportedIdentifier",
+                    format!(
+                        "Only dns identifiers are supported, not {}",
+                        identifier.kind
+                    ),
+                ));
+            }
+            let name = identifier.value.to_ascii_lowercase();
+            if !is_controlled(&name, &self.domains) {
+                return Err(Problem::new(
+                    "rejectedIdentifier",
+                    format!(
+                        "locald only issues certificates for .localhost names and the domains in [acme] domains, not {name}"
+                    ),
+                ));
+            }
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let order = Order {
+            account,
+            names,
+            expires: Utc::now() + chrono::Duration::days(ORDER_DAYS),
+            certificate: None,
+        };
+        let json = Self::order_json(endpoint, &id, &order);
+        let mut store = self.store();
+        let now = Utc::now();
+        store.orders.retain(|_, order| order.expires > now);
+        store.orders.insert(id.clone(), order);
+        drop(store);
+
+        Ok(Reply::json(StatusCode::CREATED, &json).at(format!("{}/order/{id}", endpoint.base)))
+    }
+
+    fn order_json(endpoint: &Endpoint, id: &str, order: &Order) -> Value {
+        let base = &endpoint.base;
+        let mut json = json!({
+            "status": if order.certificate.is_some() { "valid" } else { "ready" },
+            "expires": order.expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "identifiers": order
+                .names
+                .iter()
+                .map(|name| json!({ "type": "dns", "value": name }))
+                .collect::<Vec<_>>(),
+            "authorizations": (0..order.names.len())
+                .map(|index| format!("{base}/authz/{id}/{index}"))
+                .collect::<Vec<_>>(),
+            "finalize": format!("{base}/order/{id}/finalize"),
+        });
+        if order.certificate.is_some() {
+            json["certificate"] = json!(format!("{base}/cert/{id}"));
+        }
+        json
+    }
+
+    /// Looks up an order of the account that signed `signed`.
+    fn owned_order(&self, signed: &Signed, id: &str) -> Result<Order, Problem> {
+        let account = signed.account()?;
+        let order = self
+            .store()
+            .orders
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Problem::not_found("order"))?;
+        if order.account != account {
+            return Err(Problem::unauthorized(
+                "The order belongs to another account",
+            ));
+        }
+        Ok(order)
+    }
+
+    fn order(&self, endpoint: &Endpoint, id: &str, body: &[u8]) -> Result<Reply, Problem> {
+        let signed = self.verify(endpoint, body)?;
+        let order = self.owned_order(&signed, id)?;
+        Ok(Reply::json(
+            StatusCode::OK,
+            &Self::order_json(endpoint, id, &order),
+        ))
+    }
+
+    /// Names are authorized when the order is created, so every authorization is
+    /// valid, with a challenge that's already been met.
+    fn authorization(
+        &self,
+        endpoint: &Endpoint,
+        (order_id, index): (String, usize),
+        body: &[u8],
+        challenge: bool,
+    ) -> Result<Reply, Problem> {
+        let signed = self.verify(endpoint, body)?;
+        let order = self.owned_order(&signed, &order_id)?;
+        let name = order
+            .names
+            .get(index)
+            .ok_or_else(|| Problem::not_found("authorization"))?;
+
+        let base = &endpoint.base;
+        let authz_url = format!("{base}/authz/{order_id}/{index}");
+        let challenge_json = json!({
+            "type": "http-01",
+            "url": format!("{base}/chall/{order_id}/{index}"),
+            "status": "valid",
+            "token": format!("{order_id}{index}"),
+            "validated": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+        if challenge {
+            return Ok(Reply::json(StatusCode::OK, &challenge_json).up(authz_url));
+        }
+
+        let (value, wildcard) = name
+            .strip_prefix("*.")
+            .map_or((name.as_str(), false), |base| (base, true));
+        let mut json = json!({
+            "status": "valid",
+            "expires": order.expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "identifier": { "type": "dns", "value": value },
+            "challenges": [challenge_json],
+        });
+        if wildcard {
+            json["wildcard"] = json!(true);
+        }
+        Ok(Reply::json(StatusCode::OK, &json))
+    }
+
+    fn finalize(&self, endpoint: &Endpoint, id: &str, body: &[u8]) -> Result<Reply, Problem> {
+        #[derive(Deserialize)]
+        struct Finalize {
+            csr: String,
+        }
+
+        let signed = self.verify(endpoint, body)?;
+        let mut order = self.owned_order(&signed, id)?;
+        if order.certificate.is_some() {
+            return Err(Problem {
+                status: StatusCode::FORBIDDEN,
+                ..Problem::new("orderNotReady", "The order is already finalized")
+            });
+        }
+        let request: Finalize = signed.payload()?;
+        let csr = SigningRequest::from_der(&jws::decode(&request.csr)?)
+            .map_err(|e| Problem::new("badCSR", format!("{e:#}")))?;
+
+        let mut requested = csr.names.clone();
+        requested.sort();
+        requested.dedup();
+        let mut ordered = order.names.clone();
+        ordered.sort();
+        if requested != ordered {
+            return Err(Problem::new(
+                "badCSR",
+                format!(
+                    "The CSR asks for {}, but the order is for {}",
+                    requested.join(", "),
+                    ordered.join(", ")
+                ),
+            ));
+        }
+
+        let certs = self.certs.as_ref().ok_or_else(|| Problem {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            ..Problem::new("serverInternal", "HTTPS is not set up; run `locald trust`")
+        })?;
+        let certificate = certs.sign(csr, crate::tls::VALIDITY).map_err(|e| Problem {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            ..Problem::new("serverInternal", format!("{e:#}"))
+        })?;
+
+        order.certificate = Some(certificate);
+        let json = Self::order_json(endpoint, id, &order);
+        self.store().orders.insert(id.to_string(), order);
+        Ok(Reply::json(StatusCode::OK, &json).at(format!("{}/order/{id}", endpoint.base)))
+    }
+
+    fn certificate(&self, endpoint: &Endpoint, id: &str, body: &[u8]) -> Result<Reply, Problem> {
+        let signed = self.verify(endpoint, body)?;
+        let certificate = self
+            .owned_order(&signed, id)?
+            .certificate
+            .ok_or_else(|| Problem::not_found("certificate"))?;
+        Ok(Reply {
+            status: StatusCode::OK,
+            location: None,
+            up: None,
+            content_type: "application/pem-certificate-chain",
+            body: certificate.into_bytes(),
+        })
+    }
+
+    /// locald doesn't publish revocation lists, so revocation is only acknowledged.
+    fn revoke(&self, endpoint: &Endpoint, body: &[u8]) -> Result<Reply, Problem> {
+        self.verify(endpoint, body)?;
+        Ok(Reply::json(StatusCode::OK, &json!({})))
+    }
+}
+
+/// Whether locald issues certificates for `name`: `localhost`, anything under
+/// `.localhost`, or an allowed domain or subdomain of one. Names (or wildcard bases)
+/// that are a single label or a public suffix never qualify.
+fn is_controlled(name: &str, domains: &[String]) -> bool {
+    let name = name.strip_prefix("*.").unwrap_or(name);
+    if name == "localhost" || name.ends_with(".localhost") {
+        return true;
+    }
+    !locald_utils::domain::is_public_suffix(name)
+        && domains.iter().any(|domain| {
+            name == domain
+                || name
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+}
+
+/// Turns away clients that aren't on this machine, or whose address is unknown.
+async fn require_loopback(request: Request, next: Next) -> Response {
+    let loopback = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .is_some_and(|ConnectInfo(peer)| peer.ip().is_loopback());
+    if !loopback {
+        return Problem::unauthorized("The ACME server only accepts clients on this machine")
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn handle_directory(endpoint: Endpoint) -> impl IntoResponse {
+    let base = &endpoint.base;
+    axum::Json(json!({
+        "newNonce": format!("{base}/new-nonce"),
+        "newAccount": format!("{base}/new-account"),
+        "newOrder": format!("{base}/new-order"),
+        "revokeCert": format!("{base}/revoke-cert"),
+        "meta": { "externalAccountRequired": false },
+    }))
+}
+
+async fn handle_new_nonce(
+    State(acme): State<Acme>,
+    endpoint: Endpoint,
+    method: Method,
+) -> Response {
+    let status = if method == Method::HEAD {
+        StatusCode::OK
+    } else {
+        StatusCode::NO_CONTENT
+    };
+    acme.respond(
+        &endpoint,
+        Ok(Reply {
+            status,
+            location: None,
+            up: None,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }),
+    )
+}
+
+async fn handle_new_account(State(acme): State<Acme>, endpoint: Endpoint, body: Bytes) -> Response {
+    let result = acme.new_account(&endpoint, &body).await;
+    acme.respond(&endpoint, result)
+}
+
+async fn handle_account(
+    State(acme): State<Acme>,
+    Path(id): Path<String>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    let result = acme.account(&endpoint, &id, &body).await;
+    acme.respond(&endpoint, result)
+}
+
+async fn handle_new_order(State(acme): State<Acme>, endpoint: Endpoint, body: Bytes) -> Response {
+    let result = acme.new_order(&endpoint, &body);
+    acme.respond(&endpoint, result)
+}
+
+async fn handle_order(
+    State(acme): State<Acme>,
+    Path(id): Path<String>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    acme.respond(&endpoint, acme.order(&endpoint, &id, &body))
+}
+
+async fn handle_finalize(
+    State(acme): State<Acme>,
+    Path(id): Path<String>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    acme.respond(&endpoint, acme.finalize(&endpoint, &id, &body))
+}
+
+async fn handle_authorization(
+    State(acme): State<Acme>,
+    Path(path): Path<(String, usize)>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    acme.respond(&endpoint, acme.authorization(&endpoint, path, &body, false))
+}
+
+async fn handle_challenge(
+    State(acme): State<Acme>,
+    Path(path): Path<(String, usize)>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    acme.respond(&endpoint, acme.authorization(&endpoint, path, &body, true))
+}
+
+async fn handle_certificate(
+    State(acme): State<Acme>,
+    Path(id): Path<String>,
+    endpoint: Endpoint,
+    body: Bytes,
+) -> Response {
+    acme.respond(&endpoint, acme.certificate(&endpoint, &id, &body))
+}
+
+async fn handle_revoke(State(acme): State<Acme>, endpoint: Endpoint, body: Bytes) -> Response {
+    acme.respond(&endpoint, acme.revoke(&endpoint, &body))
+}
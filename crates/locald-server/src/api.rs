@@ -1,11 +1,11 @@
 use axum::{
     Router,
-    extract::{Path, State, WebSocketUpgrade, ws::WebSocket},
+    extract::{Path, Query, State, WebSocketUpgrade, ws::WebSocket},
     response::{
         IntoResponse,
         sse::{Event as SseEvent, Sse},
     },
-    routing::{get, post},
+    routing::{get, post, put},
 };
 use futures_util::{SinkExt, StreamExt, stream::Stream};
 use hyper::StatusCode;
@@ -13,10 +13,13 @@ use serde::Deserialize;
 use std::convert::Infallible;
 use std::sync::Arc;
 
+use crate::acme::Acme;
+use crate::log_store::LogFilter;
 use crate::manager::ProcessManager;
+use locald_core::config::FaultConfig;
 use locald_core::ipc::Event;
 
-pub fn router(pm: ProcessManager) -> Router {
+pub fn router(pm: ProcessManager, acme: Acme) -> Router {
     Router::new()
         .route("/state", get(handle_state))
         .route("/logs", get(handle_ws))
@@ -28,8 +31,27 @@ pub fn router(pm: ProcessManager) -> Router {
         .route("/services/:name/restart", post(handle_service_restart))
         .route("/services/:name/reset", post(handle_service_reset))
         .route("/services/:name/pty", get(handle_pty_ws))
+        .route(
+            "/services/:name/requests",
+            get(handle_requests_list).delete(handle_requests_clear),
+        )
+        .route(
+            "/services/:name/requests/capture",
+            put(handle_requests_capture),
+        )
+        .route("/services/:name/requests/:id", get(handle_request_get))
+        .route(
+            "/services/:name/requests/:id/replay",
+            post(handle_request_replay),
+        )
+        .route("/faults", get(handle_faults_list))
+        .route(
+            "/services/:name/faults",
+            put(handle_faults_set).delete(handle_faults_reset),
+        )
         .route("/services/:name", get(handle_service_inspect))
         .with_state(Arc::new(pm))
+        .nest("/acme", acme.router())
 }
 
 #[derive(Deserialize)]
@@ -118,11 +140,50 @@ async fn handle_state(State(pm): State<Arc<ProcessManager>>) -> impl IntoRespons
     axum::Json(services)
 }
 
+/// Filters for the `/api/logs` websocket, e.g. `?service=shop:api&level=warn&where=user_id=42`.
+#[derive(Debug, Deserialize)]
+struct LogsParams {
+    service: Option<String>,
+    /// The minimum level of structured entries.
+    level: Option<String>,
+    /// Comma-separated `key=value` pairs that structured entries must have.
+    #[serde(rename = "where")]
+    fields: Option<String>,
+}
+
+impl LogsParams {
+    fn into_filter(self) -> Result<LogFilter, String> {
+        let level = self.level.as_deref().map(str::parse).transpose()?;
+        let fields = self
+            .fields
+            .iter()
+            .flat_map(|f| f.split(','))
+            .map(|pair| {
+                pair.split_once('=')
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .ok_or_else(|| format!("expected key=value in 'where', got '{pair}'"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(LogFilter {
+            service: self.service,
+            level,
+            fields,
+            ..LogFilter::default()
+        })
+    }
+}
+
 async fn handle_ws(
     ws: WebSocketUpgrade,
+    Query(params): Query<LogsParams>,
     State(pm): State<Arc<ProcessManager>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, pm))
+    match params.into_filter() {
+        Ok(filter) => ws
+            .on_upgrade(move |socket| handle_socket(socket, pm, filter))
+            .into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
 }
 
 async fn handle_events(
@@ -148,10 +209,10 @@ async fn handle_events(
     Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
 }
 
-async fn handle_socket(mut socket: WebSocket, pm: Arc<ProcessManager>) {
+async fn handle_socket(mut socket: WebSocket, pm: Arc<ProcessManager>, filter: LogFilter) {
     let mut rx = pm.log_sender.subscribe();
     let recent = pm.get_recent_logs();
-    for entry in recent {
+    for entry in recent.into_iter().filter(|e| filter.matches(e)) {
         if let Ok(msg) = serde_json::to_string(&entry)
             && socket
                 .send(axum::extract::ws::Message::Text(msg))
@@ -163,6 +224,9 @@ async fn handle_socket(mut socket: WebSocket, pm: Arc<ProcessManager>) {
     }
 
     while let Ok(entry) = rx.recv().await {
+        if !filter.matches(&entry) {
+            continue;
+        }
         if let Ok(msg) = serde_json::to_string(&entry)
             && socket
                 .send(axum::extract::ws::Message::Text(msg))
@@ -249,3 +313,87 @@ async fn handle_service_inspect(
         Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
 }
+
+async fn handle_requests_list(
+    Path(name): Path<String>,
+    State(pm): State<Arc<ProcessManager>>,
+) -> impl IntoResponse {
+    axum::Json(pm.request_inspector().list(&name))
+}
+
+async fn handle_requests_clear(
+    Path(name): Path<String>,
+    State(pm): State<Arc<ProcessManager>>,
+) -> impl IntoResponse {
+    pm.request_inspector().clear(&name);
+    StatusCode::OK
+}
+
+#[derive(Deserialize)]
+struct CaptureParams {
+    enabled: bool,
+}
+
+async fn handle_requests_capture(
+    Path(name): Path<String>,
+    State(pm): State<Arc<ProcessManager>>,
+    axum::Json(params): axum::Json<CaptureParams>,
+) -> impl IntoResponse {
+    pm.request_inspector().set_enabled(&name, params.enabled);
+    StatusCode::OK
+}
+
+async fn handle_request_get(
+    Path((name, id)): Path<(String, u64)>,
+    State(pm): State<Arc<ProcessManager>>,
+) -> impl IntoResponse {
+    pm.request_inspector().get(&name, id).map_or_else(
+        || (StatusCode::NOT_FOUND, "Request not found").into_response(),
+        |request| axum::Json(request).into_response(),
+    )
+}
+
+async fn handle_request_replay(
+    Path((name, id)): Path<(String, u64)>,
+    State(pm): State<Arc<ProcessManager>>,
+) -> impl IntoResponse {
+    match pm.request_inspector().replay(&*pm, &name, id).await {
+        Ok(request) => axum::Json(request).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    }
+}
+
+async fn handle_faults_list(State(pm): State<Arc<ProcessManager>>) -> impl IntoResponse {
+    axum::Json(pm.list_faults().await)
+}
+
+#[derive(Deserialize)]
+struct FaultParams {
+    enabled: bool,
+    #[serde(default)]
+    rules: Option<FaultConfig>,
+}
+
+async fn handle_faults_set(
+    Path(name): Path<String>,
+    State(pm): State<Arc<ProcessManager>>,
+    axum::Json(params): axum::Json<FaultParams>,
+) -> impl IntoResponse {
+    match pm
+        .set_faults(&name, Some(params.enabled), params.rules)
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn handle_faults_reset(
+    Path(name): Path<String>,
+    State(pm): State<Arc<ProcessManager>>,
+) -> impl IntoResponse {
+    match pm.set_faults(&name, None, None).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,1562 @@
+//! Converts a Docker Compose file into a `locald.toml` (`locald import compose`).
+//!
+//! Each compose service becomes:
+//!
+//! - a `postgres` service if it runs the official `postgres` image,
+//! - an `exec` service if compose builds it from source (locald runs it on the host),
+//! - a `container` service otherwise.
+//!
+//! Whatever has no locald equivalent is left out and reported as an [`ImportNote`],
+//! so the result is a starting point to review rather than a silent approximation.
+
+use crate::service::exec::parse_stop_signal;
+use anyhow::{Context, Result};
+use locald_core::config::{
+    CommonServiceConfig, ContainerServiceConfig, Dependency, DependencyCondition, DependencyConfig,
+    ExecServiceConfig, ExposeConfig, HealthCheckConfig, LocaldConfig, PostgresServiceConfig,
+    PostgresSetting, ProbeConfig, ProbeType, ProjectConfig, RestartConfig, RestartMode,
+    RestartPolicy, ServiceConfig, TypedServiceConfig,
+};
+use regex::Regex;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// The file names `docker compose` looks for, in its order of preference.
+pub const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// Where the `postgres` image looks for init scripts.
+const INITDB_DIR: &str = "/docker-entrypoint-initdb.d";
+
+/// Something in the compose file that wasn't carried over to `locald.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportNote {
+    /// The compose service it's about, if any.
+    pub service: Option<String>,
+    /// The compose key, e.g. `volumes`.
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ImportNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.service {
+            Some(service) => write!(f, "services.{service}.{}: {}", self.key, self.message),
+            None => write!(f, "{}: {}", self.key, self.message),
+        }
+    }
+}
+
+/// The result of converting a compose file.
+#[derive(Debug)]
+pub struct ComposeImport {
+    pub config: LocaldConfig,
+    pub notes: Vec<ImportNote>,
+}
+
+impl ComposeImport {
+    /// The config as `locald.toml` content, with tables and keys in a stable order.
+    pub fn to_toml(&self) -> Result<String> {
+        let value = toml::Value::try_from(&self.config)?;
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    /// Replaces directories mounted into a Postgres service's init directory with the
+    /// `.sql` files in them, in the order the `postgres` image runs them.
+    async fn expand_init_dirs(&mut self, dir: &Path) {
+        for (name, service) in &mut self.config.services {
+            let ServiceConfig::Typed(TypedServiceConfig::Postgres(postgres)) = service else {
+                continue;
+            };
+            let mut scripts = Vec::new();
+            for script in std::mem::take(&mut postgres.init_scripts) {
+                let is_dir = tokio::fs::metadata(dir.join(&script))
+                    .await
+                    .is_ok_and(|m| m.is_dir());
+                if !is_dir {
+                    scripts.push(script);
+                    continue;
+                }
+
+                let mut files = Vec::new();
+                if let Ok(mut entries) = tokio::fs::read_dir(dir.join(&script)).await {
+                    while let Ok(Some(entry)) = entries.next_entry().await {
+                        files.push(entry.file_name().to_string_lossy().into_owned());
+                    }
+                }
+                files.sort();
+                for file in files {
+                    let path = format!("{}/{file}", script.trim_end_matches('/'));
+                    if is_sql(&file) {
+                        scripts.push(path);
+                    } else {
+                        self.notes.push(ImportNote {
+                            service: Some(name.clone()),
+                            key: "volumes".to_string(),
+                            message: format!(
+                                "`{path}` was not added to `init_scripts`; locald only runs `.sql` init scripts"
+                            ),
+                        });
+                    }
+                }
+            }
+            postgres.init_scripts = scripts;
+        }
+    }
+}
+
+/// Reads and converts the compose file at `path`. The project is named after the
+/// compose project, or else the directory the file is in.
+pub async fn import_compose_file(path: &Path) -> Result<ComposeImport> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut import = import_compose(&content, dir)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    import.expand_init_dirs(dir).await;
+    Ok(import)
+}
+
+/// Converts the content of a compose file in `dir`.
+pub fn import_compose(content: &str, dir: &Path) -> Result<ComposeImport> {
+    let compose: ComposeFile = serde_yaml::from_str(content)?;
+    let mut importer = Importer::new(&compose);
+
+    for (key, value) in &compose.other {
+        importer.top_level_key(key, value);
+    }
+
+    let mut services = HashMap::new();
+    for (name, service) in &compose.services {
+        if let Some(config) = importer.service(name, service) {
+            services.insert(name.clone(), config);
+        }
+    }
+
+    let name = compose.name.clone().unwrap_or_else(|| {
+        dir.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("app")
+            .to_string()
+    });
+
+    Ok(ComposeImport {
+        config: LocaldConfig {
+            project: ProjectConfig {
+                name,
+                domain: None,
+                workspace: None,
+                constellation: None,
+            },
+            services,
+            routes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        },
+        notes: importer.notes,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ComposeFile {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    services: BTreeMap<String, ComposeService>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ComposeService {
+    image: Option<String>,
+    build: Option<Value>,
+    command: Option<Value>,
+    environment: Option<Value>,
+    ports: Vec<Value>,
+    expose: Vec<Value>,
+    depends_on: Option<Value>,
+    healthcheck: Option<ComposeHealthcheck>,
+    volumes: Vec<Value>,
+    working_dir: Option<String>,
+    restart: Option<String>,
+    stop_signal: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ComposeHealthcheck {
+    test: Option<Value>,
+    interval: Option<String>,
+    timeout: Option<String>,
+    disable: bool,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Postgres,
+    Exec,
+    Container,
+}
+
+impl Kind {
+    fn of(service: &ComposeService) -> Option<Self> {
+        if service.build.is_some() {
+            Some(Self::Exec)
+        } else if service.image.as_deref().is_some_and(is_postgres_image) {
+            Some(Self::Postgres)
+        } else if service.image.is_some() {
+            Some(Self::Container)
+        } else {
+            None
+        }
+    }
+}
+
+/// A published port: `published:target/protocol`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PortMapping {
+    published: Option<u16>,
+    target: u16,
+    udp: bool,
+}
+
+/// A volume: `source:target`. `bind` is set for host paths, as opposed to named volumes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mount {
+    source: Option<String>,
+    target: String,
+    bind: bool,
+}
+
+struct Importer<'a> {
+    compose: &'a ComposeFile,
+    notes: Vec<ImportNote>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(compose: &'a ComposeFile) -> Self {
+        Self {
+            compose,
+            notes: Vec::new(),
+        }
+    }
+
+    fn note(&mut self, service: Option<&str>, key: &str, message: impl Into<String>) {
+        self.notes.push(ImportNote {
+            service: service.map(str::to_string),
+            key: key.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn top_level_key(&mut self, key: &str, value: &Value) {
+        // `version` is obsolete, and `x-*` keys only hold YAML anchors
+        if key == "version" || key.starts_with("x-") {
+            return;
+        }
+        let message = match key {
+            "volumes" | "networks" | "secrets" | "configs" if is_empty(value) => return,
+            "volumes" => "named volumes were not imported; locald keeps each service's data itself",
+            "networks" => {
+                "networks were not imported; locald services reach each other on localhost"
+            }
+            "secrets" | "configs" => {
+                "locald has no equivalent; pass the values through `env` instead"
+            }
+            "include" => "included compose files were not imported; import each of them on its own",
+            _ => "unknown key, left out",
+        };
+        self.note(None, key, message);
+    }
+
+    fn service(&mut self, name: &str, service: &ComposeService) -> Option<ServiceConfig> {
+        let Some(kind) = Kind::of(service) else {
+            self.note(
+                Some(name),
+                "image",
+                "has neither `image` nor `build`, so it was left out",
+            );
+            return None;
+        };
+
+        let mut common = CommonServiceConfig {
+            depends_on: self.depends_on(name, service.depends_on.as_ref()),
+            restart: self.restart(name, service.restart.as_deref()),
+            stop_signal: self.stop_signal(name, service.stop_signal.as_deref()),
+            ..CommonServiceConfig::default()
+        };
+        for (key, value) in &service.other {
+            self.service_key(name, key, value);
+        }
+        if let Some(image) = &service.image {
+            if image.contains('$') {
+                self.note(
+                    Some(name),
+                    "image",
+                    format!(
+                        "`{image}` uses a variable, which locald doesn't substitute in `image`"
+                    ),
+                );
+            }
+        }
+
+        let env = self.environment(name, service.environment.as_ref());
+        let ports = self.ports(name, service);
+        let primary = ports
+            .iter()
+            .find(|p| !p.udp)
+            .or_else(|| ports.first())
+            .copied();
+        for extra in ports.iter().filter(|p| Some(**p) != primary) {
+            self.note(
+                Some(name),
+                "ports",
+                format!(
+                    "{} was not imported; a locald service has one port",
+                    describe_port(*extra)
+                ),
+            );
+        }
+
+        let config = match kind {
+            Kind::Postgres => {
+                let mut postgres = self.postgres(name, service, env);
+                common.expose = primary.and_then(expose);
+                postgres.common = common;
+                TypedServiceConfig::Postgres(postgres)
+            }
+            Kind::Exec => {
+                common.env = env;
+                common.port = primary.map(|p| p.target);
+                common.expose = primary
+                    .filter(|p| p.published != Some(p.target))
+                    .and_then(expose);
+                common.health_check = self.healthcheck(name, kind, service, common.port);
+                TypedServiceConfig::Exec(self.exec(name, service, common))
+            }
+            Kind::Container => {
+                common.env = env;
+                common.expose = primary.and_then(expose);
+                let container_port = primary.map(|p| p.target);
+                common.health_check = self.healthcheck(name, kind, service, container_port);
+                for mount in self.mounts(name, &service.volumes) {
+                    self.note(
+                        Some(name),
+                        "volumes",
+                        format!(
+                            "{} was not imported; container services can't mount volumes",
+                            describe_mount(&mount)
+                        ),
+                    );
+                }
+                TypedServiceConfig::Container(ContainerServiceConfig {
+                    common,
+                    image: service.image.clone().unwrap_or_default(),
+                    command: service
+                        .command
+                        .as_ref()
+                        .map(|c| translate_vars(&command_string(c))),
+                    container_port,
+                    workdir: service.working_dir.clone(),
+                })
+            }
+        };
+
+        if service.other.contains_key("entrypoint") {
+            self.note(
+                Some(name),
+                "entrypoint",
+                "locald has no `entrypoint`; fold it into `command` if it matters",
+            );
+        }
+
+        Some(ServiceConfig::Typed(config))
+    }
+
+    /// Keys locald has no equivalent for.
+    fn service_key(&mut self, name: &str, key: &str, value: &Value) {
+        let message = match key {
+            // Compose-only plumbing that locald doesn't need
+            "container_name" | "networks" | "hostname" | "platform" | "pull_policy" => return,
+            _ if key.starts_with("x-") => return,
+            // Handled with `command`
+            "entrypoint" => return,
+            "env_file" if is_dotenv(value) => return,
+            "env_file" => {
+                "locald only reads `.env` from the project directory; move these variables into `env`"
+            }
+            "profiles" => "compose profiles were not imported; see `[profiles]` in locald.toml",
+            "deploy" | "scale" => "locald runs one instance of each service",
+            _ => "locald has no equivalent, so it was left out",
+        };
+        self.note(Some(name), key, message);
+    }
+
+    fn postgres(
+        &mut self,
+        name: &str,
+        service: &ComposeService,
+        env: HashMap<String, String>,
+    ) -> PostgresServiceConfig {
+        let mut postgres = PostgresServiceConfig {
+            version: service.image.as_deref().and_then(postgres_version),
+            ..PostgresServiceConfig::default()
+        };
+
+        let mut env: Vec<_> = env.into_iter().collect();
+        env.sort();
+        for (key, value) in env {
+            match key.as_str() {
+                "POSTGRES_DB" => postgres.databases.push(value),
+                "POSTGRES_USER" | "POSTGRES_PASSWORD" => {
+                    let field = if key == "POSTGRES_USER" {
+                        "user"
+                    } else {
+                        "password"
+                    };
+                    self.note(
+                        Some(name),
+                        &format!("environment.{key}"),
+                        format!(
+                            "locald's Postgres always uses user and password `postgres`; refer to it as `${{services.{name}.{field}}}`"
+                        ),
+                    );
+                }
+                _ => self.note(
+                    Some(name),
+                    &format!("environment.{key}"),
+                    "locald configures Postgres itself, so this was left out",
+                ),
+            }
+        }
+
+        if let Some(command) = &service.command {
+            let command = command_string(command);
+            let args = shlex::split(&command).unwrap_or_default();
+            let mut args = args.iter();
+            let mut ignored = Vec::new();
+            while let Some(arg) = args.next() {
+                let setting = if arg == "-c" {
+                    args.next().map(String::as_str)
+                } else {
+                    arg.strip_prefix("-c")
+                };
+                match setting.and_then(|s| s.split_once('=')) {
+                    Some((key, value)) => {
+                        postgres
+                            .settings
+                            .insert(key.to_string(), postgres_setting(value));
+                    }
+                    None if arg == "postgres" => {}
+                    None => ignored.push(arg.as_str()),
+                }
+            }
+            if !ignored.is_empty() {
+                self.note(
+                    Some(name),
+                    "command",
+                    format!(
+                        "`{}` was not imported; only `-c name=value` settings are",
+                        ignored.join(" ")
+                    ),
+                );
+            }
+        }
+
+        for mount in self.mounts(name, &service.volumes) {
+            let init_target = mount
+                .target
+                .strip_prefix(INITDB_DIR)
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'));
+            match (init_target, &mount.source) {
+                (Some(rest), Some(source)) if mount.bind => {
+                    if rest.trim_matches('/').is_empty() || is_sql(rest) {
+                        postgres.init_scripts.push(source.clone());
+                    } else {
+                        self.note(
+                            Some(name),
+                            "volumes",
+                            format!(
+                                "`{source}` was not added to `init_scripts`; locald only runs `.sql` init scripts"
+                            ),
+                        );
+                    }
+                }
+                _ => self.note(
+                    Some(name),
+                    "volumes",
+                    format!(
+                        "{} was not imported; locald keeps each Postgres service's data in its own directory",
+                        describe_mount(&mount)
+                    ),
+                ),
+            }
+        }
+
+        // locald waits for Postgres to accept connections on its own
+        if let Some(healthcheck) = &service.healthcheck {
+            self.healthcheck_extras(name, healthcheck);
+        }
+
+        postgres
+    }
+
+    fn exec(
+        &mut self,
+        name: &str,
+        service: &ComposeService,
+        common: CommonServiceConfig,
+    ) -> ExecServiceConfig {
+        let context = service
+            .build
+            .as_ref()
+            .and_then(|build| match build {
+                Value::String(context) => Some(context.clone()),
+                Value::Mapping(build) => build
+                    .get("context")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::Sequence(_)
+                | Value::Tagged(_) => None,
+            })
+            .map_or_else(|| ".".to_string(), |c| relative_path(&c));
+        let workdir = (context != ".").then(|| context.clone());
+
+        let command = service
+            .command
+            .as_ref()
+            .map(|c| translate_vars(&command_string(c)));
+        if command.is_some() {
+            self.note(
+                Some(name),
+                "build",
+                format!(
+                    "locald runs `command` on the host in `{context}` instead of building an image; check that it starts the service there"
+                ),
+            );
+        } else {
+            self.note(
+                Some(name),
+                "build",
+                format!(
+                    "locald runs services built from source on the host in `{context}`; set `command` to what starts it"
+                ),
+            );
+        }
+        if let Some(working_dir) = &service.working_dir {
+            self.note(
+                Some(name),
+                "working_dir",
+                format!("`{working_dir}` is a path in the image; the service runs in `{context}`"),
+            );
+        }
+        for mount in self.mounts(name, &service.volumes) {
+            let is_context = mount.bind && mount.source.as_deref() == Some(context.as_str());
+            if !is_context {
+                self.note(
+                    Some(name),
+                    "volumes",
+                    format!(
+                        "{} was not imported; the service runs on the host and sees its files directly",
+                        describe_mount(&mount)
+                    ),
+                );
+            }
+        }
+
+        ExecServiceConfig {
+            common,
+            command,
+            image: None,
+            container_port: None,
+            workdir,
+            build: None,
+            watch: None,
+        }
+    }
+
+    fn environment(&mut self, name: &str, environment: Option<&Value>) -> HashMap<String, String> {
+        let mut env = HashMap::new();
+        let mut add = |key: &str, value: Option<String>| {
+            // `KEY` alone passes the variable through from the shell
+            let value = value.map_or_else(
+                || format!("${{env.{key}}}"),
+                |value| self.rewrite_hosts(&translate_vars(&value)),
+            );
+            env.insert(key.to_string(), value);
+        };
+
+        match environment {
+            Some(Value::Mapping(map)) => {
+                for (key, value) in map {
+                    if let Some(key) = scalar(key) {
+                        add(&key, scalar(value));
+                    }
+                }
+            }
+            Some(Value::Sequence(list)) => {
+                for entry in list.iter().filter_map(scalar) {
+                    match entry.split_once('=') {
+                        Some((key, value)) => add(key, Some(value.to_string())),
+                        None => add(&entry, None),
+                    }
+                }
+            }
+            None => {}
+            Some(_) => self.note(
+                Some(name),
+                "environment",
+                "expected a map or a list, so it was left out",
+            ),
+        }
+        env
+    }
+
+    /// Rewrites addresses of other compose services (`db:5432`, `redis://cache:6379`)
+    /// into references, since locald services don't reach each other by name.
+    fn rewrite_hosts(&self, value: &str) -> String {
+        if let Some(reference) = self.postgres_url(value) {
+            return reference;
+        }
+        if self.compose.services.contains_key(value) {
+            return format!("${{services.{value}.host}}");
+        }
+
+        let mut value = value.to_string();
+        for name in self.compose.services.keys() {
+            let Ok(address) = Regex::new(&format!(r"(^|[/@\s=,]){}:\d+\b", regex::escape(name)))
+            else {
+                continue;
+            };
+            value = address
+                .replace_all(
+                    &value,
+                    format!("${{1}}$${{services.{name}.host}}:$${{services.{name}.port}}"),
+                )
+                .into_owned();
+        }
+        value
+    }
+
+    /// `postgres://user:password@db:5432/app` for a Postgres service `db` becomes
+    /// `${services.db.url}` (or the URL of the `app` database), with locald's credentials.
+    fn postgres_url(&self, value: &str) -> Option<String> {
+        let url = Regex::new(
+            r"^postgres(?:ql)?://(?:[^@/]*@)?([A-Za-z0-9_.-]+)(?::\d+)?(?:/([^?]*))?(\?.*)?$",
+        )
+        .ok()?;
+        let captures = url.captures(value)?;
+        let host = captures.get(1)?.as_str();
+        let service = self.compose.services.get(host)?;
+        if Kind::of(service) != Some(Kind::Postgres) {
+            return None;
+        }
+
+        let databases = postgres_databases(service);
+        let database = captures.get(2).map_or("", |m| m.as_str());
+        let query = captures.get(3).map_or("", |m| m.as_str());
+        let default = databases.first().map_or("postgres", String::as_str);
+        if database.is_empty() || database == default {
+            Some(format!("${{services.{host}.url}}{query}"))
+        } else if databases.iter().any(|d| d == database) {
+            Some(format!(
+                "${{services.{host}.databases.{database}.url}}{query}"
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn ports(&mut self, name: &str, service: &ComposeService) -> Vec<PortMapping> {
+        let mut ports = Vec::new();
+        for port in &service.ports {
+            match parse_port(port) {
+                Some(mapping) => ports.push(mapping),
+                None => self.note(
+                    Some(name),
+                    "ports",
+                    format!(
+                        "`{}` was not imported; only single ports without variables are supported",
+                        scalar(port).unwrap_or_else(|| "(long syntax)".to_string())
+                    ),
+                ),
+            }
+        }
+        // `expose` lists ports only other services can reach; that's still the port
+        // the service listens on
+        if ports.is_empty() {
+            ports.extend(
+                service
+                    .expose
+                    .iter()
+                    .filter_map(parse_port)
+                    .map(|p| PortMapping {
+                        published: None,
+                        ..p
+                    })
+                    .take(1),
+            );
+        }
+        ports
+    }
+
+    fn mounts(&mut self, name: &str, volumes: &[Value]) -> Vec<Mount> {
+        let mut mounts = Vec::new();
+        for volume in volumes {
+            match parse_mount(volume) {
+                Some(mount) => mounts.push(mount),
+                None => self.note(
+                    Some(name),
+                    "volumes",
+                    "a volume couldn't be read, so it was left out",
+                ),
+            }
+        }
+        mounts
+    }
+
+    /// The dependencies of `name`, minus those on services that weren't imported.
+    fn depends_on(&mut self, name: &str, depends_on: Option<&Value>) -> Vec<Dependency> {
+        let mut dependencies = self.dependencies(name, depends_on);
+        dependencies.retain(|dependency| {
+            let service = dependency.service();
+            let imported = self
+                .compose
+                .services
+                .get(service)
+                .and_then(Kind::of)
+                .is_some();
+            if !imported {
+                self.note(
+                    Some(name),
+                    &format!("depends_on.{service}"),
+                    format!("`{service}` was not imported, so this dependency was left out"),
+                );
+            }
+            imported
+        });
+        dependencies
+    }
+
+    fn dependencies(&mut self, name: &str, depends_on: Option<&Value>) -> Vec<Dependency> {
+        match depends_on {
+            Some(Value::Sequence(list)) => list
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|s| Dependency::Service(s.to_string()))
+                .collect(),
+            Some(Value::Mapping(map)) => {
+                let mut dependencies = Vec::new();
+                for (service, options) in map {
+                    let Some(service) = service.as_str() else {
+                        continue;
+                    };
+                    let condition = options
+                        .get("condition")
+                        .and_then(Value::as_str)
+                        .unwrap_or("service_started");
+                    let condition = match condition {
+                        "service_started" => DependencyCondition::Started,
+                        "service_healthy" => DependencyCondition::Healthy,
+                        "service_completed_successfully" => DependencyCondition::Completed,
+                        other => {
+                            self.note(
+                                Some(name),
+                                &format!("depends_on.{service}.condition"),
+                                format!(
+                                    "unknown condition `{other}`; waiting for it to start instead"
+                                ),
+                            );
+                            DependencyCondition::Started
+                        }
+                    };
+                    if options.get("required").and_then(Value::as_bool) == Some(false) {
+                        self.note(
+                            Some(name),
+                            &format!("depends_on.{service}.required"),
+                            "locald dependencies are always required",
+                        );
+                    }
+                    dependencies.push(match condition {
+                        DependencyCondition::Started => Dependency::Service(service.to_string()),
+                        DependencyCondition::Healthy | DependencyCondition::Completed => {
+                            Dependency::Detailed(DependencyConfig {
+                                service: service.to_string(),
+                                condition,
+                                timeout: None,
+                            })
+                        }
+                    });
+                }
+                dependencies
+            }
+            None => Vec::new(),
+            Some(_) => {
+                self.note(
+                    Some(name),
+                    "depends_on",
+                    "expected a list or a map, so it was left out",
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn healthcheck(
+        &mut self,
+        name: &str,
+        kind: Kind,
+        service: &ComposeService,
+        port: Option<u16>,
+    ) -> Option<HealthCheckConfig> {
+        let healthcheck = service.healthcheck.as_ref()?;
+        self.healthcheck_extras(name, healthcheck);
+        if healthcheck.disable {
+            return None;
+        }
+
+        let command = match healthcheck.test.as_ref()? {
+            Value::String(command) => command.clone(),
+            Value::Sequence(test) => {
+                let test: Vec<String> = test.iter().filter_map(scalar).collect();
+                match test.split_first() {
+                    Some((first, _)) if first == "NONE" => return None,
+                    Some((first, args)) if first == "CMD" => {
+                        shlex::try_join(args.iter().map(String::as_str))
+                            .unwrap_or_else(|_| args.join(" "))
+                    }
+                    Some((first, args)) if first == "CMD-SHELL" => args.join(" "),
+                    _ => test.join(" "),
+                }
+            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Number(_)
+            | Value::Mapping(_)
+            | Value::Tagged(_) => {
+                self.note(
+                    Some(name),
+                    "healthcheck.test",
+                    "expected a string or a list, so it was left out",
+                );
+                return None;
+            }
+        };
+        let command = translate_vars(&command);
+        let interval = self.duration(
+            name,
+            "healthcheck.interval",
+            healthcheck.interval.as_deref(),
+        );
+        let timeout = self.duration(name, "healthcheck.timeout", healthcheck.timeout.as_deref());
+
+        // A check run inside the container runs on the host here, where the container's
+        // port is mapped somewhere else, so HTTP checks of it become HTTP probes
+        if kind == Kind::Container {
+            if let Some(path) = port.and_then(|port| http_check_path(&command, port)) {
+                return Some(HealthCheckConfig::Probe(ProbeConfig {
+                    kind: ProbeType::Http,
+                    path: Some(path),
+                    interval,
+                    timeout,
+                    command: None,
+                }));
+            }
+            self.note(
+                Some(name),
+                "healthcheck.test",
+                "this command ran inside the container; locald runs it on the host, so check that it still works there",
+            );
+        }
+
+        Some(HealthCheckConfig::Probe(ProbeConfig {
+            kind: ProbeType::Command,
+            path: None,
+            interval,
+            timeout,
+            command: Some(command),
+        }))
+    }
+
+    /// Notes the healthcheck options locald doesn't have.
+    fn healthcheck_extras(&mut self, name: &str, healthcheck: &ComposeHealthcheck) {
+        for key in healthcheck.other.keys() {
+            self.note(
+                Some(name),
+                &format!("healthcheck.{key}"),
+                "locald has no equivalent, so it was left out",
+            );
+        }
+    }
+
+    fn duration(&mut self, name: &str, key: &str, duration: Option<&str>) -> Option<u64> {
+        let duration = duration?;
+        let seconds = parse_duration(duration);
+        if seconds.is_none() {
+            self.note(
+                Some(name),
+                key,
+                format!("`{duration}` isn't a duration locald understands, so it was left out"),
+            );
+        }
+        seconds
+    }
+
+    fn restart(&mut self, name: &str, restart: Option<&str>) -> Option<RestartConfig> {
+        let restart = restart?;
+        match restart {
+            "no" => None,
+            "always" | "unless-stopped" => Some(RestartConfig::Mode(RestartMode::Always)),
+            "on-failure" => Some(RestartConfig::Mode(RestartMode::OnFailure)),
+            _ => {
+                let retries = restart
+                    .strip_prefix("on-failure:")
+                    .and_then(|n| n.parse().ok());
+                if retries.is_none() {
+                    self.note(
+                        Some(name),
+                        "restart",
+                        format!("unknown restart policy `{restart}`, left out"),
+                    );
+                }
+                retries.map(|max_retries| {
+                    RestartConfig::Policy(RestartPolicy {
+                        mode: RestartMode::OnFailure,
+                        max_retries: Some(max_retries),
+                        backoff: None,
+                        max_backoff: None,
+                    })
+                })
+            }
+        }
+    }
+
+    fn stop_signal(&mut self, name: &str, signal: Option<&str>) -> Option<String> {
+        let signal = signal?;
+        if parse_stop_signal(signal).is_some() {
+            Some(signal.to_string())
+        } else {
+            self.note(
+                Some(name),
+                "stop_signal",
+                format!("locald can't stop services with {signal}; it uses SIGTERM instead"),
+            );
+            None
+        }
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Mapping(map) => map.is_empty(),
+        Value::Sequence(list) => list.is_empty(),
+        Value::Bool(_) | Value::Number(_) | Value::String(_) | Value::Tagged(_) => false,
+    }
+}
+
+fn is_dotenv(value: &Value) -> bool {
+    match value {
+        Value::String(file) => relative_path(file) == ".env",
+        Value::Sequence(files) => files.iter().all(is_dotenv),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::Mapping(_) | Value::Tagged(_) => {
+            false
+        }
+    }
+}
+
+/// The value of a YAML scalar as a string, the way compose reads `environment` values.
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null | Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => None,
+    }
+}
+
+/// `command` as a string: as written, or a list joined with shell quoting.
+fn command_string(command: &Value) -> String {
+    match command {
+        Value::Sequence(args) => {
+            let args: Vec<String> = args.iter().filter_map(scalar).collect();
+            shlex::try_join(args.iter().map(String::as_str)).unwrap_or_else(|_| args.join(" "))
+        }
+        Value::Null
+        | Value::Bool(_)
+        | Value::Number(_)
+        | Value::String(_)
+        | Value::Mapping(_)
+        | Value::Tagged(_) => scalar(command).unwrap_or_default(),
+    }
+}
+
+/// Translates compose variable substitution into locald references:
+/// `$VAR` and `${VAR}` become `${env.VAR}`, `${VAR:-default}` and `${VAR-default}`
+/// become `${env.VAR:-default}`, and `$$` becomes `$`.
+fn translate_vars(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(after) = after.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let Some(end) = body.find('}') else {
+                out.push_str(&rest[start..]);
+                rest = "";
+                break;
+            };
+            let expr = &body[..end];
+            let name_len = expr
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(expr.len());
+            let (name, modifier) = expr.split_at(name_len);
+            let default = modifier
+                .strip_prefix(":-")
+                .or_else(|| modifier.strip_prefix('-'));
+            if name.is_empty() {
+                out.push_str(&rest[start..start + end + 3]);
+            } else if let Some(default) = default {
+                let _ = write!(out, "${{env.{name}:-{default}}}");
+            } else if modifier.is_empty() || modifier.starts_with(":?") || modifier.starts_with('?')
+            {
+                let _ = write!(out, "${{env.{name}}}");
+            } else {
+                // `${VAR:+alternative}` has no locald equivalent; leave it for the shell
+                out.push_str(&rest[start..start + end + 3]);
+            }
+            rest = &body[end + 1..];
+        } else {
+            let name_len = after
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(after.len());
+            if name_len == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+                // `$1` and a lone `$` are left as they are
+                out.push('$');
+                rest = after;
+            } else {
+                let _ = write!(out, "${{env.{}}}", &after[..name_len]);
+                rest = &after[name_len..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_postgres_image(image: &str) -> bool {
+    image_name(image).0.rsplit('/').next() == Some("postgres")
+}
+
+/// Splits `registry/name:tag@digest` into the name and the tag.
+fn image_name(image: &str) -> (&str, Option<&str>) {
+    let image = image.split('@').next().unwrap_or(image);
+    match image.rsplit_once(':') {
+        // A colon before the last slash is a registry port, not a tag
+        Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+        _ => (image, None),
+    }
+}
+
+/// The Postgres version in a tag such as `16-alpine` or `15.4`.
+fn postgres_version(image: &str) -> Option<String> {
+    let tag = image_name(image).1?;
+    let version: String = tag
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let version = version.trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+fn postgres_databases(service: &ComposeService) -> Vec<String> {
+    let database = match &service.environment {
+        Some(Value::Mapping(map)) => map.get("POSTGRES_DB").and_then(scalar),
+        Some(Value::Sequence(list)) => list
+            .iter()
+            .filter_map(Value::as_str)
+            .find_map(|entry| entry.strip_prefix("POSTGRES_DB="))
+            .map(str::to_string),
+        _ => None,
+    };
+    database.into_iter().collect()
+}
+
+fn postgres_setting(value: &str) -> PostgresSetting {
+    match value {
+        "on" | "true" => PostgresSetting::Bool(true),
+        "off" | "false" => PostgresSetting::Bool(false),
+        _ => value.parse().map_or_else(
+            |_| PostgresSetting::String(value.to_string()),
+            PostgresSetting::Integer,
+        ),
+    }
+}
+
+/// Parses `"8080:80"`, `"127.0.0.1:8080:80/udp"`, `80`, or the long syntax. Port
+/// ranges and variables aren't supported.
+fn parse_port(port: &Value) -> Option<PortMapping> {
+    if let Value::Mapping(port) = port {
+        let target = port.get("target").and_then(scalar)?.parse().ok()?;
+        let published = match port.get("published").and_then(scalar) {
+            Some(published) => Some(published.parse().ok()?),
+            None => None,
+        };
+        let udp = port.get("protocol").and_then(Value::as_str) == Some("udp");
+        return Some(PortMapping {
+            published,
+            target,
+            udp,
+        });
+    }
+
+    let port = scalar(port)?;
+    let (port, protocol) = port.split_once('/').unwrap_or((&port, "tcp"));
+    let mut parts = port.rsplit(':');
+    let target = parts.next()?.parse().ok()?;
+    let published = match parts.next() {
+        Some("") | None => None,
+        Some(published) => Some(published.parse().ok()?),
+    };
+    Some(PortMapping {
+        published,
+        target,
+        udp: protocol == "udp",
+    })
+}
+
+fn expose(port: PortMapping) -> Option<ExposeConfig> {
+    let published = port.published?;
+    Some(if port.udp {
+        ExposeConfig {
+            tcp: None,
+            udp: Some(published),
+        }
+    } else {
+        ExposeConfig {
+            tcp: Some(published),
+            udp: None,
+        }
+    })
+}
+
+fn describe_port(port: PortMapping) -> String {
+    let protocol = if port.udp { "/udp" } else { "" };
+    port.published.map_or_else(
+        || format!("port `{}{protocol}`", port.target),
+        |published| format!("port `{published}:{}{protocol}`", port.target),
+    )
+}
+
+/// Parses `./src:/app:ro`, `data:/var/lib/data`, `/tmp`, or the long syntax.
+fn parse_mount(volume: &Value) -> Option<Mount> {
+    if let Value::Mapping(volume) = volume {
+        let target = volume.get("target").and_then(Value::as_str)?.to_string();
+        let source = volume.get("source").and_then(Value::as_str);
+        let bind = volume.get("type").and_then(Value::as_str) == Some("bind");
+        return Some(Mount {
+            source: source.map(|s| {
+                if bind {
+                    relative_path(s)
+                } else {
+                    s.to_string()
+                }
+            }),
+            target,
+            bind,
+        });
+    }
+
+    let volume = volume.as_str()?;
+    let mut parts = volume.split(':');
+    let first = parts.next()?;
+    let Some(target) = parts.next() else {
+        return Some(Mount {
+            source: None,
+            target: first.to_string(),
+            bind: false,
+        });
+    };
+    let bind = first.starts_with(['.', '/', '~']);
+    Some(Mount {
+        source: Some(if bind {
+            relative_path(first)
+        } else {
+            first.to_string()
+        }),
+        target: target.to_string(),
+        bind,
+    })
+}
+
+fn describe_mount(mount: &Mount) -> String {
+    mount.source.as_ref().map_or_else(
+        || format!("volume `{}`", mount.target),
+        |source| format!("volume `{source}:{}`", mount.target),
+    )
+}
+
+fn is_sql(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+}
+
+/// A compose path relative to the project: `./db/init.sql` becomes `db/init.sql`.
+fn relative_path(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    let path = path.strip_prefix("./").unwrap_or(path);
+    if path.is_empty() {
+        ".".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Parses compose durations such as `30s`, `1m30s` or `500ms`, rounded up to seconds.
+fn parse_duration(duration: &str) -> Option<u64> {
+    let mut millis: u64 = 0;
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "h" => 3_600_000,
+            "m" => 60_000,
+            "s" => 1_000,
+            "ms" => 1,
+            _ => return None,
+        };
+        millis = millis.checked_add(value.checked_mul(unit)?)?;
+        rest = &rest[unit_len..];
+    }
+    (millis > 0).then(|| millis.div_ceil(1_000))
+}
+
+/// The path of an HTTP check of `port` on localhost, e.g. `/health` in
+/// `curl -f http://localhost:8080/health`.
+fn http_check_path(command: &str, port: u16) -> Option<String> {
+    let url = Regex::new(&format!(
+        r"https?://(?:localhost|127\.0\.0\.1|0\.0\.0\.0){}(/[^\s'\x22|;&]*)?",
+        if port == 80 {
+            "(?::80)?".to_string()
+        } else {
+            format!(":{port}")
+        }
+    ))
+    .ok()?;
+    let captures = url.captures(command)?;
+    Some(captures.get(1).map_or("/", |m| m.as_str()).to_string())
+}
+
+#[cfg(test)]
+#[allow(clippy::literal_string_with_formatting_args)] // compose variables look like them
+mod tests {
+    use super::*;
+
+    fn import(content: &str) -> ComposeImport {
+        let Ok(import) = import_compose(content, Path::new("/projects/shop")) else {
+            unreachable!("compose file should parse");
+        };
+        import
+    }
+
+    fn service<'a>(import: &'a ComposeImport, name: &str) -> &'a TypedServiceConfig {
+        let Some(ServiceConfig::Typed(service)) = import.config.services.get(name) else {
+            unreachable!("service {name} should be imported");
+        };
+        service
+    }
+
+    #[test]
+    fn test_translates_services() {
+        let import = import(
+            r#"
+services:
+  db:
+    image: postgres:16-alpine
+    environment:
+      POSTGRES_DB: shop
+      POSTGRES_PASSWORD: secret
+    ports:
+      - "5433:5432"
+    volumes:
+      - ./db/schema.sql:/docker-entrypoint-initdb.d/01-schema.sql
+      - pgdata:/var/lib/postgresql/data
+    command: postgres -c max_connections=200 -c fsync=off
+    healthcheck:
+      test: ["CMD-SHELL", "pg_isready -U postgres"]
+  cache:
+    image: redis:7
+    ports: ["6379"]
+    healthcheck:
+      test: ["CMD", "curl", "-f", "http://localhost:6379/ping"]
+      interval: 1m30s
+      timeout: 5s
+      retries: 3
+  web:
+    build: ./web
+    command: npm start
+    working_dir: /app
+    ports:
+      - 3000:3000
+      - "9229:9229"
+    environment:
+      - DATABASE_URL=postgres://app:secret@db:5432/shop?sslmode=disable
+      - REDIS_URL=redis://cache:6379
+      - API_KEY
+      - LOG_LEVEL=${LOG_LEVEL:-info}
+      - PRICE=$$5
+    volumes:
+      - ./web:/app
+    depends_on:
+      db:
+        condition: service_healthy
+      cache:
+        condition: service_started
+    restart: on-failure:3
+volumes:
+  pgdata:
+"#,
+        );
+
+        assert_eq!(import.config.project.name, "shop");
+
+        let TypedServiceConfig::Postgres(db) = service(&import, "db") else {
+            unreachable!("db should be a postgres service");
+        };
+        assert_eq!(db.version.as_deref(), Some("16"));
+        assert_eq!(db.databases, vec!["shop".to_string()]);
+        assert_eq!(db.init_scripts, vec!["db/schema.sql".to_string()]);
+        assert_eq!(
+            db.settings.get("max_connections"),
+            Some(&PostgresSetting::Integer(200))
+        );
+        assert_eq!(
+            db.settings.get("fsync"),
+            Some(&PostgresSetting::Bool(false))
+        );
+        assert_eq!(db.common.expose.and_then(|e| e.tcp), Some(5433));
+
+        let TypedServiceConfig::Container(cache) = service(&import, "cache") else {
+            unreachable!("cache should be a container service");
+        };
+        assert_eq!(cache.image, "redis:7");
+        assert_eq!(cache.container_port, Some(6379));
+        assert_eq!(cache.common.expose, None);
+        assert_eq!(
+            cache.common.health_check,
+            Some(HealthCheckConfig::Probe(ProbeConfig {
+                kind: ProbeType::Http,
+                path: Some("/ping".to_string()),
+                interval: Some(90),
+                timeout: Some(5),
+                command: None,
+            }))
+        );
+
+        let TypedServiceConfig::Exec(web) = service(&import, "web") else {
+            unreachable!("web should be an exec service");
+        };
+        assert_eq!(web.command.as_deref(), Some("npm start"));
+        assert_eq!(web.workdir.as_deref(), Some("web"));
+        assert_eq!(web.common.port, Some(3000));
+        assert_eq!(web.common.expose, None);
+        let env = &web.common.env;
+        assert_eq!(
+            env.get("DATABASE_URL").map(String::as_str),
+            Some("${services.db.url}?sslmode=disable")
+        );
+        assert_eq!(
+            env.get("REDIS_URL").map(String::as_str),
+            Some("redis://${services.cache.host}:${services.cache.port}")
+        );
+        assert_eq!(
+            env.get("API_KEY").map(String::as_str),
+            Some("${env.API_KEY}")
+        );
+        assert_eq!(
+            env.get("LOG_LEVEL").map(String::as_str),
+            Some("${env.LOG_LEVEL:-info}")
+        );
+        assert_eq!(env.get("PRICE").map(String::as_str), Some("$5"));
+        assert_eq!(
+            web.common.depends_on,
+            vec![
+                Dependency::Detailed(DependencyConfig {
+                    service: "db".to_string(),
+                    condition: DependencyCondition::Healthy,
+                    timeout: None,
+                }),
+                Dependency::Service("cache".to_string()),
+            ]
+        );
+        assert_eq!(
+            web.common.restart,
+            Some(RestartConfig::Policy(RestartPolicy {
+                mode: RestartMode::OnFailure,
+                max_retries: Some(3),
+                backoff: None,
+                max_backoff: None,
+            }))
+        );
+
+        let notes: Vec<String> = import.notes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            notes,
+            vec![
+                "volumes: named volumes were not imported; locald keeps each service's data itself",
+                "services.cache.healthcheck.retries: locald has no equivalent, so it was left out",
+                "services.db.environment.POSTGRES_PASSWORD: locald's Postgres always uses user and password `postgres`; refer to it as `${services.db.password}`",
+                "services.db.volumes: volume `pgdata:/var/lib/postgresql/data` was not imported; locald keeps each Postgres service's data in its own directory",
+                "services.web.ports: port `9229:9229` was not imported; a locald service has one port",
+                "services.web.build: locald runs `command` on the host in `web` instead of building an image; check that it starts the service there",
+                "services.web.working_dir: `/app` is a path in the image; the service runs in `web`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_what_it_cannot_translate() {
+        let import = import(
+            r#"
+name: store
+services:
+  worker:
+    image: ghcr.io/acme/worker:1.2
+    entrypoint: /bin/sh
+    command: ["run", "--queue", "high priority"]
+    volumes:
+      - ./data:/data
+    healthcheck:
+      test: ./check.sh
+    stop_signal: SIGUSR1
+    restart: unless-stopped
+    cap_add: [NET_ADMIN]
+    ports:
+      - "8000-8010:8000-8010"
+    depends_on: [orphan]
+  orphan:
+    environment:
+      A: b
+"#,
+        );
+
+        assert_eq!(import.config.project.name, "store");
+        assert!(!import.config.services.contains_key("orphan"));
+        let problems: Vec<String> =
+            crate::config_loader::ConfigLoader::check_config(&import.config)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+        assert_eq!(problems, Vec::<String>::new());
+
+        let TypedServiceConfig::Container(worker) = service(&import, "worker") else {
+            unreachable!("worker should be a container service");
+        };
+        assert_eq!(
+            worker.command.as_deref(),
+            Some("run --queue 'high priority'")
+        );
+        assert_eq!(worker.common.stop_signal, None);
+        assert_eq!(
+            worker.common.restart,
+            Some(RestartConfig::Mode(RestartMode::Always))
+        );
+
+        let keys: Vec<String> = import
+            .notes
+            .iter()
+            .map(|n| format!("{}.{}", n.service.as_deref().unwrap_or(""), n.key))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "orphan.image",
+                "worker.depends_on.orphan",
+                "worker.stop_signal",
+                "worker.cap_add",
+                "worker.ports",
+                "worker.healthcheck.test",
+                "worker.volumes",
+                "worker.entrypoint",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_translate_vars() {
+        assert_eq!(translate_vars("$HOME/x"), "${env.HOME}/x");
+        assert_eq!(
+            translate_vars("${A}-${B:-b}-${C-c}"),
+            "${env.A}-${env.B:-b}-${env.C:-c}"
+        );
+        assert_eq!(translate_vars("${TOKEN:?missing}"), "${env.TOKEN}");
+        assert_eq!(translate_vars("echo $$PATH $1"), "echo $PATH $1");
+        assert_eq!(translate_vars("${A:+set}"), "${A:+set}");
+    }
+
+    #[test]
+    fn test_parse_helpers() {
+        assert_eq!(parse_duration("1m30s"), Some(90));
+        assert_eq!(parse_duration("500ms"), Some(1));
+        assert_eq!(parse_duration("soon"), None);
+
+        let port = |s: &str| parse_port(&Value::String(s.to_string()));
+        assert_eq!(
+            port("127.0.0.1:8125:8125/udp"),
+            Some(PortMapping {
+                published: Some(8125),
+                target: 8125,
+                udp: true
+            })
+        );
+        assert_eq!(
+            port("80"),
+            Some(PortMapping {
+                published: None,
+                target: 80,
+                udp: false
+            })
+        );
+        assert_eq!(port("${PORT}:80"), None);
+
+        assert_eq!(
+            postgres_version("postgres:15.4-bookworm").as_deref(),
+            Some("15.4")
+        );
+        assert_eq!(postgres_version("postgres:latest"), None);
+        assert!(is_postgres_image("docker.io/library/postgres"));
+        assert!(!is_postgres_image("postgis/postgis:16-3.4"));
+        assert!(!is_postgres_image("localhost:5000/postgres-tools"));
+    }
+}
@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use locald_core::config::{
-    CommonServiceConfig, EnvLayer, EnvLayerKind, EnvLayerSource, ExecServiceConfig, GlobalConfig,
-    LocaldConfig, ProjectConfig, ResolvedEnv, ServiceConfig, TypedServiceConfig,
-    WorkerServiceConfig, merge_env_layers, overlay_env,
+    CommonServiceConfig, CorsConfig, EnvLayer, EnvLayerKind, EnvLayerSource, ExecServiceConfig,
+    FaultConfig, GlobalConfig, HeaderRules, LocaldConfig, ProjectConfig, ResolvedEnv,
+    ServiceConfig, TypedServiceConfig, WorkerServiceConfig, domains,
+    interpolate::{self, Reference, Segment},
+    merge_env_layers, overlay_env, routes,
 };
 use serde::Deserialize;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use tracing::{info, warn};
 
@@ -38,6 +40,72 @@ impl std::fmt::Display for Provenance {
     }
 }
 
+/// The services that can't be started because their dependencies form a cycle
+/// (or depend on one), sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyCycle(pub Vec<String>);
+
+impl std::fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Circular dependency between services: {}",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for DependencyCycle {}
+
+/// A problem with a project's configuration, and the key it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The path of the key, e.g. `["services", "web", "depends_on"]`.
+    pub key: Vec<String>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: &[&str], message: impl Into<String>) -> Self {
+        Self {
+            key: key.iter().map(ToString::to_string).collect(),
+            message: message.into(),
+        }
+    }
+
+    /// The key in TOML's dotted form (see [`dotted_key`]).
+    #[must_use]
+    pub fn dotted_key(&self) -> String {
+        dotted_key(&self.key)
+    }
+}
+
+/// Writes a key path in TOML's dotted form, quoting parts that aren't bare keys
+/// (`routes."/api/*"`).
+#[must_use]
+pub fn dotted_key(key: &[String]) -> String {
+    key.iter()
+        .map(|part| {
+            let bare = !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if bare {
+                part.clone()
+            } else {
+                format!("{part:?}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ConfigLoader {
     pub global: GlobalConfig,
@@ -140,11 +208,24 @@ impl ConfigLoader {
                     Provenance::Default
                 }
             }
+            "server.startup_concurrency"
+            | "logs.max_size_mb"
+            | "logs.max_age_days"
+            | "dns.enabled"
+            | "dns.port"
+            | "dns.upstream"
+            | "acme.domains" => {
+                if self.global_path.exists() {
+                    Provenance::Global(self.global_path.clone())
+                } else {
+                    Provenance::Default
+                }
+            }
             _ => Provenance::Default,
         }
     }
 
-    async fn load_upstream_configs(start_path: &PathBuf) -> Vec<(LayerConfig, PathBuf)> {
+    pub(crate) async fn load_upstream_configs(start_path: &PathBuf) -> Vec<(LayerConfig, PathBuf)> {
         let discovered = Self::discover_layers(start_path);
         let mut configs = Vec::new();
 
@@ -211,29 +292,20 @@ impl ConfigLoader {
         for (k, v) in &override_svc.common.env {
             base.common.env.insert(k.clone(), v.clone());
         }
+        if let Some(restart) = override_svc.common.restart {
+            base.common.restart = Some(restart);
+        }
+        if let Some(watch) = &override_svc.watch {
+            base.watch = Some(watch.clone());
+        }
     }
 
-    /// Loads configuration for a project from a directory.
-    ///
-    /// Tries to load `locald.toml` first, falling back to `Procfile`.
-    /// Also loads `.env` files if present.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if:
-    /// - Neither `locald.toml` nor `Procfile` exists.
-    /// - The configuration file cannot be read or parsed.
-    pub async fn load_project_config(
-        path: &PathBuf,
-    ) -> Result<(LocaldConfig, HashMap<String, String>)> {
-        // 1. Load Global/Context/Workspace Config (Recursive)
-        let workspace_env = Self::load_workspace_config(path).await?;
-        let upstream_configs = Self::load_upstream_configs(path).await;
-
-        // 2. Read Project Config
-        let (mut config, _config_source_path) = Self::read_project_config(path).await?;
-
-        // Populate Workspace and Constellation info
+    /// Names the project's workspace and constellation after the layers above it, and
+    /// merges their services under the project's own.
+    pub(crate) fn apply_upstream_layers(
+        config: &mut LocaldConfig,
+        upstream_configs: Vec<(LayerConfig, PathBuf)>,
+    ) {
         for (layer_config, layer_path) in &upstream_configs {
             let file_name = layer_path
                 .file_name()
@@ -267,18 +339,193 @@ impl ConfigLoader {
             }
         }
 
-        // 3. Merge Upstream Services into Project Config
         let mut merged_services = HashMap::new();
         for (layer_config, _) in upstream_configs {
             Self::merge_service_configs(&mut merged_services, &layer_config.services);
         }
         Self::merge_service_configs(&mut merged_services, &config.services);
         config.services = merged_services;
+    }
+
+    /// Checks everything about a project's services that parsing can't: that routes
+    /// and dependencies point at real services, that domains are valid and claimed by
+    /// one service each, that fault rules, header rules, CORS policies, stop signals
+    /// and `${...}` references are valid, and that dependencies don't form a cycle.
+    ///
+    /// Returns every problem found, in a stable order.
+    #[must_use]
+    pub fn check_config(config: &LocaldConfig) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        if let Some(Err(e)) = config
+            .project
+            .domain
+            .as_deref()
+            .map(domains::validate_domain)
+        {
+            problems.push(ConfigProblem::new(&["project", "domain"], e));
+        }
+
+        for (pattern, target) in &config.routes {
+            let key = ["routes", pattern.as_str()];
+            if let Err(e) = routes::validate_pattern(pattern) {
+                problems.push(ConfigProblem::new(&key, e));
+            } else if !config.services.contains_key(target.service()) {
+                problems.push(ConfigProblem::new(
+                    &key,
+                    format!(
+                        "Route '{pattern}' points to unknown service '{}'",
+                        target.service()
+                    ),
+                ));
+            }
+        }
+
+        let mut names: Vec<&String> = config.services.keys().collect();
+        names.sort();
+        let mut unknown_dependencies = false;
+        for name in &names {
+            let service = &config.services[*name];
+            let common = service.common();
+            for domain in &common.domains {
+                if let Err(e) = domains::validate_domain(domain) {
+                    problems.push(ConfigProblem::new(
+                        &["services", name, "domains"],
+                        format!("Service '{name}': {e}"),
+                    ));
+                }
+            }
+            if let Some(Err(e)) = common.faults.as_ref().map(FaultConfig::validate) {
+                problems.push(ConfigProblem::new(
+                    &["services", name, "faults"],
+                    format!("Service '{name}' faults: {e}"),
+                ));
+            }
+            if let Some(Err(e)) = common.headers.as_ref().map(HeaderRules::validate) {
+                problems.push(ConfigProblem::new(
+                    &["services", name, "headers"],
+                    format!("Service '{name}' headers: {e}"),
+                ));
+            }
+            if let Some(Err(e)) = common.cors.as_ref().map(CorsConfig::validate) {
+                problems.push(ConfigProblem::new(
+                    &["services", name, "cors"],
+                    format!("Service '{name}' cors: {e}"),
+                ));
+            }
+            if let Some(signal) = &common.stop_signal {
+                if crate::service::exec::parse_stop_signal(signal).is_none() {
+                    problems.push(ConfigProblem::new(
+                        &["services", name, "stop_signal"],
+                        format!(
+                            "Service '{name}' stop_signal: unknown signal '{signal}' (expected SIGTERM, SIGINT, SIGQUIT or SIGKILL)"
+                        ),
+                    ));
+                }
+            }
+            for dependency in service.depends_on() {
+                let dependency = dependency.service();
+                if !config.services.contains_key(dependency) {
+                    unknown_dependencies = true;
+                    problems.push(ConfigProblem::new(
+                        &["services", name, "depends_on"],
+                        format!("Service '{name}' depends on unknown service '{dependency}'"),
+                    ));
+                }
+            }
+            if let Err((key, e)) = Self::validate_references(service, config) {
+                let mut path = vec!["services", name.as_str()];
+                path.extend(key.split('.'));
+                problems.push(ConfigProblem::new(
+                    &path,
+                    format!("Service '{name}' {key}: {e}"),
+                ));
+            }
+        }
+
+        // Each domain is served by one service: its default domain, or one it claims
+        let mut owners: HashMap<String, &str> = HashMap::new();
+        for name in &names {
+            let domain = crate::manager::ProcessManager::get_service_domain(name, &config.project)
+                .to_lowercase();
+            if let Some(other) = owners.insert(domain.clone(), name) {
+                problems.push(ConfigProblem::new(
+                    &["services", name],
+                    format!("Services '{other}' and '{name}' are both served on {domain}"),
+                ));
+            }
+        }
+        for name in &names {
+            for domain in &config.services[*name].common().domains {
+                let domain = domain.to_lowercase();
+                match owners.get(&domain) {
+                    Some(other) if other != name => problems.push(ConfigProblem::new(
+                        &["services", name, "domains"],
+                        format!(
+                            "Service '{name}' claims {domain}, which service '{other}' is served on"
+                        ),
+                    )),
+                    _ => {
+                        owners.insert(domain, name);
+                    }
+                }
+            }
+        }
+
+        if !unknown_dependencies {
+            if let Err(e) = Self::resolve_startup_order(config) {
+                let key = match e.downcast_ref::<DependencyCycle>() {
+                    Some(DependencyCycle(services)) => services
+                        .iter()
+                        .find(|s| !config.services[s.as_str()].depends_on().is_empty())
+                        .map_or_else(Vec::new, |s| vec!["services", s.as_str(), "depends_on"]),
+                    None => Vec::new(),
+                };
+                problems.push(ConfigProblem::new(&key, e.to_string()));
+            }
+        }
+
+        problems
+    }
+
+    /// Loads configuration for a project from a directory.
+    ///
+    /// Tries to load `locald.toml` first, falling back to `Procfile`.
+    /// Also loads `.env` files if present. If `profile` is given, it is applied to the
+    /// merged services.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Neither `locald.toml` nor `Procfile` exists.
+    /// - The configuration file cannot be read or parsed.
+    /// - The profile doesn't exist or can't be applied.
+    pub async fn load_project_config(
+        path: &PathBuf,
+        profile: Option<&str>,
+    ) -> Result<(LocaldConfig, HashMap<String, String>)> {
+        // 1. Load Global/Context/Workspace Config (Recursive)
+        let workspace_env = Self::load_workspace_config(path).await?;
+        let upstream_configs = Self::load_upstream_configs(path).await;
 
-        // 4. Load .env if exists
+        // 2. Read Project Config
+        let (mut config, _config_source_path) = Self::read_project_config(path).await?;
+
+        // 3. Merge Upstream Services into Project Config
+        Self::apply_upstream_layers(&mut config, upstream_configs);
+        if let Some(profile) = profile {
+            config.apply_profile(profile).map_err(anyhow::Error::msg)?;
+        }
+
+        // 4. Check routes, domains, dependencies and `${...}` references
+        if let Some(problem) = Self::check_config(&config).into_iter().next() {
+            anyhow::bail!(problem.message);
+        }
+
+        // 5. Load .env if exists
         let dot_env_vars = Self::read_dotenv(path);
 
-        // 5. Merge: Workspace -> .env
+        // 6. Merge: Workspace -> .env
         // Workspace envs are defaults, .env overrides them.
         let mut final_env = workspace_env;
         final_env.extend(dot_env_vars);
@@ -286,6 +533,38 @@ impl ConfigLoader {
         Ok((config, final_env))
     }
 
+    /// Loads the variables a project adds to its services' environment: the
+    /// workspace's env layers, with `.env` over them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a workspace config can't be read.
+    pub async fn load_env_vars(path: &PathBuf) -> Result<HashMap<String, String>> {
+        let mut env = Self::load_workspace_config(path).await?;
+        env.extend(Self::read_dotenv(path));
+        Ok(env)
+    }
+
+    /// The variables `${env.NAME}` references resolve against: `client_env`, the
+    /// environment `locald up` was run in, with `env_vars` (see
+    /// [`Self::load_env_vars`]) over it.
+    #[must_use]
+    pub fn host_env(
+        mut client_env: HashMap<String, String>,
+        env_vars: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        client_env.extend(env_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        client_env
+    }
+
+    /// The current process's environment, leaving out variables that aren't UTF-8.
+    #[must_use]
+    pub fn process_env() -> HashMap<String, String> {
+        std::env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .collect()
+    }
+
     pub async fn load_env_provenance_report(&self, path: &PathBuf) -> Result<EnvProvenanceReport> {
         let (config, config_source_path) = match Self::read_project_config(path).await {
             Ok(v) => v,
@@ -334,7 +613,9 @@ impl ConfigLoader {
 
         // Collect all layers in order: Upstream -> Project
         let mut all_layers = upstream_configs;
-        let LocaldConfig { project, services } = project_config;
+        let LocaldConfig {
+            project, services, ..
+        } = project_config;
         all_layers.push((
             LayerConfig {
                 project: Some(project),
@@ -381,7 +662,7 @@ impl ConfigLoader {
                     let depends_on = service.depends_on();
                     if !depends_on.is_empty() {
                         prov.depends_on = Some(ProvenancedField {
-                            value: depends_on.clone(),
+                            value: depends_on.iter().map(|d| d.service().to_string()).collect(),
                             source: source_path.clone(),
                         });
                     }
@@ -399,20 +680,26 @@ impl ConfigLoader {
             ServiceConfig::Typed(typed) => match typed {
                 TypedServiceConfig::Exec(exec) => exec.command.clone(),
                 TypedServiceConfig::Worker(worker) => Some(worker.command.clone()),
+                TypedServiceConfig::Task(task) => Some(task.command.clone()),
                 TypedServiceConfig::Container(container) => container.command.clone(),
-                TypedServiceConfig::Postgres(_) | TypedServiceConfig::Site(_) => None,
+                TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
+                | TypedServiceConfig::Site(_) => None,
             },
         }
     }
 
-    fn service_workdir(service: &ServiceConfig) -> Option<String> {
+    pub(crate) fn service_workdir(service: &ServiceConfig) -> Option<String> {
         match service {
             ServiceConfig::Legacy(exec) => exec.workdir.clone(),
             ServiceConfig::Typed(typed) => match typed {
                 TypedServiceConfig::Exec(exec) => exec.workdir.clone(),
                 TypedServiceConfig::Worker(worker) => worker.workdir.clone(),
+                TypedServiceConfig::Task(task) => task.workdir.clone(),
                 TypedServiceConfig::Container(container) => container.workdir.clone(),
-                TypedServiceConfig::Postgres(_) | TypedServiceConfig::Site(_) => None,
+                TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
+                | TypedServiceConfig::Site(_) => None,
             },
         }
     }
@@ -592,12 +879,21 @@ impl ConfigLoader {
                             depends_on: Vec::new(),
                             health_check: None,
                             stop_signal: None,
+                            restart: None,
+                            expose: None,
+                            domains: Vec::new(),
+                            faults: None,
+                            headers: None,
+                            cors: None,
+                            protocol: None,
+                            tls: false,
                         },
                         command: Some(command),
                         image: None,
                         container_port: None,
                         workdir: None,
                         build: None,
+                        watch: None,
                     }))
                 } else {
                     ServiceConfig::Typed(TypedServiceConfig::Worker(WorkerServiceConfig {
@@ -607,9 +903,18 @@ impl ConfigLoader {
                             depends_on: Vec::new(),
                             health_check: None,
                             stop_signal: None,
+                            restart: None,
+                            expose: None,
+                            domains: Vec::new(),
+                            faults: None,
+                            headers: None,
+                            cors: None,
+                            protocol: None,
+                            tls: false,
                         },
                         command,
                         workdir: None,
+                        watch: None,
                     }))
                 };
 
@@ -631,69 +936,264 @@ impl ConfigLoader {
                 constellation: None,
             },
             services,
+            routes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
         }
     }
 
-    /// Resolves environment variables with variable substitution.
+    /// Resolves `${...}` references (see [`interpolate`]) in environment variables.
     ///
-    /// Supports `${services.service_name.field}` syntax to inject values
-    /// from other services (e.g., ports, URLs).
+    /// `${env.NAME}` is looked up in `host_env` (see [`Self::host_env`]), and
+    /// `lookup_fn` is called with the full name (`project:service`) and field of each
+    /// service that is referenced.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The regex for substitution fails to compile (unlikely).
-    /// - A referenced service or field cannot be resolved by the `lookup_fn`.
+    /// Returns an error naming the variable if a reference is invalid or can't be
+    /// resolved.
     pub async fn resolve_env<F, Fut>(
         env: &HashMap<String, String>,
         config: &LocaldConfig,
+        host_env: &HashMap<String, String>,
         lookup_fn: F,
     ) -> Result<HashMap<String, String>>
     where
-        F: Fn(String, String) -> Fut,
-        Fut: std::future::Future<Output = Result<String>>,
+        F: Fn(String, String) -> Fut + Send + Sync,
+        Fut: std::future::Future<Output = Result<String>> + Send,
     {
         let mut resolved = HashMap::new();
-        let re = regex::Regex::new(r"\$\{services\.([^.]+)\.([^}]+)\}")?;
-
         for (k, v) in env {
-            let mut new_val = v.clone();
-            let mut replacements = Vec::new();
-            // We need to collect captures first because of async closure
-            let mut captures = Vec::new();
-            for cap in re.captures_iter(v) {
-                captures.push((
-                    cap.get(0).map(|m| m.range()),
-                    cap.get(1).map(|m| m.as_str().to_string()),
-                    cap.get(2).map(|m| m.as_str().to_string()),
-                ));
-            }
+            let value = Self::interpolate(v, config, host_env, &lookup_fn)
+                .await
+                .map_err(|e| anyhow::anyhow!("env.{k}: {e}"))?;
+            resolved.insert(k.clone(), value);
+        }
+        Ok(resolved)
+    }
 
-            for (range_opt, service_name_opt, field_opt) in captures {
-                if let (Some(range), Some(service_name), Some(field)) =
-                    (range_opt, service_name_opt, field_opt)
-                {
-                    let full_service_name = format!("{}:{}", config.project.name, service_name);
-                    let val = lookup_fn(full_service_name, field).await?;
-                    replacements.push((range, val));
+    /// Resolves `${...}` references in a service's command, working directory and
+    /// health check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the option if a reference is invalid or can't be
+    /// resolved.
+    pub async fn resolve_service_config<F, Fut>(
+        service: &ServiceConfig,
+        config: &LocaldConfig,
+        host_env: &HashMap<String, String>,
+        lookup_fn: F,
+    ) -> Result<ServiceConfig>
+    where
+        F: Fn(String, String) -> Fut + Send + Sync,
+        Fut: std::future::Future<Output = Result<String>> + Send,
+    {
+        let mut resolved = service.clone();
+        for (key, value) in resolved.interpolated_fields_mut() {
+            *value = Self::interpolate(value, config, host_env, &lookup_fn)
+                .await
+                .map_err(|e| anyhow::anyhow!("{key}: {e}"))?;
+        }
+        Ok(resolved)
+    }
+
+    /// Substitutes the references in `template`.
+    async fn interpolate<F, Fut>(
+        template: &str,
+        config: &LocaldConfig,
+        host_env: &HashMap<String, String>,
+        lookup_fn: &F,
+    ) -> std::result::Result<String, String>
+    where
+        F: Fn(String, String) -> Fut + Send + Sync,
+        Fut: std::future::Future<Output = Result<String>> + Send,
+    {
+        let mut value = String::new();
+        for segment in interpolate::parse(template)? {
+            let reference = match segment {
+                Segment::Text(text) => {
+                    value.push_str(&text);
+                    continue;
                 }
-            }
+                Segment::Reference(reference) => reference,
+            };
+            let resolved = match &reference {
+                Reference::Service {
+                    project,
+                    service,
+                    field,
+                } => {
+                    let project = project.as_deref().unwrap_or(&config.project.name);
+                    if project == config.project.name && !config.services.contains_key(service) {
+                        return Err(format!("unknown service '{service}'"));
+                    }
+                    lookup_fn(format!("{project}:{service}"), field.clone())
+                        .await
+                        .map_err(|e| format!("{e:#}"))?
+                }
+                Reference::Project(field) => match field.as_str() {
+                    "name" => config.project.name.clone(),
+                    "domain" => config.project.root_domain(),
+                    _ => config
+                        .project
+                        .workspace
+                        .clone()
+                        .ok_or("the project is not part of a workspace")?,
+                },
+                Reference::Env { name, default } => {
+                    Self::lookup_env(name, default.as_deref(), host_env)?
+                }
+            };
+            value.push_str(&resolved);
+        }
+        Ok(value)
+    }
 
-            replacements.sort_by_key(|(r, _)| std::cmp::Reverse(r.start));
-            for (range, val) in replacements {
-                new_val.replace_range(range, &val);
+    fn lookup_env<S: std::hash::BuildHasher>(
+        name: &str,
+        default: Option<&str>,
+        host_env: &HashMap<String, String, S>,
+    ) -> std::result::Result<String, String> {
+        match (host_env.get(name), default) {
+            (Some(v), Some(default)) if v.is_empty() => Ok(default.to_string()),
+            (Some(v), _) => Ok(v.clone()),
+            (None, Some(default)) => Ok(default.to_string()),
+            (None, None) => Err(format!(
+                "environment variable {name} is not set (use ${{env.{name}:-default}} for a fallback)"
+            )),
+        }
+    }
+
+    /// Checks that the `${env.NAME}` references without a default are set in
+    /// `host_env` (see [`Self::host_env`]), which is what the daemon resolves them
+    /// against.
+    #[must_use]
+    pub fn check_env_references<S: std::hash::BuildHasher>(
+        config: &LocaldConfig,
+        host_env: &HashMap<String, String, S>,
+    ) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let mut names: Vec<&String> = config.services.keys().collect();
+        names.sort();
+        for name in names {
+            for (key, variable, default) in Self::env_references(&config.services[name]) {
+                if let Err(e) = Self::lookup_env(&variable, default.as_deref(), host_env) {
+                    let mut path = vec!["services", name.as_str()];
+                    path.extend(key.split('.'));
+                    problems.push(ConfigProblem::new(
+                        &path,
+                        format!("Service '{name}' {key}: {e}"),
+                    ));
+                }
             }
+        }
+        problems
+    }
+
+    /// The names of the variables the config's `${env.NAME}` references use.
+    #[must_use]
+    pub fn referenced_env_names(config: &LocaldConfig) -> BTreeSet<String> {
+        config
+            .services
+            .values()
+            .flat_map(Self::env_references)
+            .map(|(_, variable, _)| variable)
+            .collect()
+    }
 
-            resolved.insert(k.clone(), new_val);
+    /// The `${env.NAME}` references of a service that parse, as the key they're in,
+    /// the variable's name and its default.
+    fn env_references(service: &ServiceConfig) -> Vec<(String, String, Option<String>)> {
+        let mut references = Vec::new();
+        for (key, template) in Self::reference_templates(service) {
+            let Ok(segments) = interpolate::parse(&template) else {
+                continue;
+            };
+            for segment in segments {
+                if let Segment::Reference(Reference::Env { name, default }) = segment {
+                    references.push((key.clone(), name, default));
+                }
+            }
         }
+        references
+    }
 
-        Ok(resolved)
+    /// The values of a service that may contain `${...}` references, by key
+    /// (`env.DATABASE_URL`, `command`), sorted.
+    fn reference_templates(service: &ServiceConfig) -> Vec<(String, String)> {
+        let mut service = service.clone();
+        let mut templates: Vec<(String, String)> = service
+            .env()
+            .iter()
+            .map(|(k, v)| (format!("env.{k}"), v.clone()))
+            .collect();
+        templates.extend(
+            service
+                .interpolated_fields_mut()
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.clone())),
+        );
+        templates.sort();
+        templates
+    }
+
+    /// Checks the `${...}` references of a service: that they parse, and that services
+    /// of the same project exist. Errors name the key the reference is in
+    /// (`env.DATABASE_URL`, `command`).
+    fn validate_references(
+        service: &ServiceConfig,
+        config: &LocaldConfig,
+    ) -> Result<(), (String, String)> {
+        for (key, template) in Self::reference_templates(service) {
+            let segments = match interpolate::parse(&template) {
+                Ok(segments) => segments,
+                Err(e) => return Err((key, e)),
+            };
+            for segment in segments {
+                if let Segment::Reference(Reference::Service {
+                    project: None,
+                    service,
+                    field,
+                }) = segment
+                {
+                    let Some(target) = config.services.get(&service) else {
+                        return Err((key, format!("unknown service '{service}'")));
+                    };
+                    let postgres_only = interpolate::POSTGRES_FIELDS.contains(&field.as_str())
+                        || field.starts_with("databases.");
+                    if postgres_only
+                        && !matches!(
+                            target,
+                            ServiceConfig::Typed(TypedServiceConfig::Postgres(_))
+                        )
+                    {
+                        return Err((
+                            key,
+                            format!(
+                                "'{field}' is only available for Postgres services, and '{service}' isn't one"
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
     pub fn resolve_startup_order(config: &LocaldConfig) -> Result<Vec<String>> {
+        Ok(Self::resolve_startup_layers(config)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Groups services into dependency layers.
+    ///
+    /// Every service in a layer depends only on services in earlier layers, so the
+    /// services within a layer can be started concurrently.
+    pub fn resolve_startup_layers(config: &LocaldConfig) -> Result<Vec<Vec<String>>> {
         let mut in_degree: HashMap<String, usize> = HashMap::new();
         let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
-        let mut queue: VecDeque<String> = VecDeque::new();
 
         // Initialize
         for name in config.services.keys() {
@@ -704,6 +1204,7 @@ impl ConfigLoader {
         // Build graph
         for (name, service) in &config.services {
             for dep in service.depends_on() {
+                let dep = dep.service();
                 if !config.services.contains_key(dep) {
                     anyhow::bail!("Service '{name}' depends on unknown service '{dep}'");
                 }
@@ -721,40 +1222,54 @@ impl ConfigLoader {
         }
 
         // Find initial nodes (0 dependencies)
-        for (name, &degree) in &in_degree {
-            if degree == 0 {
-                queue.push_back(name.clone());
-            }
-        }
+        let mut layer: Vec<String> = in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(name, _)| name.clone())
+            .collect();
 
-        let mut sorted = Vec::new();
-        while let Some(node) = queue.pop_front() {
-            sorted.push(node.clone());
-
-            if let Some(neighbors) = dependents.get(&node) {
-                for neighbor in neighbors {
-                    let degree = in_degree.get_mut(neighbor).ok_or_else(|| {
-                        anyhow::anyhow!("Service {neighbor} not found in in_degree map")
-                    })?;
-                    *degree -= 1;
-                    if *degree == 0 {
-                        queue.push_back(neighbor.clone());
+        let mut layers = Vec::new();
+        let mut visited = 0;
+        while !layer.is_empty() {
+            layer.sort();
+            visited += layer.len();
+
+            let mut next = Vec::new();
+            for node in &layer {
+                if let Some(neighbors) = dependents.get(node) {
+                    for neighbor in neighbors {
+                        let degree = in_degree.get_mut(neighbor).ok_or_else(|| {
+                            anyhow::anyhow!("Service {neighbor} not found in in_degree map")
+                        })?;
+                        *degree -= 1;
+                        if *degree == 0 {
+                            next.push(neighbor.clone());
+                        }
                     }
                 }
             }
+
+            layers.push(std::mem::replace(&mut layer, next));
         }
 
-        if sorted.len() != config.services.len() {
-            anyhow::bail!("Circular dependency detected in services");
+        if visited != config.services.len() {
+            let mut services: Vec<String> = in_degree
+                .into_iter()
+                .filter(|(_, degree)| *degree > 0)
+                .map(|(name, _)| name)
+                .collect();
+            services.sort();
+            return Err(DependencyCycle(services).into());
         }
 
-        Ok(sorted)
+        Ok(layers)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use locald_core::config::HealthCheckConfig;
 
     #[tokio::test]
     async fn service_provenance_comes_from_project_config_path() {
@@ -864,7 +1379,7 @@ port = 4000
         };
 
         // Load config
-        let (config, _) = ConfigLoader::load_project_config(&project_dir)
+        let (config, _) = ConfigLoader::load_project_config(&project_dir, None)
             .await
             .expect("load config");
         let web = config.services.get("web").expect("web service");
@@ -900,4 +1415,150 @@ port = 4000
             Some(&project_path)
         );
     }
+
+    #[test]
+    fn startup_layers_group_independent_services() {
+        let config: LocaldConfig = toml::from_str(
+            r#"
+[project]
+name = "layers"
+
+[services.db]
+type = "postgres"
+
+[services.cache]
+command = "redis-server"
+
+[services.api]
+command = "api"
+depends_on = ["db", "cache"]
+
+[services.worker]
+command = "worker"
+depends_on = ["db"]
+
+[services.web]
+command = "web"
+depends_on = ["api"]
+"#,
+        )
+        .expect("parse config");
+
+        let layers = ConfigLoader::resolve_startup_layers(&config).expect("layers");
+        assert_eq!(
+            layers,
+            vec![
+                vec!["cache".to_string(), "db".to_string()],
+                vec!["api".to_string(), "worker".to_string()],
+                vec!["web".to_string()],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn references_resolve_in_env_command_and_health_check() {
+        let config: LocaldConfig = toml::from_str(
+            r#"
+[project]
+name = "shop"
+
+[services.db]
+type = "postgres"
+
+[services.api]
+command = "api --db ${services.db.url} --cwd ${env.LOCALD_TEST_UNSET:-/tmp} ${PORT}"
+workdir = "${project.name}"
+health_check = { type = "http", path = "/health/${project.domain}" }
+env = { DB_PORT = "${services.db.port}", AUTH = "${projects.auth.services.api.url}", TOKEN = "${env.API_TOKEN}", USER = "${env.USER}" }
+"#,
+        )
+        .expect("parse config");
+        let lookup = |service: String, field: String| async move {
+            match (service.as_str(), field.as_str()) {
+                ("shop:db", "url") => Ok("postgres://localhost:5432/postgres".to_string()),
+                ("shop:db", "port") => Ok("5432".to_string()),
+                ("auth:api", "url") => Ok("http://localhost:4000".to_string()),
+                _ => anyhow::bail!("Service {service} not found"),
+            }
+        };
+        let api = &config.services["api"];
+        // `.env` wins over the environment `locald up` was run in
+        let host_env = ConfigLoader::host_env(
+            HashMap::from([
+                ("API_TOKEN".to_string(), "from-shell".to_string()),
+                ("USER".to_string(), "alice".to_string()),
+            ]),
+            &HashMap::from([("API_TOKEN".to_string(), "from-dotenv".to_string())]),
+        );
+
+        let env = ConfigLoader::resolve_env(api.env(), &config, &host_env, &lookup)
+            .await
+            .expect("resolve env");
+        assert_eq!(env["DB_PORT"], "5432");
+        assert_eq!(env["AUTH"], "http://localhost:4000");
+        assert_eq!(env["TOKEN"], "from-dotenv");
+        assert_eq!(env["USER"], "alice");
+        assert!(ConfigLoader::check_env_references(&config, &host_env).is_empty());
+        assert_eq!(
+            ConfigLoader::referenced_env_names(&config),
+            BTreeSet::from([
+                "API_TOKEN".to_string(),
+                "LOCALD_TEST_UNSET".to_string(),
+                "USER".to_string(),
+            ])
+        );
+
+        let resolved = ConfigLoader::resolve_service_config(api, &config, &host_env, &lookup)
+            .await
+            .expect("resolve service");
+        assert_eq!(
+            ConfigLoader::service_command(&resolved).as_deref(),
+            Some("api --db postgres://localhost:5432/postgres --cwd /tmp ${PORT}")
+        );
+        assert_eq!(
+            ConfigLoader::service_workdir(&resolved).as_deref(),
+            Some("shop")
+        );
+        let Some(HealthCheckConfig::Probe(probe)) = resolved.health_check() else {
+            unreachable!("expected an http probe");
+        };
+        assert_eq!(probe.path.as_deref(), Some("/health/shop.localhost"));
+
+        // Unknown references name the service and key
+        let mut broken = config.clone();
+        broken.services.insert(
+            "web".to_string(),
+            toml::from_str(r#"command = "web ${services.dbb.url}""#).expect("parse service"),
+        );
+        let error = ConfigLoader::validate_references(&broken.services["web"], &broken)
+            .expect_err("dbb doesn't exist");
+        assert_eq!(
+            error,
+            ("command".to_string(), "unknown service 'dbb'".to_string())
+        );
+        broken.services.insert(
+            "web".to_string(),
+            toml::from_str(r#"command = "web ${services.api.password}""#).expect("parse service"),
+        );
+        let error = ConfigLoader::validate_references(&broken.services["web"], &broken)
+            .expect_err("api isn't a Postgres service");
+        assert_eq!(error.0, "command");
+        assert!(
+            error.1.starts_with("'password' is only available"),
+            "{error:?}"
+        );
+        let env = HashMap::from([("X".to_string(), "${env.LOCALD_TEST_UNSET}".to_string())]);
+        let error = ConfigLoader::resolve_env(&env, &config, &host_env, &lookup)
+            .await
+            .expect_err("the variable is unset");
+        assert!(
+            error
+                .to_string()
+                .starts_with("env.X: environment variable LOCALD_TEST_UNSET is not set"),
+            "{error}"
+        );
+        let problems = ConfigLoader::check_env_references(&config, &HashMap::new());
+        let keys: Vec<String> = problems.iter().map(ConfigProblem::dotted_key).collect();
+        assert_eq!(keys, ["services.api.env.TOKEN", "services.api.env.USER"]);
+    }
 }
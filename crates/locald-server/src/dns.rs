@@ -0,0 +1,295 @@
+//! A small DNS server for project domains (`[dns] enabled = true`).
+//!
+//! `/etc/hosts` can't express wildcards and has to be rewritten through the shim
+//! whenever a project changes. This server answers A and AAAA queries for
+//! `*.localhost`, each project's root domain and any name below it with the loopback
+//! address, reading the domains from the live [`ServiceResolver`] on every query.
+//! Everything else is relayed unchanged to an upstream resolver.
+//!
+//! Only UDP is served; replies from upstream are relayed as-is.
+
+use anyhow::{Context, Result};
+use locald_core::resolver::ServiceResolver;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
+
+/// Used when no upstream is configured and `/etc/resolv.conf` has no usable nameserver.
+const FALLBACK_UPSTREAM: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53);
+
+/// How long to wait for the upstream resolver.
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Kept short so answers track projects being added and removed.
+const LOCAL_TTL: u32 = 5;
+
+/// The largest datagram we accept or relay.
+const MAX_DATAGRAM: usize = 4096;
+
+const HEADER_LEN: usize = 12;
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+/// Answers lookups for project domains and forwards the rest.
+#[derive(Debug)]
+pub struct DnsServer {
+    resolver: Arc<dyn ServiceResolver>,
+    upstream: SocketAddr,
+}
+
+impl DnsServer {
+    pub fn new(resolver: Arc<dyn ServiceResolver>, upstream: SocketAddr) -> Self {
+        Self { resolver, upstream }
+    }
+
+    /// Binds the DNS port on `127.0.0.1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the port is in use, or is privileged and we aren't root.
+    pub async fn bind(port: u16) -> Result<UdpSocket> {
+        UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], port)))
+            .await
+            .with_context(|| format!("Failed to bind DNS port {port}"))
+    }
+
+    /// Answers queries on `socket` until the task is dropped.
+    pub async fn serve(self: Arc<Self>, socket: UdpSocket) {
+        if let Ok(addr) = socket.local_addr() {
+            info!(
+                "DNS server listening on {addr}, forwarding to {}",
+                self.upstream
+            );
+        }
+        let socket = Arc::new(socket);
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("Failed to receive DNS query: {e}");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+
+            let query = buf[..len].to_vec();
+            let server = self.clone();
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                let Some(response) = server.respond(&query).await else {
+                    return;
+                };
+                if let Err(e) = socket.send_to(&response, peer).await {
+                    debug!("Failed to send DNS response to {peer}: {e}");
+                }
+            });
+        }
+    }
+
+    /// Builds the response to a query, answering locally or asking upstream.
+    pub async fn respond(&self, query: &[u8]) -> Option<Vec<u8>> {
+        if let Some(question) = Question::parse(query) {
+            let domains = self.resolver.local_domains().await;
+            if is_local(&question.name, &domains) {
+                return Some(question.answer(query));
+            }
+        }
+        match self.forward(query).await {
+            Ok(response) => Some(response),
+            Err(e) => {
+                debug!("Failed to forward DNS query to {}: {e:#}", self.upstream);
+                None
+            }
+        }
+    }
+
+    async fn forward(&self, query: &[u8]) -> Result<Vec<u8>> {
+        let bind: SocketAddr = if self.upstream.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(self.upstream).await?;
+        socket.send(query).await?;
+
+        let mut buf = vec![0; MAX_DATAGRAM];
+        let len = tokio::time::timeout(UPSTREAM_TIMEOUT, socket.recv(&mut buf))
+            .await
+            .context("Upstream DNS server timed out")??;
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+/// Picks the resolver to forward to: the first nameserver in `/etc/resolv.conf`
+/// that isn't `own` (which would loop back to us).
+pub async fn system_upstream(own: SocketAddr) -> SocketAddr {
+    let content = tokio::fs::read_to_string("/etc/resolv.conf")
+        .await
+        .unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|addr| addr.trim().parse().ok())
+        .map(|ip| SocketAddr::new(ip, 53))
+        .find(|addr| *addr != own)
+        .unwrap_or(FALLBACK_UPSTREAM)
+}
+
+/// Whether `name` is `localhost`, below it, or at or below one of `domains`.
+fn is_local(name: &str, domains: &[String]) -> bool {
+    std::iter::once("localhost")
+        .chain(domains.iter().map(String::as_str))
+        .any(|domain| {
+            name == domain
+                || name
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+}
+
+/// The single question of a standard query.
+#[derive(Debug, PartialEq, Eq)]
+struct Question {
+    /// The lowercased name, without the trailing dot.
+    name: String,
+    qtype: u16,
+    qclass: u16,
+    /// Where the question ends in the query.
+    end: usize,
+}
+
+impl Question {
+    /// Parses a standard query with exactly one question. Anything else (responses,
+    /// other opcodes, compressed names) returns `None` and is left to upstream.
+    fn parse(query: &[u8]) -> Option<Self> {
+        let header = query.get(..HEADER_LEN)?;
+        let is_response = header[2] & 0x80 != 0;
+        let opcode = (header[2] >> 3) & 0x0f;
+        let qdcount = u16::from_be_bytes([header[4], header[5]]);
+        if is_response || opcode != 0 || qdcount != 1 {
+            return None;
+        }
+
+        let mut labels = Vec::new();
+        let mut pos = HEADER_LEN;
+        loop {
+            let len = usize::from(*query.get(pos)?);
+            pos += 1;
+            if len == 0 {
+                break;
+            }
+            // Compression pointers and extended label types don't appear in questions
+            if len > 63 {
+                return None;
+            }
+            let label = query.get(pos..pos + len)?;
+            labels.push(std::str::from_utf8(label).ok()?.to_ascii_lowercase());
+            pos += len;
+        }
+
+        let fixed = query.get(pos..pos + 4)?;
+        Some(Self {
+            name: labels.join("."),
+            qtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+            qclass: u16::from_be_bytes([fixed[2], fixed[3]]),
+            end: pos + 4,
+        })
+    }
+
+    /// Answers with the loopback address. Other record types for a local name get an
+    /// empty answer rather than NXDOMAIN, since the name itself exists.
+    fn answer(&self, query: &[u8]) -> Vec<u8> {
+        let (rdata, rdlength): (&[u8], u16) = match (self.qclass, self.qtype) {
+            (CLASS_IN, TYPE_A) => (&Ipv4Addr::LOCALHOST.octets(), 4),
+            (CLASS_IN, TYPE_AAAA) => (&Ipv6Addr::LOCALHOST.octets(), 16),
+            _ => (&[], 0),
+        };
+        let answers: u16 = (rdlength > 0).into();
+
+        let mut response = Vec::with_capacity(self.end + 16 + rdata.len());
+        response.extend_from_slice(&query[..2]);
+        // QR, AA and RA set; RD copied from the query; NOERROR
+        response.push(0x84 | (query[2] & 0x01));
+        response.push(0x80);
+        response.extend_from_slice(&1u16.to_be_bytes());
+        response.extend_from_slice(&answers.to_be_bytes());
+        response.extend_from_slice(&[0, 0, 0, 0]);
+        response.extend_from_slice(&query[HEADER_LEN..self.end]);
+
+        if answers > 0 {
+            // A pointer back to the name in the question
+            response.extend_from_slice(&[0xc0, 0x0c]);
+            response.extend_from_slice(&self.qtype.to_be_bytes());
+            response.extend_from_slice(&CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&LOCAL_TTL.to_be_bytes());
+            response.extend_from_slice(&rdlength.to_be_bytes());
+            response.extend_from_slice(rdata);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            query.push(u8::try_from(label.len()).unwrap_or_default());
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.push(0);
+        query.extend_from_slice(&qtype.to_be_bytes());
+        query.extend_from_slice(&CLASS_IN.to_be_bytes());
+        query
+    }
+
+    #[test]
+    fn test_answers_local_names_with_loopback() {
+        let domains = vec!["shop.test".to_string()];
+        assert!(is_local("localhost", &domains));
+        assert!(is_local("api.shop.localhost", &domains));
+        assert!(is_local("shop.test", &domains));
+        assert!(is_local("pr-42.api.shop.test", &domains));
+        assert!(!is_local("myshop.test", &domains));
+        assert!(!is_local("example.com", &domains));
+
+        let a = query("API.Shop.Test", TYPE_A);
+        let Some(question) = Question::parse(&a) else {
+            unreachable!("query should parse");
+        };
+        assert_eq!(question.name, "api.shop.test");
+        let response = question.answer(&a);
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!(response[2], 0x85);
+        assert_eq!(&response[6..8], &[0, 1]);
+        assert_eq!(&response[response.len() - 4..], &[127, 0, 0, 1]);
+
+        let aaaa = query("shop.test", TYPE_AAAA);
+        let Some(question) = Question::parse(&aaaa) else {
+            unreachable!("query should parse");
+        };
+        let response = question.answer(&aaaa);
+        assert_eq!(
+            &response[response.len() - 16..],
+            &Ipv6Addr::LOCALHOST.octets()
+        );
+
+        let mx = query("shop.test", 15);
+        let Some(question) = Question::parse(&mx) else {
+            unreachable!("query should parse");
+        };
+        let response = question.answer(&mx);
+        assert_eq!(&response[6..8], &[0, 0]);
+        assert_eq!(response.len(), mx.len());
+
+        assert!(Question::parse(&a[..HEADER_LEN + 3]).is_none());
+    }
+}
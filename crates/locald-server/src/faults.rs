@@ -0,0 +1,204 @@
+//! Fault injection in the proxy (`[services.<name>.faults]`, `locald proxy fault`).
+//!
+//! Rules come from `locald.toml` and can be switched on, off or replaced at runtime.
+//! For each proxied request the rules are rolled once: the request is delayed, then
+//! either its connection is dropped, it is answered with an error, or it is forwarded
+//! with its response body throttled.
+
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use futures_util::{StreamExt, stream};
+use http_body_util::{BodyStream, StreamBody};
+use hyper::StatusCode;
+use hyper::body::Frame;
+use locald_core::config::FaultConfig;
+use rand::Rng;
+use std::time::Duration;
+
+/// A runtime change to a service's fault rules, made through the API or CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultOverride {
+    /// Whether faults are injected.
+    pub enabled: bool,
+    /// Rules replacing those in `locald.toml`, if any.
+    pub rules: Option<FaultConfig>,
+}
+
+/// The rules a service ends up with, and whether they are being injected.
+#[must_use]
+pub fn effective(
+    configured: Option<FaultConfig>,
+    runtime: Option<FaultOverride>,
+) -> Option<(FaultConfig, bool)> {
+    match runtime {
+        Some(FaultOverride { enabled, rules }) => rules.or(configured).map(|r| (r, enabled)),
+        None => configured.map(|r| (r, r.enabled)),
+    }
+}
+
+/// What happens to one request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Forward the request, limiting the response to `bandwidth` bytes per second.
+    Forward { bandwidth: Option<u64> },
+    /// Answer with this status instead of forwarding.
+    Error(StatusCode),
+    /// Close the connection without answering.
+    Reset,
+}
+
+/// Rolls `rules` for one request, returning the delay to add and what to do after it.
+pub fn roll(rules: &FaultConfig, rng: &mut impl Rng) -> (Duration, Fault) {
+    let jitter = if rules.jitter_ms > 0 {
+        rng.gen_range(0..=rules.jitter_ms)
+    } else {
+        0
+    };
+    let delay = Duration::from_millis(rules.latency_ms.saturating_add(jitter));
+
+    let fault = if rng.gen_range(0..100) < rules.reset_rate {
+        Fault::Reset
+    } else if rng.gen_range(0..100) < rules.error_rate {
+        Fault::Error(
+            StatusCode::from_u16(rules.error_status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
+        )
+    } else {
+        Fault::Forward {
+            bandwidth: rules.bandwidth,
+        }
+    };
+    (delay, fault)
+}
+
+/// Rolls `rules` for one request and waits out the injected delay.
+pub async fn inject(rules: &FaultConfig) -> Fault {
+    let (delay, fault) = roll(rules, &mut rand::thread_rng());
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    fault
+}
+
+/// A response whose body fails before anything is sent, so the server drops the
+/// connection and the client sees it closed or reset.
+#[must_use]
+pub fn reset_response() -> Response {
+    let body = stream::once(async {
+        Err::<Bytes, _>(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "connection reset by locald fault injection",
+        ))
+    });
+    Response::new(Body::from_stream(body))
+}
+
+/// An injected error, marked so it isn't mistaken for the service's own.
+#[must_use]
+pub fn error_response(status: StatusCode) -> Response {
+    let mut res = Response::new(Body::from(format!(
+        "{status} (injected by locald fault rules)\n"
+    )));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        "x-locald-fault",
+        hyper::header::HeaderValue::from_static("error"),
+    );
+    res
+}
+
+/// Streams the response body at no more than `bytes_per_sec`.
+#[must_use]
+pub fn throttle(res: Response, bytes_per_sec: u64) -> Response {
+    let bytes_per_sec = bytes_per_sec.max(1);
+    // Send a tenth of a second's worth at a time, so the rate is smooth
+    let piece = usize::try_from(bytes_per_sec / 10)
+        .unwrap_or(usize::MAX)
+        .max(1);
+
+    let (parts, body) = res.into_parts();
+    // Work on frames rather than data so trailers (e.g. gRPC's) still arrive
+    let frames = BodyStream::new(body)
+        .flat_map(move |frame| {
+            let frames = match frame.map(Frame::into_data) {
+                Ok(Ok(mut bytes)) => {
+                    let mut pieces = Vec::new();
+                    while !bytes.is_empty() {
+                        pieces.push(Ok(Frame::data(bytes.split_to(piece.min(bytes.len())))));
+                    }
+                    pieces
+                }
+                Ok(Err(trailers)) => vec![Ok(trailers)],
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(frames)
+        })
+        .then(move |frame| async move {
+            if let Some(bytes) = frame.as_ref().ok().and_then(Frame::data_ref) {
+                let len = u64::try_from(bytes.len()).unwrap_or(u64::MAX);
+                let micros = len.saturating_mul(1_000_000) / bytes_per_sec;
+                tokio::time::sleep(Duration::from_micros(micros)).await;
+            }
+            frame
+        });
+    Response::from_parts(parts, Body::new(StreamBody::new(frames)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_roll_applies_rates_and_latency() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+        let always_error = FaultConfig {
+            latency_ms: 100,
+            jitter_ms: 50,
+            error_rate: 100,
+            error_status: 502,
+            ..FaultConfig::default()
+        };
+        for _ in 0..20 {
+            let (delay, fault) = roll(&always_error, &mut rng);
+            assert!((100..=150).contains(&delay.as_millis()));
+            assert_eq!(fault, Fault::Error(StatusCode::BAD_GATEWAY));
+        }
+
+        let always_reset = FaultConfig {
+            reset_rate: 100,
+            error_rate: 100,
+            ..FaultConfig::default()
+        };
+        assert_eq!(roll(&always_reset, &mut rng).1, Fault::Reset);
+
+        let throttled = FaultConfig {
+            bandwidth: Some(1000),
+            ..FaultConfig::default()
+        };
+        assert_eq!(
+            roll(&throttled, &mut rng),
+            (
+                Duration::ZERO,
+                Fault::Forward {
+                    bandwidth: Some(1000)
+                }
+            )
+        );
+
+        let configured = FaultConfig {
+            enabled: false,
+            ..always_error
+        };
+        assert_eq!(effective(Some(configured), None), Some((configured, false)));
+        let on = FaultOverride {
+            enabled: true,
+            rules: None,
+        };
+        assert_eq!(
+            effective(Some(configured), Some(on)),
+            Some((configured, true))
+        );
+        assert_eq!(effective(None, Some(on)), None);
+    }
+}
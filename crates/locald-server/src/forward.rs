@@ -0,0 +1,275 @@
+//! Stable TCP and UDP ports in front of services (`expose = { tcp = 5432 }`).
+//!
+//! Services get a new port whenever they start, which breaks database GUIs and other
+//! clients pointed at them. Each exposed port is a listener owned by the
+//! [`ProxyManager`](crate::proxy::ProxyManager) that looks up the service's current
+//! port for every new connection (or UDP peer) and relays traffic to it.
+
+use anyhow::{Context, Result};
+use locald_core::resolver::{ExposeProtocol, ExposedPort, ServiceResolver};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How long a UDP peer can stay quiet before its upstream socket is closed.
+const UDP_SESSION_IDLE: Duration = Duration::from_mins(1);
+
+/// The largest UDP datagram.
+const MAX_DATAGRAM: usize = 65_535;
+
+/// How long to wait before binding a port again after it failed; doubled after each
+/// further failure, up to [`MAX_BIND_RETRY`].
+const MIN_BIND_RETRY: Duration = Duration::from_secs(5);
+const MAX_BIND_RETRY: Duration = Duration::from_mins(5);
+
+type ForwardKey = (ExposeProtocol, u16);
+
+/// A running listener, stopped when dropped.
+#[derive(Debug)]
+struct Forward {
+    service: String,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Forward {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// When a port that failed to bind is tried again.
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    at: Instant,
+    delay: Duration,
+}
+
+/// The exposed ports currently being listened on.
+#[derive(Debug, Default)]
+pub(crate) struct Forwards {
+    active: HashMap<ForwardKey, Forward>,
+    /// Ports that failed to bind, so the warning isn't repeated on every pass.
+    failed: HashMap<ForwardKey, String>,
+    /// Ports that failed to bind aren't retried on every pass, since binding a privileged
+    /// port launches `locald-shim`.
+    retries: HashMap<ForwardKey, Retry>,
+}
+
+impl Forwards {
+    /// Starts listeners for newly exposed ports and stops those no longer wanted.
+    pub(crate) async fn reconcile(&mut self, resolver: &Arc<dyn ServiceResolver>) {
+        let mut wanted: HashMap<ForwardKey, String> = HashMap::new();
+        for ExposedPort {
+            service,
+            protocol,
+            port,
+        } in resolver.exposed_ports().await
+        {
+            match wanted.entry((protocol, port)) {
+                Entry::Occupied(existing) => {
+                    if self.failed.get(existing.key()) != Some(&service) {
+                        warn!(
+                            "{protocol} port {port} is exposed by both {} and {service}; ignoring {service}",
+                            existing.get()
+                        );
+                        self.failed.insert((protocol, port), service);
+                    }
+                }
+                Entry::Vacant(slot) => {
+                    slot.insert(service);
+                }
+            }
+        }
+
+        self.active
+            .retain(|key, forward| wanted.get(key) == Some(&forward.service));
+        self.failed.retain(|key, _| wanted.contains_key(key));
+        self.retries.retain(|key, _| wanted.contains_key(key));
+
+        let now = Instant::now();
+        for ((protocol, port), service) in wanted {
+            let key = (protocol, port);
+            let retry = self.retries.get(&key).copied();
+            if self.active.contains_key(&key) || retry.is_some_and(|retry| now < retry.at) {
+                continue;
+            }
+            match spawn(protocol, port, &service, resolver.clone()).await {
+                Ok(task) => {
+                    info!("Forwarding {protocol} port {port} to {service}");
+                    self.failed.remove(&key);
+                    self.retries.remove(&key);
+                    self.active.insert(key, Forward { service, task });
+                }
+                Err(e) => {
+                    let delay = retry.map_or(MIN_BIND_RETRY, |retry| {
+                        retry.delay.saturating_mul(2).min(MAX_BIND_RETRY)
+                    });
+                    self.retries.insert(
+                        key,
+                        Retry {
+                            at: now + delay,
+                            delay,
+                        },
+                    );
+                    if self.failed.get(&key) != Some(&service) {
+                        warn!("Failed to expose {protocol} port {port} for {service}: {e:#}");
+                        self.failed.insert(key, service);
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn spawn(
+    protocol: ExposeProtocol,
+    port: u16,
+    service: &str,
+    resolver: Arc<dyn ServiceResolver>,
+) -> Result<JoinHandle<()>> {
+    let service = service.to_string();
+    match protocol {
+        ExposeProtocol::Tcp => {
+            let listener = bind_tcp(port).await?;
+            Ok(tokio::spawn(serve_tcp(listener, service, resolver)))
+        }
+        ExposeProtocol::Udp => {
+            let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], port)))
+                .await
+                .with_context(|| format!("Failed to bind UDP port {port}"))?;
+            Ok(tokio::spawn(serve_udp(socket, service, resolver)))
+        }
+    }
+}
+
+/// Binds on loopback, going through `locald-shim` for privileged ports.
+async fn bind_tcp(port: u16) -> Result<TcpListener> {
+    if port < 1024 {
+        match crate::shim_client::bind_privileged_loopback_port(port).await {
+            // Exposed ports are for this machine only, whatever the shim bound
+            Ok(listener) if !listener.local_addr()?.ip().is_loopback() => {
+                anyhow::bail!("locald-shim bound port {port} beyond loopback");
+            }
+            Ok(listener) => {
+                listener.set_nonblocking(true)?;
+                return Ok(TcpListener::from_std(listener)?);
+            }
+            Err(e) => warn!(
+                "Failed to acquire privileged port {} via shim: {}. Falling back to direct bind.",
+                port, e
+            ),
+        }
+    }
+    TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))
+        .await
+        .with_context(|| format!("Failed to bind TCP port {port}"))
+}
+
+async fn serve_tcp(listener: TcpListener, service: String, resolver: Arc<dyn ServiceResolver>) {
+    loop {
+        let (mut inbound, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept connection for {service}: {e}");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let service = service.clone();
+        let resolver = resolver.clone();
+        tokio::spawn(async move {
+            // Looked up per connection, so restarts on a new port are picked up
+            let Some(port) = resolver.resolve_service_port(&service).await else {
+                debug!("{service} is not running; closing connection from {peer}");
+                return;
+            };
+            match TcpStream::connect(("localhost", port)).await {
+                Ok(mut outbound) => {
+                    if let Err(e) = copy_bidirectional(&mut inbound, &mut outbound).await {
+                        debug!("Connection from {peer} to {service} ended: {e}");
+                    }
+                }
+                Err(e) => warn!("Failed to connect to {service} on port {port}: {e}"),
+            }
+        });
+    }
+}
+
+async fn serve_udp(socket: UdpSocket, service: String, resolver: Arc<dyn ServiceResolver>) {
+    let socket = Arc::new(socket);
+    // One upstream socket per peer, so replies can be routed back
+    let sessions: Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>> = Arc::default();
+    let mut buf = vec![0; MAX_DATAGRAM];
+
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive datagram for {service}: {e}");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let existing = sessions.lock().await.get(&peer).cloned();
+        let upstream = if let Some(upstream) = existing {
+            upstream
+        } else {
+            let Some(port) = resolver.resolve_service_port(&service).await else {
+                debug!("{service} is not running; dropping datagram from {peer}");
+                continue;
+            };
+            match connect_udp(port).await {
+                Ok(upstream) => {
+                    let upstream = Arc::new(upstream);
+                    sessions.lock().await.insert(peer, upstream.clone());
+                    tokio::spawn(relay_replies(
+                        socket.clone(),
+                        upstream.clone(),
+                        peer,
+                        sessions.clone(),
+                    ));
+                    upstream
+                }
+                Err(e) => {
+                    warn!("Failed to forward datagram to {service} on port {port}: {e:#}");
+                    continue;
+                }
+            }
+        };
+
+        if let Err(e) = upstream.send(&buf[..len]).await {
+            debug!("Failed to forward datagram from {peer} to {service}: {e}");
+        }
+    }
+}
+
+async fn connect_udp(port: u16) -> Result<UdpSocket> {
+    let upstream = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    upstream.connect(("127.0.0.1", port)).await?;
+    Ok(upstream)
+}
+
+/// Sends the service's replies back to `peer` until the session goes idle.
+async fn relay_replies(
+    socket: Arc<UdpSocket>,
+    upstream: Arc<UdpSocket>,
+    peer: SocketAddr,
+    sessions: Arc<Mutex<HashMap<SocketAddr, Arc<UdpSocket>>>>,
+) {
+    let mut buf = vec![0; MAX_DATAGRAM];
+    while let Ok(Ok(len)) = tokio::time::timeout(UDP_SESSION_IDLE, upstream.recv(&mut buf)).await {
+        if let Err(e) = socket.send_to(&buf[..len], peer).await {
+            debug!("Failed to send reply to {peer}: {e}");
+        }
+    }
+    sessions.lock().await.remove(&peer);
+}
@@ -0,0 +1,265 @@
+//! Header rules and CORS in the proxy (`[services.<name>.headers]`,
+//! `[services.<name>.cors]`).
+//!
+//! Request rules run before a request is forwarded (and captured), response rules and
+//! CORS headers after the service answers. CORS preflights are answered by the proxy
+//! without reaching the service.
+
+use axum::body::Body;
+use axum::response::Response;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, StatusCode};
+use locald_core::config::{CorsConfig, HeaderChanges, HeaderRules};
+use std::net::IpAddr;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Marks requests that arrived over HTTPS, so `X-Forwarded-Proto` is right.
+#[derive(Debug, Clone, Copy)]
+pub struct Https;
+
+/// The host (and port) a request is for. HTTP/2 clients send it as `:authority`,
+/// which hyper puts in the URI instead of adding a `Host` header.
+#[must_use]
+pub fn authority<'a>(uri: &'a hyper::Uri, headers: &'a HeaderMap) -> Option<&'a str> {
+    uri.authority()
+        .map(hyper::http::uri::Authority::as_str)
+        .or_else(|| headers.get(header::HOST)?.to_str().ok())
+}
+
+/// Where a request came from, for the `X-Forwarded-*` headers.
+#[derive(Debug, Clone)]
+pub struct Forwarded {
+    /// The client's address, if known.
+    pub client: Option<IpAddr>,
+    /// The host the client asked for (see [`authority`]), without the port.
+    pub host: String,
+    /// Whether the client connected over HTTPS.
+    pub https: bool,
+}
+
+/// Applies `rules` to a request on its way to the service. Returns the request id to
+/// echo in the response, if `request_id` is on.
+pub fn apply_request(
+    rules: &HeaderRules,
+    headers: &mut HeaderMap,
+    forwarded: &Forwarded,
+) -> Option<HeaderValue> {
+    if rules.forwarded {
+        if let Some(client) = forwarded.client {
+            let forwarded_for = match headers.get(&X_FORWARDED_FOR).map(HeaderValue::to_str) {
+                Some(Ok(existing)) => format!("{existing}, {client}"),
+                _ => client.to_string(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+                headers.insert(X_FORWARDED_FOR, value);
+            }
+        }
+        if let Ok(host) = HeaderValue::from_str(&forwarded.host) {
+            headers.insert(X_FORWARDED_HOST, host);
+        }
+        let proto = if forwarded.https { "https" } else { "http" };
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static(proto));
+    }
+
+    let request_id = rules.request_id.then(|| {
+        headers.get(&X_REQUEST_ID).cloned().unwrap_or_else(|| {
+            HeaderValue::from_str(&uuid::Uuid::new_v4().to_string())
+                .unwrap_or_else(|_| HeaderValue::from_static("locald"))
+        })
+    });
+    if let Some(id) = &request_id {
+        headers.insert(X_REQUEST_ID, id.clone());
+    }
+
+    apply_changes(&rules.request, headers);
+    request_id
+}
+
+/// Applies `rules` to the service's response.
+pub fn apply_response(
+    rules: &HeaderRules,
+    headers: &mut HeaderMap,
+    request_id: Option<HeaderValue>,
+) {
+    if let Some(id) = request_id {
+        headers.insert(X_REQUEST_ID, id);
+    }
+    apply_changes(&rules.response, headers);
+}
+
+fn apply_changes(changes: &HeaderChanges, headers: &mut HeaderMap) {
+    for name in &changes.remove {
+        headers.remove(name.as_str());
+    }
+    for (name, value) in &changes.set {
+        // Names and values were checked when the config was loaded
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Answers a CORS preflight, if the request is one.
+pub fn preflight(cors: &CorsConfig, method: &Method, headers: &HeaderMap) -> Option<Response> {
+    if method != Method::OPTIONS || !headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+        return None;
+    }
+    let origin = headers.get(header::ORIGIN)?;
+
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = StatusCode::NO_CONTENT;
+    if !allow_origin(cors, origin, res.headers_mut()) {
+        // Without CORS headers the browser refuses the real request
+        return Some(res);
+    }
+
+    let res_headers = res.headers_mut();
+    if let Ok(methods) = HeaderValue::from_str(&cors.methods.join(", ")) {
+        res_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+    }
+    let allowed_headers = if cors.headers.is_empty() {
+        headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS).cloned()
+    } else {
+        HeaderValue::from_str(&cors.headers.join(", ")).ok()
+    };
+    if let Some(allowed_headers) = allowed_headers {
+        res_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+    }
+    if let Some(max_age) = cors.max_age {
+        res_headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+    }
+    Some(res)
+}
+
+/// Adds CORS headers to a response for a request from `origin`.
+pub fn apply_cors(cors: &CorsConfig, origin: Option<&HeaderValue>, headers: &mut HeaderMap) {
+    let Some(origin) = origin else {
+        return;
+    };
+    if allow_origin(cors, origin, headers) && !cors.expose_headers.is_empty() {
+        if let Ok(expose) = HeaderValue::from_str(&cors.expose_headers.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+        }
+    }
+}
+
+/// Sets `Access-Control-Allow-Origin` (and `-Credentials`) if `origin` is allowed.
+fn allow_origin(cors: &CorsConfig, origin: &HeaderValue, headers: &mut HeaderMap) -> bool {
+    let allowed = origin
+        .to_str()
+        .is_ok_and(|origin| cors.allows_origin(origin));
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    if !allowed {
+        return false;
+    }
+
+    // Credentials can't be combined with a wildcard, so echo the origin instead
+    let any = cors.origins.iter().any(|o| o == "*");
+    let value = if any && !cors.credentials {
+        HeaderValue::from_static("*")
+    } else {
+        origin.clone()
+    };
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    if cors.credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_request_rules() {
+        let rules = HeaderRules {
+            forwarded: true,
+            request_id: true,
+            request: HeaderChanges {
+                set: BTreeMap::from([("x-user".to_string(), "dev".to_string())]),
+                remove: vec!["cookie".to_string()],
+            },
+            ..HeaderRules::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("session=1"));
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("10.0.0.1"));
+        let forwarded = Forwarded {
+            client: Some(IpAddr::from([127, 0, 0, 1])),
+            host: "api.shop.localhost".to_string(),
+            https: true,
+        };
+
+        let id = apply_request(&rules, &mut headers, &forwarded);
+        assert!(id.is_some());
+        assert_eq!(headers.get(&X_REQUEST_ID), id.as_ref());
+        assert_eq!(headers[&X_FORWARDED_FOR], "10.0.0.1, 127.0.0.1");
+        assert_eq!(headers[&X_FORWARDED_HOST], "api.shop.localhost");
+        assert_eq!(headers[&X_FORWARDED_PROTO], "https");
+        assert_eq!(headers["x-user"], "dev");
+        assert!(!headers.contains_key(header::COOKIE));
+
+        // A client's own request id is kept
+        let mut headers = HeaderMap::new();
+        headers.insert(X_REQUEST_ID, HeaderValue::from_static("abc"));
+        let id = apply_request(&rules, &mut headers, &forwarded);
+        assert_eq!(id, Some(HeaderValue::from_static("abc")));
+    }
+
+    #[test]
+    fn test_cors_preflight_and_response() {
+        let cors = CorsConfig {
+            origins: vec!["https://web.localhost".to_string()],
+            credentials: true,
+            max_age: Some(600),
+            ..CorsConfig::default()
+        };
+        let mut request = HeaderMap::new();
+        request.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://web.localhost"),
+        );
+        request.insert(
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static("PUT"),
+        );
+        request.insert(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_static("content-type"),
+        );
+
+        assert!(preflight(&cors, &Method::GET, &request).is_none());
+        let Some(res) = preflight(&cors, &Method::OPTIONS, &request) else {
+            unreachable!("OPTIONS with Access-Control-Request-Method is a preflight");
+        };
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let allowed = res.headers();
+        assert_eq!(
+            allowed[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://web.localhost"
+        );
+        assert_eq!(allowed[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(
+            allowed[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+        assert_eq!(allowed[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let mut response = HeaderMap::new();
+        let other = HeaderValue::from_static("https://evil.example");
+        apply_cors(&cors, Some(&other), &mut response);
+        assert!(!response.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
@@ -30,6 +30,24 @@ impl HealthMonitor {
         }
     }
 
+    /// Whether `spawn_check` will start a readiness probe for a service with this configuration.
+    ///
+    /// When it does, the service should stay `Starting` until the probe reports back.
+    pub(crate) fn has_check(
+        config: &ServiceConfig,
+        port: Option<u16>,
+        has_docker_healthcheck: bool,
+    ) -> bool {
+        match config.health_check() {
+            Some(HealthCheckConfig::Command(_)) => true,
+            Some(HealthCheckConfig::Probe(probe)) => match probe.kind {
+                ProbeType::Http | ProbeType::Tcp => port.is_some(),
+                ProbeType::Command => probe.command.is_some(),
+            },
+            None => has_docker_healthcheck || port.is_some(),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn spawn_check(
         &self,
@@ -171,9 +189,10 @@ impl HealthMonitor {
                             proxy_ports,
                             snapshot,
                             service.service_config.clone(),
-                            service.config.project.workspace.clone(),
-                            service.config.project.constellation.clone(),
+                            service.config.clone(),
                             service.warnings.clone(),
+                            service.restart_state,
+                            service.task_run.clone(),
                         )),
                     )
                 } else {
@@ -191,9 +210,10 @@ impl HealthMonitor {
                 proxy_ports,
                 snapshot,
                 service_config,
-                workspace,
-                constellation,
+                config,
                 warnings,
+                restart_state,
+                task_run,
             )) = snapshot_info
             {
                 let status = crate::manager::ProcessManager::build_service_status(
@@ -205,9 +225,10 @@ impl HealthMonitor {
                     source,
                     snapshot,
                     Some(&service_config),
-                    workspace,
-                    constellation,
+                    Some(&config),
                     warnings,
+                    restart_state,
+                    task_run,
                 )
                 .await;
 
@@ -254,11 +275,12 @@ impl HealthMonitor {
                             proxy_ports,
                             snapshot,
                             service.service_config.clone(),
-                            service.config.project.workspace.clone(),
-                            service.config.project.constellation.clone(),
+                            service.config.clone(),
                             service.warnings.clone(),
                             service.health_status,
                             service.health_source,
+                            service.restart_state,
+                            service.task_run.clone(),
                         )),
                     )
                 }
@@ -274,11 +296,12 @@ impl HealthMonitor {
                 proxy_ports,
                 snapshot,
                 service_config,
-                workspace,
-                constellation,
+                config,
                 warnings,
                 health_status,
                 health_source,
+                restart_state,
+                task_run,
             )) = snapshot_info
             {
                 let status = crate::manager::ProcessManager::build_service_status(
@@ -290,9 +313,10 @@ impl HealthMonitor {
                     health_source,
                     snapshot,
                     Some(&service_config),
-                    workspace,
-                    constellation,
+                    Some(&config),
                     warnings,
+                    restart_state,
+                    task_run,
                 )
                 .await;
 
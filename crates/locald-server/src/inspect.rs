@@ -0,0 +1,474 @@
+//! Capture and replay of HTTP traffic through the proxy (`locald requests`).
+//!
+//! Capture is off until turned on for a service. While it's on, the proxy records each
+//! request's method, path, headers and body, and the response's status, headers and
+//! body, into a per-service ring buffer. Bodies are copied as they stream through, up
+//! to [`MAX_CAPTURED_BODY`] bytes, so large downloads and SSE streams aren't held up.
+
+use crate::proxy::{Backends, Dispatch};
+use anyhow::{Context, Result};
+use axum::body::Body;
+use axum::extract::Request;
+use axum::response::{IntoResponse, Response};
+use http_body_util::BodyExt;
+use hyper::header::HeaderMap;
+use locald_core::ipc::{CapturedBody, CapturedRequest, CapturedResponse, RequestCapture};
+use locald_core::resolver::ServiceResolver;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// How many requests are kept per service; the oldest are dropped first.
+pub const MAX_CAPTURED_REQUESTS: usize = 200;
+
+/// How much of each request and response body is kept.
+pub const MAX_CAPTURED_BODY: usize = 64 * 1024;
+
+/// The per-service capture buffers, shared between the proxy and the API.
+#[derive(Debug, Clone, Default)]
+pub struct RequestInspector {
+    state: Arc<Mutex<InspectorState>>,
+}
+
+#[derive(Debug, Default)]
+struct InspectorState {
+    enabled: HashSet<String>,
+    services: HashMap<String, VecDeque<Exchange>>,
+    next_id: u64,
+}
+
+impl InspectorState {
+    fn exchange(&mut self, service: &str, id: u64) -> Option<&mut Exchange> {
+        self.services
+            .get_mut(service)?
+            .iter_mut()
+            .rev()
+            .find(|exchange| exchange.id == id)
+    }
+}
+
+/// A captured request and response, with the bodies kept as raw bytes.
+#[derive(Debug)]
+struct Exchange {
+    id: u64,
+    service: String,
+    timestamp: i64,
+    method: String,
+    host: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: BodyBuffer,
+    response: Option<ResponseHead>,
+    response_body: BodyBuffer,
+    error: Option<String>,
+    replay_of: Option<u64>,
+}
+
+#[derive(Debug)]
+struct ResponseHead {
+    status: u16,
+    headers: Vec<(String, String)>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct BodyBuffer {
+    data: Vec<u8>,
+    size: u64,
+}
+
+impl BodyBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        let room = MAX_CAPTURED_BODY.saturating_sub(self.data.len());
+        self.data.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        self.size += chunk.len() as u64;
+    }
+
+    fn to_captured(&self) -> CapturedBody {
+        CapturedBody::new(&self.data, self.size)
+    }
+}
+
+impl Exchange {
+    fn to_captured(&self) -> CapturedRequest {
+        CapturedRequest {
+            id: self.id,
+            service: self.service.clone(),
+            timestamp: self.timestamp,
+            method: self.method.clone(),
+            host: self.host.clone(),
+            path: self.path.clone(),
+            headers: self.headers.clone(),
+            body: self.body.to_captured(),
+            response: self.response.as_ref().map(|head| CapturedResponse {
+                status: head.status,
+                headers: head.headers.clone(),
+                body: self.response_body.to_captured(),
+                duration_ms: head.duration_ms,
+            }),
+            error: self.error.clone(),
+            replay_of: self.replay_of,
+        }
+    }
+}
+
+/// Which body of an exchange a chunk belongs to.
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+impl RequestInspector {
+    fn lock(&self) -> MutexGuard<'_, InspectorState> {
+        // The state stays consistent even if a holder panicked
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Whether requests to `service` are being captured.
+    #[must_use]
+    pub fn is_enabled(&self, service: &str) -> bool {
+        self.lock().enabled.contains(service)
+    }
+
+    /// Turns capture on or off for `service`. Turning it off keeps what was captured.
+    pub fn set_enabled(&self, service: &str, enabled: bool) {
+        let mut state = self.lock();
+        if enabled {
+            state.enabled.insert(service.to_string());
+        } else {
+            state.enabled.remove(service);
+        }
+    }
+
+    /// The requests captured for `service`, oldest first.
+    #[must_use]
+    pub fn list(&self, service: &str) -> RequestCapture {
+        let state = self.lock();
+        RequestCapture {
+            enabled: state.enabled.contains(service),
+            requests: state
+                .services
+                .get(service)
+                .map(|exchanges| exchanges.iter().map(Exchange::to_captured).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// One captured request.
+    #[must_use]
+    pub fn get(&self, service: &str, id: u64) -> Option<CapturedRequest> {
+        self.lock()
+            .exchange(service, id)
+            .map(|exchange| exchange.to_captured())
+    }
+
+    /// Forgets everything captured for `service`.
+    pub fn clear(&self, service: &str) {
+        self.lock().services.remove(service);
+    }
+
+    /// Starts capturing `req` if capture is on for `service`, copying its body as it is
+    /// forwarded. `path` is the path the service will see.
+    pub fn capture(&self, service: &str, req: Request, path: &str) -> (Request, Option<Capture>) {
+        if !self.is_enabled(service) {
+            return (req, None);
+        }
+
+        let host = crate::headers::authority(req.uri(), req.headers())
+            .unwrap_or_default()
+            .to_string();
+        let id = self.start(
+            service,
+            req.method().as_str(),
+            &host,
+            path,
+            req.headers(),
+            None,
+        );
+        let (parts, body) = req.into_parts();
+        let body = self.tee(body, service, id, Direction::Request);
+        (
+            Request::from_parts(parts, body),
+            Some(Capture {
+                inspector: self.clone(),
+                service: service.to_string(),
+                id,
+                started: Instant::now(),
+            }),
+        )
+    }
+
+    /// Sends a captured request to `service` again and captures the result. It goes the
+    /// way the proxy sends requests: with the service's header and fault rules, over its
+    /// protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request isn't found, its body wasn't captured in full,
+    /// the service isn't running or can't be reached.
+    pub async fn replay<R: ServiceResolver + ?Sized>(
+        &self,
+        resolver: &R,
+        service: &str,
+        id: u64,
+    ) -> Result<CapturedRequest> {
+        let original = self
+            .get(service, id)
+            .with_context(|| format!("No captured request {id} for {service}"))?;
+        if original.body.truncated {
+            anyhow::bail!(
+                "The body of request {id} was larger than {} KB and wasn't captured in full",
+                MAX_CAPTURED_BODY / 1024
+            );
+        }
+        let port = resolver
+            .resolve_service_port(service)
+            .await
+            .with_context(|| format!("{service} is not running"))?;
+
+        let mut builder = hyper::Request::builder()
+            .method(original.method.as_str())
+            .uri(format!("http://localhost:{port}{}", original.path));
+        let mut headers = HeaderMap::new();
+        for (name, value) in &original.headers {
+            if let (Ok(name), Ok(value)) = (
+                hyper::header::HeaderName::try_from(name.as_str()),
+                hyper::header::HeaderValue::try_from(value.as_str()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        if let Some(rules) = resolver.service_headers(service).await {
+            // The replay comes from locald, so there's no client to add to X-Forwarded-For
+            let forwarded = crate::headers::Forwarded {
+                client: None,
+                host: original
+                    .host
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                https: false,
+            };
+            crate::headers::apply_request(&rules, &mut headers, &forwarded);
+        }
+        if let Some(h) = builder.headers_mut() {
+            h.clone_from(&headers);
+        }
+        let req = builder.body(Body::from(original.body.bytes()))?;
+
+        let new_id = self.start(
+            service,
+            &original.method,
+            &original.host,
+            &original.path,
+            &headers,
+            Some(id),
+        );
+        let capture = Capture {
+            inspector: self.clone(),
+            service: service.to_string(),
+            id: new_id,
+            started: Instant::now(),
+        };
+        self.append(service, new_id, Direction::Request, &original.body.bytes());
+
+        let faults = resolver.service_faults(service).await;
+        let protocol = resolver.service_protocol(service).await;
+        let dispatch = Backends::new()
+            .dispatch(
+                faults.as_ref(),
+                protocol,
+                &original.host,
+                req,
+                Some(capture),
+            )
+            .await;
+        let response = match dispatch {
+            Dispatch::Injected(response) => response,
+            Dispatch::Sent {
+                response, capture, ..
+            } => match (response.await, capture) {
+                (Ok(res), Some(capture)) => capture.response(res.into_response()),
+                (Ok(res), None) => res.into_response(),
+                (Err(e), capture) => {
+                    if let Some(capture) = capture {
+                        capture.error(e.to_string());
+                    }
+                    anyhow::bail!("Failed to replay request {id} to {service}: {e}");
+                }
+            },
+        };
+        // Read the body so it's captured
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .ok();
+
+        self.get(service, new_id)
+            .context("Replayed request was dropped from the capture buffer")
+    }
+
+    fn start(
+        &self,
+        service: &str,
+        method: &str,
+        host: &str,
+        path: &str,
+        headers: &HeaderMap,
+        replay_of: Option<u64>,
+    ) -> u64 {
+        let mut state = self.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        let exchanges = state.services.entry(service.to_string()).or_default();
+        if exchanges.len() >= MAX_CAPTURED_REQUESTS {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(Exchange {
+            id,
+            service: service.to_string(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            method: method.to_string(),
+            host: host.to_string(),
+            path: path.to_string(),
+            headers: header_pairs(headers),
+            body: BodyBuffer::default(),
+            response: None,
+            response_body: BodyBuffer::default(),
+            error: None,
+            replay_of,
+        });
+        id
+    }
+
+    fn append(&self, service: &str, id: u64, direction: Direction, chunk: &[u8]) {
+        let mut state = self.lock();
+        if let Some(exchange) = state.exchange(service, id) {
+            match direction {
+                Direction::Request => exchange.body.push(chunk),
+                Direction::Response => exchange.response_body.push(chunk),
+            }
+        }
+    }
+
+    fn tee(&self, body: Body, service: &str, id: u64, direction: Direction) -> Body {
+        let inspector = self.clone();
+        let service = service.to_string();
+        // Map frames rather than data so trailers (e.g. gRPC's) pass through
+        Body::new(body.map_frame(move |frame| {
+            if let Some(chunk) = frame.data_ref() {
+                inspector.append(&service, id, direction, chunk);
+            }
+            frame
+        }))
+    }
+}
+
+/// A request being captured, waiting for its response.
+#[derive(Debug)]
+pub struct Capture {
+    inspector: RequestInspector,
+    service: String,
+    id: u64,
+    started: Instant,
+}
+
+impl Capture {
+    /// Records the response head and copies the body as it is sent to the client.
+    #[must_use]
+    pub fn response(self, res: Response) -> Response {
+        let duration_ms = u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        let (parts, body) = res.into_parts();
+        {
+            let mut state = self.inspector.lock();
+            if let Some(exchange) = state.exchange(&self.service, self.id) {
+                exchange.response = Some(ResponseHead {
+                    status: parts.status.as_u16(),
+                    headers: header_pairs(&parts.headers),
+                    duration_ms,
+                });
+            }
+        }
+        let body = self
+            .inspector
+            .tee(body, &self.service, self.id, Direction::Response);
+        Response::from_parts(parts, body)
+    }
+
+    /// Records why the request got no response.
+    pub fn error(self, error: impl Into<String>) {
+        let mut state = self.inspector.lock();
+        if let Some(exchange) = state.exchange(&self.service, self.id) {
+            exchange.error = Some(error.into());
+        }
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::StatusCode;
+
+    #[tokio::test]
+    async fn test_capture_records_request_and_response() {
+        let inspector = RequestInspector::default();
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/orders")
+            .header("host", "shop.localhost")
+            .body(Body::from("{\"qty\":2}"))
+            .unwrap();
+
+        let (req, capture) = inspector.capture("shop:api", req, "/orders");
+        assert!(capture.is_none(), "capture is off by default");
+        drop(req);
+
+        inspector.set_enabled("shop:api", true);
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/orders")
+            .header("host", "shop.localhost")
+            .body(Body::from("{\"qty\":2}"))
+            .unwrap();
+        let (req, capture) = inspector.capture("shop:api", req, "/orders");
+        axum::body::to_bytes(req.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let Some(capture) = capture else {
+            unreachable!("capture is on");
+        };
+        let res = capture.response((StatusCode::CREATED, "created").into_response());
+        axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let captured = inspector.list("shop:api");
+        assert!(captured.enabled);
+        assert_eq!(captured.requests.len(), 1);
+        let request = &captured.requests[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.host, "shop.localhost");
+        assert_eq!(request.path, "/orders");
+        assert_eq!(request.body.text().as_deref(), Some("{\"qty\":2}"));
+        let Some(response) = &request.response else {
+            unreachable!("response was recorded");
+        };
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body.text().as_deref(), Some("created"));
+    }
+}
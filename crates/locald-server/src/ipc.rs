@@ -1,5 +1,6 @@
 use crate::ShutdownReason;
 use crate::container::ContainerManager;
+use crate::log_store::LogFilter;
 use crate::manager::ProcessManager;
 use anyhow::Result;
 use locald_core::config::LocaldConfig;
@@ -10,6 +11,9 @@ use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::{broadcast, mpsc::Sender};
 use tracing::{error, info};
 
+/// How many historical entries `locald logs` shows when no time window is given.
+const LOG_HISTORY: usize = 2000;
+
 pub async fn run_ipc_server(
     manager: ProcessManager,
     container_manager: Arc<ContainerManager>,
@@ -56,6 +60,29 @@ pub async fn run_ipc_server(
     }
 }
 
+/// Reads one request, however many reads it takes to arrive. Clients don't close
+/// their end after writing (they wait for the response on the same stream), so the
+/// request is complete once it parses. Returns `None` if the client sent nothing.
+async fn read_request(stream: &mut UnixStream) -> Result<Option<IpcRequest>> {
+    let mut bytes = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            if bytes.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(serde_json::from_slice(&bytes)?));
+        }
+        bytes.extend_from_slice(&buf[..n]);
+        match serde_json::from_slice(&bytes) {
+            Ok(request) => return Ok(Some(request)),
+            Err(e) if e.is_eof() => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 async fn handle_connection(
     mut stream: UnixStream,
     manager: ProcessManager,
@@ -63,14 +90,9 @@ async fn handle_connection(
     shutdown_tx: Sender<ShutdownReason>,
     version: String,
 ) -> Result<()> {
-    let mut buf = [0; 4096];
-    let n = stream.read(&mut buf).await?;
-
-    if n == 0 {
+    let Some(request) = read_request(&mut stream).await? else {
         return Ok(());
-    }
-
-    let request: IpcRequest = serde_json::from_slice(&buf[..n])?;
+    };
     tracing::debug!("Received request: {:?}", request);
 
     if let IpcRequest::RunContainer {
@@ -107,6 +129,7 @@ async fn handle_connection(
                 service: "container".to_string(),
                 stream: stream_type,
                 message: line,
+                structured: None,
             });
             let mut bytes = serde_json::to_vec(&event)?;
             bytes.push(b'\n');
@@ -130,17 +153,42 @@ async fn handle_connection(
         return Ok(());
     }
 
-    if let IpcRequest::Logs { service, mode } = request {
+    if let IpcRequest::Logs {
+        service,
+        mode,
+        since,
+        until,
+        tail,
+        grep,
+        level,
+        fields,
+    } = request
+    {
+        let grep = match grep.as_deref().map(regex::Regex::new).transpose() {
+            Ok(grep) => grep,
+            Err(e) => {
+                let response = IpcResponse::Error(format!("Invalid --grep pattern: {e}"));
+                let mut bytes = serde_json::to_vec(&response)?;
+                bytes.push(b'\n');
+                stream.write_all(&bytes).await?;
+                return Ok(());
+            }
+        };
+        let filter = LogFilter {
+            service,
+            since,
+            until,
+            grep,
+            level,
+            fields,
+        };
+
         let mut rx = manager.log_sender.subscribe();
-        let recent = manager.get_recent_logs();
+        // Without a window, show about as much as the in-memory buffer used to hold
+        let tail = tail.or_else(|| (since.is_none() && until.is_none()).then_some(LOG_HISTORY));
+        let history = manager.query_logs(&filter, tail).await;
 
-        for entry in recent {
-            if let Some(ref s) = service
-                && &entry.service != s
-                && entry.service != format!("{}:build", s)
-            {
-                continue;
-            }
+        for entry in history {
             let mut bytes = serde_json::to_vec(&entry)?;
             bytes.push(b'\n');
             stream.write_all(&bytes).await?;
@@ -153,10 +201,7 @@ async fn handle_connection(
         loop {
             match rx.recv().await {
                 Ok(entry) => {
-                    if let Some(ref s) = service
-                        && &entry.service != s
-                        && entry.service != format!("{}:build", s)
-                    {
+                    if !filter.matches(&entry) {
                         continue;
                     }
                     let mut bytes = serde_json::to_vec(&entry)?;
@@ -175,10 +220,14 @@ async fn handle_connection(
     if let IpcRequest::Start {
         project_path,
         verbose,
+        profile,
+        env,
     } = request
     {
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
         let manager = manager.clone();
+        manager.select_profile(&project_path, profile);
+        manager.set_client_env(&project_path, env.map(|env| env.into_iter().collect()));
 
         let handle =
             tokio::spawn(async move { manager.start(project_path, Some(tx), verbose).await });
@@ -275,6 +324,52 @@ async fn handle_connection(
             Ok(env) => IpcResponse::ServiceEnv(env),
             Err(e) => IpcResponse::Error(e.to_string()),
         },
+        IpcRequest::DbSnapshot { service, name } => match manager.snapshot(&service, name).await {
+            Ok(info) => IpcResponse::Snapshot(info),
+            Err(e) => IpcResponse::Error(format!("{e:#}")),
+        },
+        IpcRequest::DbRestore { service, name } => {
+            match manager.restore_snapshot(&service, &name).await {
+                Ok(()) => IpcResponse::Ok,
+                Err(e) => IpcResponse::Error(format!("{e:#}")),
+            }
+        }
+        IpcRequest::DbSnapshots { service } => {
+            match manager.list_snapshots(service.as_deref()).await {
+                Ok(snapshots) => IpcResponse::Snapshots(snapshots),
+                Err(e) => IpcResponse::Error(e.to_string()),
+            }
+        }
+        IpcRequest::Requests { service } => {
+            IpcResponse::Requests(manager.request_inspector().list(&service))
+        }
+        IpcRequest::CaptureRequests { service, enabled } => {
+            manager.request_inspector().set_enabled(&service, enabled);
+            IpcResponse::Ok
+        }
+        IpcRequest::ClearRequests { service } => {
+            manager.request_inspector().clear(&service);
+            IpcResponse::Ok
+        }
+        IpcRequest::ReplayRequest { service, id } => {
+            match manager
+                .request_inspector()
+                .replay(&manager, &service, id)
+                .await
+            {
+                Ok(request) => IpcResponse::Request(Box::new(request)),
+                Err(e) => IpcResponse::Error(e.to_string()),
+            }
+        }
+        IpcRequest::Faults => IpcResponse::Faults(manager.list_faults().await),
+        IpcRequest::SetFaults {
+            service,
+            enabled,
+            rules,
+        } => match manager.set_faults(&service, enabled, rules).await {
+            Ok(()) => IpcResponse::Ok,
+            Err(e) => IpcResponse::Error(e.to_string()),
+        },
         IpcRequest::Logs { .. } => unreachable!(),
         IpcRequest::RunContainer { .. } => unreachable!(),
     };
@@ -284,3 +379,42 @@ async fn handle_connection(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn reads_requests_larger_than_one_read() {
+        let env: BTreeMap<String, String> = (0..200)
+            .map(|i| (format!("VAR_{i}"), "x".repeat(64)))
+            .collect();
+        let request = IpcRequest::Start {
+            project_path: PathBuf::from("/tmp/project"),
+            verbose: false,
+            profile: None,
+            env: Some(env.clone()),
+        };
+        let bytes = serde_json::to_vec(&request).expect("serialize request");
+        assert!(bytes.len() > 4096);
+
+        let (mut client, mut server) = UnixStream::pair().expect("socket pair");
+        // The client keeps its end open, waiting for a response
+        let writer = tokio::spawn(async move {
+            client.write_all(&bytes).await.expect("write request");
+            client
+        });
+
+        let received = read_request(&mut server)
+            .await
+            .expect("read request")
+            .expect("a request");
+        let _client = writer.await.expect("writer");
+        assert!(
+            matches!(&received, IpcRequest::Start { env: Some(got), .. } if *got == env),
+            "{received:?}"
+        );
+    }
+}
@@ -70,20 +70,36 @@
 #![allow(clippy::unnecessary_debug_formatting)]
 #![allow(clippy::uninlined_format_args)]
 
+#[doc(hidden)]
+pub mod acme;
 #[doc(hidden)]
 pub mod api;
 #[doc(hidden)]
 pub mod assets;
 #[doc(hidden)]
+pub mod compose;
+#[doc(hidden)]
 // pub mod cert; // Moved to locald-utils
 pub mod config_loader;
 #[doc(hidden)]
 pub mod container;
 #[doc(hidden)]
+pub mod dns;
+#[doc(hidden)]
+pub mod faults;
+#[doc(hidden)]
+pub mod forward;
+#[doc(hidden)]
+pub mod headers;
+#[doc(hidden)]
 pub mod health;
 #[doc(hidden)]
+pub mod inspect;
+#[doc(hidden)]
 pub mod ipc;
 #[doc(hidden)]
+pub mod log_store;
+#[doc(hidden)]
 pub mod logging;
 #[doc(hidden)]
 pub mod manager;
@@ -100,12 +116,21 @@ pub mod service;
 #[doc(hidden)]
 pub mod shim_client;
 #[doc(hidden)]
+pub mod snapshots;
+#[doc(hidden)]
 pub mod state;
 #[doc(hidden)]
 pub mod static_server;
 #[doc(hidden)]
+pub mod task;
+#[doc(hidden)]
+pub mod tls;
 #[doc(hidden)]
 pub mod toolbar;
+#[doc(hidden)]
+pub mod validate;
+#[doc(hidden)]
+pub mod watch;
 
 #[cfg(test)]
 mod proxy_test;
@@ -227,15 +252,33 @@ async fn async_main(
             .unwrap_or_default(),
     ));
 
-    let manager = ProcessManager::new(
+    let mut manager = ProcessManager::new(
         notify_path.clone(),
         docker,
         state_manager,
         registry,
         Some(log_tx),
     )?;
+    manager.set_startup_concurrency(config.server.startup_concurrency);
+    manager.set_log_store(crate::log_store::LogStore::spawn(
+        crate::log_store::LogStore::default_root(),
+        config.logs,
+    ));
     manager.spawn_metrics_collector();
 
+    // Initialize CertManager
+    let cert_manager = match locald_utils::cert::CertManager::new().await {
+        Ok(cm) => Some(std::sync::Arc::new(cm)),
+        Err(e) => {
+            warn!("Failed to initialize CertManager: {e}. HTTPS will be disabled.");
+            None
+        }
+    };
+    if let Some(cm) = &cert_manager {
+        manager.set_cert_manager(cm.clone());
+        manager.spawn_tls_renewal();
+    }
+
     // Initialize ContainerManager
     let data_dir = directories::ProjectDirs::from("com", "locald", "locald")
         .map(|dirs| dirs.data_local_dir().to_path_buf())
@@ -306,22 +349,21 @@ async fn async_main(
         watch_for_upgrade(container_manager_clone, shutdown_tx_clone).await;
     });
 
-    // Initialize CertManager
-    let cert_manager = match locald_utils::cert::CertManager::new().await {
-        Ok(cm) => Some(std::sync::Arc::new(cm)),
-        Err(e) => {
-            warn!("Failed to initialize CertManager: {e}. HTTPS will be disabled.");
-            None
-        }
-    };
-
     // Run Proxy server
-    let api_router = crate::api::router(manager.clone());
-    let proxy = std::sync::Arc::new(ProxyManager::new(
+    let acme = crate::acme::Acme::load(
+        &config.acme.domains,
+        manager.cert_manager().cloned(),
+        crate::acme::accounts_path(),
+    )
+    .await;
+    let api_router = crate::api::router(manager.clone(), acme);
+    let mut proxy = ProxyManager::new(
         std::sync::Arc::new(manager.clone()),
         api_router,
         cert_manager,
-    ));
+    );
+    proxy.set_request_inspector(manager.request_inspector().clone());
+    let proxy = std::sync::Arc::new(proxy);
 
     // Bind HTTP
     let listener_http = if let Ok(port_str) = std::env::var("LOCALD_HTTP_PORT") {
@@ -422,6 +464,26 @@ async fn async_main(
         });
     }
 
+    let proxy_clone = proxy.clone();
+    tokio::spawn(async move { proxy_clone.watch_services().await });
+
+    if config.dns.enabled {
+        match crate::dns::DnsServer::bind(config.dns.port).await {
+            Ok(socket) => {
+                let upstream = match config.dns.upstream {
+                    Some(upstream) => upstream,
+                    None => crate::dns::system_upstream(socket.local_addr()?).await,
+                };
+                let dns = std::sync::Arc::new(crate::dns::DnsServer::new(
+                    std::sync::Arc::new(manager.clone()),
+                    upstream,
+                ));
+                tokio::spawn(dns.serve(socket));
+            }
+            Err(e) => error!("{e:#}. DNS server disabled."),
+        }
+    }
+
     let reason = tokio::select! {
         _ = tokio::signal::ctrl_c() => {
             info!("Received Ctrl+C, shutting down");
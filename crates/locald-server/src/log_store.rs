@@ -0,0 +1,519 @@
+//! Persistent, rotating storage for service logs.
+//!
+//! Each service gets a directory under the store root holding JSON-lines segments
+//! named after the time they were opened (`<unix millis>.jsonl`). A background task
+//! appends entries to the newest segment, starting a new one once it reaches an
+//! eighth of the size limit, and deletes the oldest segments once the service's logs
+//! exceed [`LogsConfig::max_size_mb`] or get older than [`LogsConfig::max_age_days`].
+//!
+//! The store survives daemon restarts and upgrades, so `locald logs --since 10m`
+//! can look further back than the in-memory buffer.
+
+use anyhow::{Context, Result};
+use locald_core::config::LogsConfig;
+use locald_core::ipc::LogEntry;
+use locald_core::log_format::LogLevel;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// Don't rotate segments smaller than this, however low the size limit.
+const MIN_SEGMENT_BYTES: u64 = 64 * 1024;
+
+/// Entries written between two flushes.
+const MAX_BATCH: usize = 1000;
+
+/// Timestamps above this are in milliseconds; in seconds it's the year 5138.
+const MAX_TIMESTAMP_SECS: i64 = 100_000_000_000;
+
+/// `timestamp` in Unix epoch seconds, which is what [`LogEntry::timestamp`] holds.
+/// Some sources log in milliseconds, and entries stored before that was caught still
+/// carry them.
+#[must_use]
+pub const fn timestamp_secs(timestamp: i64) -> i64 {
+    if timestamp > MAX_TIMESTAMP_SECS {
+        timestamp / 1000
+    } else {
+        timestamp
+    }
+}
+
+/// Which log entries a query or a live stream is interested in.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// A service name; its build logs (`<service>:build`) are included.
+    pub service: Option<String>,
+    /// Only entries at or after this time (Unix epoch seconds).
+    pub since: Option<i64>,
+    /// Only entries at or before this time (Unix epoch seconds).
+    pub until: Option<i64>,
+    /// Only entries whose message matches.
+    pub grep: Option<Regex>,
+    /// Only structured entries at or above this level.
+    pub level: Option<LogLevel>,
+    /// Only structured entries whose fields have these values.
+    pub fields: BTreeMap<String, String>,
+}
+
+impl LogFilter {
+    fn matches_service(&self, service: &str) -> bool {
+        self.service
+            .as_ref()
+            .is_none_or(|s| service == s || service == format!("{s}:build"))
+    }
+
+    #[must_use]
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.matches_service(&entry.service)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+            && self
+                .grep
+                .as_ref()
+                .is_none_or(|re| re.is_match(&entry.message))
+            && self.matches_structured(entry)
+    }
+
+    fn matches_structured(&self, entry: &LogEntry) -> bool {
+        if self.level.is_none() && self.fields.is_empty() {
+            return true;
+        }
+        let Some(structured) = &entry.structured else {
+            return false;
+        };
+        self.level
+            .is_none_or(|level| structured.level.is_some_and(|l| l >= level))
+            && self
+                .fields
+                .iter()
+                .all(|(key, value)| structured.field(key).as_deref() == Some(value.as_str()))
+    }
+}
+
+/// Handle to the on-disk log store.
+#[derive(Debug)]
+pub struct LogStore {
+    root: PathBuf,
+    tx: mpsc::UnboundedSender<LogEntry>,
+}
+
+impl LogStore {
+    /// The default location of the store.
+    #[must_use]
+    pub fn default_root() -> PathBuf {
+        directories::ProjectDirs::from("com", "locald", "locald").map_or_else(
+            || PathBuf::from(".locald/logs"),
+            |d| d.data_dir().join("logs"),
+        )
+    }
+
+    /// Opens the store at `root` and starts its writer task.
+    ///
+    /// Must be called from within a Tokio runtime.
+    #[must_use]
+    pub fn spawn(root: PathBuf, config: LogsConfig) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let writer = Writer {
+            root: root.clone(),
+            max_bytes: config.max_size_bytes(),
+            max_age: config.max_age(),
+            segment_bytes: (config.max_size_bytes() / 8).max(MIN_SEGMENT_BYTES),
+            open: HashMap::new(),
+        };
+        tokio::spawn(writer.run(rx));
+        Self { root, tx }
+    }
+
+    /// Queues an entry to be written. Never blocks.
+    pub fn append(&self, mut entry: LogEntry) {
+        entry.timestamp = timestamp_secs(entry.timestamp);
+        // The writer only stops when the runtime shuts down
+        self.tx.send(entry).ok();
+    }
+
+    /// Reads the stored entries matching `filter`, oldest first.
+    ///
+    /// With `tail`, only the last `tail` matching entries are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read.
+    pub async fn query(&self, filter: &LogFilter, tail: Option<usize>) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+        for service_dir in service_dirs(&self.root).await? {
+            let Some(service) = service_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !filter.matches_service(service) {
+                continue;
+            }
+
+            for segment in segments(&service_dir).await? {
+                // A segment last written before `since` can't contain anything newer
+                if let Some(since) = filter.since
+                    && let Ok(modified) = tokio::fs::metadata(&segment.path)
+                        .await
+                        .and_then(|m| m.modified())
+                    && unix_secs(modified) < since
+                {
+                    continue;
+                }
+
+                let content = match tokio::fs::read_to_string(&segment.path).await {
+                    Ok(content) => content,
+                    // Pruned since we listed it
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                // The last line may be half-written; skip anything that doesn't parse
+                entries.extend(
+                    content
+                        .lines()
+                        .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+                        .map(|mut entry| {
+                            entry.timestamp = timestamp_secs(entry.timestamp);
+                            entry
+                        })
+                        .filter(|entry| filter.matches(entry)),
+                );
+            }
+        }
+
+        // Stable, so entries within the same second keep their order
+        entries.sort_by_key(|e| e.timestamp);
+        if let Some(tail) = tail {
+            let skip = entries.len().saturating_sub(tail);
+            entries.drain(..skip);
+        }
+        Ok(entries)
+    }
+}
+
+struct Segment {
+    path: PathBuf,
+    opened_at: i64,
+}
+
+struct OpenSegment {
+    file: tokio::fs::File,
+    size: u64,
+}
+
+struct Writer {
+    root: PathBuf,
+    max_bytes: u64,
+    max_age: Duration,
+    segment_bytes: u64,
+    open: HashMap<String, OpenSegment>,
+}
+
+impl Writer {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<LogEntry>) {
+        // Age limits also apply to services that never log again
+        match service_dirs(&self.root).await {
+            Ok(dirs) => {
+                for dir in dirs {
+                    self.prune(&dir, None).await;
+                }
+            }
+            Err(e) => warn!("Failed to read log store {:?}: {}", self.root, e),
+        }
+
+        while let Some(entry) = rx.recv().await {
+            let mut batch = vec![entry];
+            while batch.len() < MAX_BATCH {
+                match rx.try_recv() {
+                    Ok(entry) => batch.push(entry),
+                    Err(_) => break,
+                }
+            }
+
+            for entry in &batch {
+                if let Err(e) = self.write(entry).await {
+                    warn!("Failed to store log entry for {}: {:#}", entry.service, e);
+                }
+            }
+            for segment in self.open.values_mut() {
+                if let Err(e) = segment.file.flush().await {
+                    warn!("Failed to flush log segment: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn write(&mut self, entry: &LogEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let rotate = self
+            .open
+            .get(&entry.service)
+            .is_none_or(|segment| segment.size >= self.segment_bytes);
+        if rotate {
+            let dir = self.root.join(&entry.service);
+            tokio::fs::create_dir_all(&dir)
+                .await
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+            let opened_at = chrono::Utc::now().timestamp_millis();
+            let path = dir.join(format!("{opened_at}.{SEGMENT_EXTENSION}"));
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+
+            if let Some(mut old) = self
+                .open
+                .insert(entry.service.clone(), OpenSegment { file, size: 0 })
+            {
+                old.file.flush().await.ok();
+            }
+            self.prune(&dir, Some(&path)).await;
+        }
+
+        let segment = self
+            .open
+            .get_mut(&entry.service)
+            .context("Log segment missing after rotation")?;
+        segment.file.write_all(&line).await?;
+        segment.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Deletes expired segments, then the oldest ones until the size limit is met.
+    async fn prune(&self, dir: &Path, active: Option<&Path>) {
+        let segments = match segments(dir).await {
+            Ok(segments) => segments,
+            Err(e) => {
+                warn!("Failed to list log segments in {:?}: {}", dir, e);
+                return;
+            }
+        };
+
+        let now = SystemTime::now();
+        let mut kept = Vec::new();
+        for segment in segments {
+            if active == Some(segment.path.as_path()) {
+                continue;
+            }
+            let Ok(metadata) = tokio::fs::metadata(&segment.path).await else {
+                continue;
+            };
+            let expired = metadata
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .is_some_and(|age| age > self.max_age);
+            if expired {
+                remove_segment(&segment.path).await;
+            } else {
+                kept.push((segment.path, metadata.len()));
+            }
+        }
+
+        // Oldest first, so the newest logs survive
+        let mut total: u64 = kept.iter().map(|(_, len)| len).sum();
+        for (path, len) in kept {
+            if total <= self.max_bytes {
+                break;
+            }
+            remove_segment(&path).await;
+            total -= len;
+        }
+    }
+}
+
+async fn remove_segment(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        warn!("Failed to remove log segment {:?}: {}", path, e);
+    }
+}
+
+async fn service_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    let mut entries = match tokio::fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(dirs),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+/// Lists a service's segments, oldest first.
+async fn segments(dir: &Path) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(segments),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(opened_at) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse().ok())
+        {
+            segments.push(Segment { path, opened_at });
+        }
+    }
+    segments.sort_by_key(|s| s.opened_at);
+    Ok(segments)
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use locald_core::ipc::LogStream;
+    use locald_core::log_format::StructuredLog;
+
+    fn entry(service: &str, timestamp: i64, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp,
+            service: service.to_string(),
+            stream: LogStream::Stdout,
+            message: message.to_string(),
+            structured: None,
+        }
+    }
+
+    /// Waits for the writer task to store at least `count` entries.
+    async fn wait_for(store: &LogStore, count: usize) {
+        for _ in 0..100 {
+            let entries = store.query(&LogFilter::default(), None).await.unwrap();
+            if entries.len() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_and_survives_reopen() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_path_buf();
+
+        let now = chrono::Utc::now().timestamp();
+
+        let store = LogStore::spawn(root.clone(), LogsConfig::default());
+        store.append(entry("app:web", now - 30, "GET /"));
+        store.append(entry("app:web:build", now - 40, "compiling"));
+        store.append(entry("app:worker", now - 20, "job done"));
+        store.append(entry("app:web", now - 10, "GET /health"));
+        wait_for(&store, 4).await;
+
+        // A new store (e.g. after a daemon restart) sees the same history
+        let store = LogStore::spawn(root, LogsConfig::default());
+        let web = LogFilter {
+            service: Some("app:web".to_string()),
+            ..LogFilter::default()
+        };
+        let messages = |entries: Vec<LogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.message).collect()
+        };
+
+        assert_eq!(
+            messages(store.query(&web, None).await.unwrap()),
+            ["compiling", "GET /", "GET /health"]
+        );
+        assert_eq!(
+            messages(store.query(&web, Some(1)).await.unwrap()),
+            ["GET /health"]
+        );
+
+        let window = LogFilter {
+            since: Some(now - 30),
+            until: Some(now - 20),
+            ..LogFilter::default()
+        };
+        assert_eq!(
+            messages(store.query(&window, None).await.unwrap()),
+            ["GET /", "job done"]
+        );
+
+        let grep = LogFilter {
+            grep: Some(Regex::new(r"^GET /\w*$").unwrap()),
+            ..LogFilter::default()
+        };
+        assert_eq!(store.query(&grep, None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_mixes_second_and_millisecond_timestamps() {
+        let tmp = tempfile::tempdir().unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        // Site builds used to log in milliseconds; process output is in seconds
+        let store = LogStore::spawn(tmp.path().to_path_buf(), LogsConfig::default());
+        store.append(entry("app:web", now - 30, "GET /"));
+        store.append(entry("app:web:build", (now - 20) * 1000, "compiling"));
+        store.append(entry("app:web", now - 10, "GET /health"));
+        store.append(entry("app:web:build", (now - 5) * 1000 + 999, "done"));
+        wait_for(&store, 4).await;
+
+        let all = store.query(&LogFilter::default(), None).await.unwrap();
+        let messages: Vec<&str> = all.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["GET /", "compiling", "GET /health", "done"]);
+        assert_eq!(all[3].timestamp, now - 5);
+
+        let window = LogFilter {
+            since: Some(now - 25),
+            until: Some(now - 10),
+            ..LogFilter::default()
+        };
+        let messages: Vec<String> = store
+            .query(&window, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(messages, ["compiling", "GET /health"]);
+    }
+
+    #[test]
+    fn test_filter_structured_fields() {
+        let mut warn = entry(
+            "app:api",
+            0,
+            r#"{"level":"warn","msg":"slow","user_id":42}"#,
+        );
+        warn.structured = StructuredLog::parse(&warn.message);
+        let plain = entry("app:api", 0, "user_id=42 is slow");
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Warn),
+            fields: BTreeMap::from([("user_id".to_string(), "42".to_string())]),
+            ..LogFilter::default()
+        };
+        assert!(filter.matches(&warn));
+        assert!(!filter.matches(&plain));
+
+        let errors = LogFilter {
+            level: Some(LogLevel::Error),
+            ..LogFilter::default()
+        };
+        assert!(!errors.matches(&warn));
+    }
+}
@@ -29,6 +29,7 @@ where
             message: visitor.message,
             stream: LogStream::Stdout, // Treat all daemon logs as stdout for now
             timestamp,
+            structured: None,
         };
 
         // Ignore errors (no receivers)
@@ -2,9 +2,9 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
-    Router,
+    Extension, Router,
     body::Body,
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     handler::Handler,
     http::Uri,
     response::{IntoResponse, Response},
@@ -18,9 +18,17 @@ use tower_http::trace::TraceLayer;
 use tracing::{error, info};
 
 use crate::assets;
+use crate::faults::{self, Fault};
+use crate::forward::Forwards;
+use crate::headers;
+use crate::inspect::{Capture, RequestInspector};
+use locald_core::config::{FaultConfig, Protocol};
 use locald_core::resolver::ServiceResolver;
 use locald_utils::cert::CertManager;
 
+/// How often exposed ports and wildcard domains are re-checked against the loaded services.
+const SERVICE_RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Manages the reverse proxy for routing requests to services.
 ///
 /// The `ProxyManager` handles:
@@ -28,11 +36,17 @@ use locald_utils::cert::CertManager;
 /// - Routing based on Host header.
 /// - WebSocket upgrades.
 /// - Serving the dashboard and docs.
+/// - Stable TCP and UDP ports forwarded to services (`expose`).
+/// - Injected latency, errors, resets and bandwidth limits (`faults`).
+/// - Header rules and CORS policies (`headers`, `cors`).
+/// - HTTP/2 and gRPC backends (`protocol`), and HTTP/2 for clients.
 #[derive(Debug)]
 pub struct ProxyManager {
     resolver: Arc<dyn ServiceResolver>,
     api_router: Router,
     cert_manager: Option<Arc<CertManager>>,
+    forwards: tokio::sync::Mutex<Forwards>,
+    inspector: RequestInspector,
 }
 
 impl ProxyManager {
@@ -52,21 +66,43 @@ impl ProxyManager {
             resolver,
             api_router,
             cert_manager,
+            forwards: tokio::sync::Mutex::default(),
+            inspector: RequestInspector::default(),
         }
     }
 
-    pub(crate) fn make_app(&self) -> Router {
-        let mut connector = hyper_util::client::legacy::connect::HttpConnector::new();
-        connector.set_nodelay(true);
-        connector.set_keepalive(Some(std::time::Duration::from_secs(60)));
+    /// Record requests into `inspector` for services that have capture turned on.
+    pub fn set_request_inspector(&mut self, inspector: RequestInspector) {
+        self.inspector = inspector;
+    }
+
+    /// Bind or release the TCP/UDP ports exposed by services to match their config.
+    pub async fn reconcile_forwarding(&self) {
+        self.forwards.lock().await.reconcile(&self.resolver).await;
+    }
 
-        let client =
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build(connector);
+    /// Issue wildcard certificates for the wildcard domains services answer on.
+    pub async fn sync_wildcard_certs(&self) {
+        if let Some(cert_manager) = &self.cert_manager {
+            cert_manager.set_wildcard_domains(self.resolver.wildcard_domains().await);
+        }
+    }
 
+    /// Keep exposed TCP/UDP ports and wildcard certificates in sync with the loaded
+    /// services.
+    pub async fn watch_services(&self) {
+        loop {
+            self.reconcile_forwarding().await;
+            self.sync_wildcard_certs().await;
+            tokio::time::sleep(SERVICE_RECONCILE_INTERVAL).await;
+        }
+    }
+
+    pub(crate) fn make_app(&self) -> Router {
         let state = AppState {
             resolver: self.resolver.clone(),
-            client,
+            backends: Backends::new(),
+            inspector: self.inspector.clone(),
         };
 
         Router::new()
@@ -106,7 +142,11 @@ impl ProxyManager {
 
     pub async fn serve_http(&self, listener: TcpListener) -> anyhow::Result<()> {
         let app = self.make_app();
-        axum::serve(listener, app).await?;
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
         Ok(())
     }
 
@@ -145,27 +185,123 @@ impl ProxyManager {
             self.resolver.set_https_port(Some(addr.port())).await;
         }
 
-        let config = rustls::ServerConfig::builder()
+        let mut config = rustls::ServerConfig::builder()
             .with_no_client_auth()
             .with_cert_resolver(cert_manager.clone());
+        // Offer HTTP/2 so gRPC clients can connect over TLS
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
         let rustls_config = RustlsConfig::from_config(Arc::new(config));
-        let app = self.make_app();
+        let app = self.make_app().layer(Extension(headers::Https));
 
         axum_server::from_tcp_rustls(listener, rustls_config)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
         Ok(())
     }
 }
 
+type Client =
+    hyper_util::client::legacy::Client<hyper_util::client::legacy::connect::HttpConnector, Body>;
+
+/// The clients requests reach services through.
+#[derive(Debug, Clone)]
+pub(crate) struct Backends {
+    http1: Client,
+    /// For services with `protocol = "h2c"` or `"grpc"`, which are spoken to over HTTP/2
+    /// with prior knowledge.
+    h2: Client,
+}
+
+/// What became of a request handed to [`Backends::dispatch`].
+pub(crate) enum Dispatch {
+    /// The fault rules answered in the service's place. The answer is already recorded
+    /// in the capture.
+    Injected(Response),
+    /// The request is on its way; the response is limited to `bandwidth` bytes per
+    /// second, if set.
+    Sent {
+        response: hyper_util::client::legacy::ResponseFuture,
+        capture: Option<Capture>,
+        bandwidth: Option<u64>,
+    },
+}
+
+impl Backends {
+    pub(crate) fn new() -> Self {
+        let mut connector = hyper_util::client::legacy::connect::HttpConnector::new();
+        connector.set_nodelay(true);
+        connector.set_keepalive(Some(std::time::Duration::from_secs(60)));
+
+        Self {
+            http1:
+                hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                    .build(connector.clone()),
+            h2: hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .http2_only(true)
+                .build(connector),
+        }
+    }
+
+    /// Runs the service's fault rules for `req`, then sends it to the service over
+    /// `protocol` unless they answered instead. `authority` is where the client sent it.
+    pub(crate) async fn dispatch(
+        &self,
+        faults: Option<&FaultConfig>,
+        protocol: Protocol,
+        authority: &str,
+        mut req: Request,
+        capture: Option<Capture>,
+    ) -> Dispatch {
+        if !protocol.is_http2() {
+            // An HTTP/2 client's request goes to the service as HTTP/1.1, with the
+            // `:authority` it was sent to as the Host header
+            *req.version_mut() = hyper::Version::HTTP_11;
+            if !req.headers().contains_key(hyper::header::HOST) {
+                if let Ok(value) = hyper::header::HeaderValue::from_str(authority) {
+                    req.headers_mut().insert(hyper::header::HOST, value);
+                }
+            }
+        }
+
+        let bandwidth = match faults {
+            Some(rules) => match faults::inject(rules).await {
+                Fault::Forward { bandwidth } => bandwidth,
+                Fault::Error(status) => {
+                    let res = faults::error_response(status);
+                    return Dispatch::Injected(match capture {
+                        Some(capture) => capture.response(res),
+                        None => res,
+                    });
+                }
+                Fault::Reset => {
+                    if let Some(capture) = capture {
+                        capture.error("Connection reset by fault rules");
+                    }
+                    return Dispatch::Injected(faults::reset_response());
+                }
+            },
+            None => None,
+        };
+
+        let client = if protocol.is_http2() {
+            &self.h2
+        } else {
+            &self.http1
+        };
+        Dispatch::Sent {
+            response: client.request(req),
+            capture,
+            bandwidth,
+        }
+    }
+}
+
 #[derive(Clone)]
 struct AppState {
     resolver: Arc<dyn ServiceResolver>,
-    client: hyper_util::client::legacy::Client<
-        hyper_util::client::legacy::connect::HttpConnector,
-        Body,
-    >,
+    backends: Backends,
+    inspector: RequestInspector,
 }
 
 async fn handle_websocket_upgrade(state: AppState, mut req: Request, backend_uri: Uri) -> Response {
@@ -191,7 +327,7 @@ async fn handle_websocket_upgrade(state: AppState, mut req: Request, backend_uri
 
     let client_upgrade_fut = hyper::upgrade::on(&mut req);
 
-    match state.client.request(backend_req).await {
+    match state.backends.http1.request(backend_req).await {
         Ok(mut backend_response) => {
             if backend_response.status() == StatusCode::SWITCHING_PROTOCOLS {
                 let backend_upgrade_fut = hyper::upgrade::on(&mut backend_response);
@@ -228,26 +364,20 @@ async fn handle_websocket_upgrade(state: AppState, mut req: Request, backend_uri
 }
 
 async fn handle_proxy(State(state): State<AppState>, mut req: Request) -> Response {
-    let host = match req.headers().get("host") {
-        Some(h) => h
-            .to_str()
-            .unwrap_or_default()
-            .split(':')
-            .next()
-            .unwrap_or_default(),
-        None => return (StatusCode::BAD_REQUEST, "Missing Host header").into_response(),
+    let Some(authority) = headers::authority(req.uri(), req.headers()).map(str::to_string) else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
     };
+    let host = authority.split(':').next().unwrap_or_default();
 
     if host == "docs.localhost" || host == "docs.local" {
         return assets::handle_docs(req.uri()).into_response();
     }
 
     // Check if there is a running service for this domain first (e.g. locald-dashboard in dev mode)
-    if let Some((service_name, port)) = state.resolver.resolve_service_by_domain(host).await {
-        let uri_string = format!(
-            "http://localhost:{port}{}",
-            req.uri().path_and_query().map_or("", |x| x.as_str())
-        );
+    let path = req.uri().path_and_query().map_or("/", |x| x.as_str());
+    if let Some(route) = state.resolver.resolve_route(host, path).await {
+        let service_name = route.service;
+        let uri_string = format!("http://localhost:{}{}", route.port, route.path);
         let uri: Uri = match uri_string.parse() {
             Ok(u) => u,
             Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid URI").into_response(),
@@ -264,6 +394,40 @@ async fn handle_proxy(State(state): State<AppState>, mut req: Request) -> Respon
             return handle_websocket_upgrade(state, req, uri).await;
         }
 
+        let cors = state.resolver.service_cors(&service_name).await;
+        if let Some(res) = cors
+            .as_ref()
+            .and_then(|cors| headers::preflight(cors, req.method(), req.headers()))
+        {
+            return res;
+        }
+        let origin = req.headers().get(hyper::header::ORIGIN).cloned();
+
+        let header_rules = state.resolver.service_headers(&service_name).await;
+        let forwarded = headers::Forwarded {
+            client: req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|info| info.0.ip()),
+            host: host.to_string(),
+            https: req.extensions().get::<headers::Https>().is_some(),
+        };
+        let request_id = header_rules
+            .as_ref()
+            .and_then(|rules| headers::apply_request(rules, req.headers_mut(), &forwarded));
+        // Header rules and CORS apply to whatever the client gets back, injected errors
+        // included
+        let finish = |mut res: Response| {
+            if let Some(rules) = &header_rules {
+                headers::apply_response(rules, res.headers_mut(), request_id.clone());
+            }
+            if let Some(cors) = &cors {
+                headers::apply_cors(cors, origin.as_ref(), res.headers_mut());
+            }
+            res
+        };
+
+        let protocol = state.resolver.service_protocol(&service_name).await;
         let is_passthrough = req.headers().get("x-locald-passthrough").is_some();
         let accepts_html = req
             .headers()
@@ -272,31 +436,37 @@ async fn handle_proxy(State(state): State<AppState>, mut req: Request) -> Respon
             .map(|v| v.contains("text/html"))
             .unwrap_or(false);
 
+        let (mut req, capture) = state.inspector.capture(&service_name, req, &route.path);
         *req.uri_mut() = uri;
 
-        let backend_future = state.client.request(req);
+        let faults = state.resolver.service_faults(&service_name).await;
+        let dispatch = state
+            .backends
+            .dispatch(faults.as_ref(), protocol, &authority, req, capture)
+            .await;
+        let (backend_future, capture, bandwidth) = match dispatch {
+            Dispatch::Injected(res) => return finish(res),
+            Dispatch::Sent {
+                response,
+                capture,
+                bandwidth,
+            } => (response, capture, bandwidth),
+        };
+        let throttle = |res: Response| match bandwidth {
+            Some(bytes_per_sec) => faults::throttle(finish(res), bytes_per_sec),
+            None => finish(res),
+        };
 
-        if is_passthrough || !accepts_html {
-            match backend_future.await {
-                Ok(res) => return res.into_response(),
-                Err(e) => {
-                    error!("Proxy error: {e}");
-                    return error_response(StatusCode::BAD_GATEWAY, format!("Proxy error: {e}"));
-                }
-            }
+        if is_passthrough || !accepts_html || protocol == Protocol::Grpc {
+            return throttle(backend_response(backend_future.await, capture, protocol));
         }
 
         let response = tokio::select! {
-            res = backend_future => {
-                match res {
-                    Ok(res) => res.into_response(),
-                    Err(e) => {
-                        error!("Proxy error: {e}");
-                        error_response(StatusCode::BAD_GATEWAY, format!("Proxy error: {e}"))
-                    }
-                }
-            }
+            res = backend_future => throttle(backend_response(res, capture, protocol)),
             () = tokio::time::sleep(std::time::Duration::from_millis(500)) => {
+                if let Some(capture) = capture {
+                    capture.error("Service was slow to respond; showed the loading page");
+                }
                 loading_response(&service_name)
             }
         };
@@ -312,6 +482,54 @@ async fn handle_proxy(State(state): State<AppState>, mut req: Request) -> Respon
     (StatusCode::NOT_FOUND, format!("Domain {host} not found")).into_response()
 }
 
+/// Turns the backend's answer into the client's response, recording it if captured.
+fn backend_response(
+    result: Result<hyper::Response<hyper::body::Incoming>, hyper_util::client::legacy::Error>,
+    capture: Option<Capture>,
+    protocol: Protocol,
+) -> Response {
+    match result {
+        Ok(res) => {
+            let res = res.into_response();
+            match capture {
+                Some(capture) => capture.response(res),
+                None => res,
+            }
+        }
+        Err(e) => {
+            error!("Proxy error: {e}");
+            if let Some(capture) = capture {
+                capture.error(e.to_string());
+            }
+            if protocol == Protocol::Grpc {
+                return grpc_unavailable(&format!("Proxy error: {e}"));
+            }
+            error_response(StatusCode::BAD_GATEWAY, format!("Proxy error: {e}"))
+        }
+    }
+}
+
+/// Bytes escaped in a `grpc-message` header.
+const GRPC_MESSAGE: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS.add(b'%');
+
+/// A "trailers-only" gRPC response with status UNAVAILABLE, which gRPC clients treat
+/// as retryable, where an HTML error page would just fail to parse.
+fn grpc_unavailable(message: &str) -> Response {
+    let mut res = Response::new(Body::empty());
+    let headers = res.headers_mut();
+    headers.insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/grpc"),
+    );
+    headers.insert("grpc-status", hyper::header::HeaderValue::from_static("14"));
+    // grpc-message is percent-encoded, including `%` itself
+    let message = percent_encoding::utf8_percent_encode(message, GRPC_MESSAGE).to_string();
+    if let Ok(message) = hyper::header::HeaderValue::from_str(&message) {
+        headers.insert("grpc-message", message);
+    }
+    res
+}
+
 fn error_response(status: StatusCode, message: impl std::fmt::Display) -> Response {
     let html = format!(
         r#"<!DOCTYPE html>
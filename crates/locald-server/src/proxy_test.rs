@@ -162,3 +162,663 @@ async fn test_proxy_connection_success() {
         .unwrap();
     assert_eq!(&body_bytes[..], b"Hello World!");
 }
+
+/// Routes `/api/*` to the port it's given, the way a project's `[routes]` table would.
+#[derive(Debug)]
+struct RoutingResolver {
+    port: u16,
+}
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for RoutingResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        None
+    }
+    async fn resolve_route(
+        &self,
+        _domain: &str,
+        path: &str,
+    ) -> Option<locald_core::resolver::ResolvedRoute> {
+        let routes = std::collections::BTreeMap::from([(
+            "/api/*".to_string(),
+            locald_core::config::RouteTarget::Service("api".to_string()),
+        )]);
+        let matched = locald_core::config::routes::match_route(&routes, path)?;
+        Some(locald_core::resolver::ResolvedRoute {
+            service: matched.service.to_string(),
+            port: self.port,
+            path: matched.path,
+        })
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+#[tokio::test]
+async fn test_proxy_strips_route_prefix() {
+    // A backend that echoes the path it was asked for
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let backend = Router::new().fallback(|uri: axum::http::Uri| async move { uri.to_string() });
+    tokio::spawn(async move { axum::serve(listener, backend).await });
+
+    let proxy = ProxyManager::new(Arc::new(RoutingResolver { port }), Router::new(), None);
+    let app = proxy.make_app();
+
+    let req = Request::builder()
+        .uri("/api/users?page=2")
+        .header("Host", "shop.localhost")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body_bytes[..], b"/users?page=2");
+
+    let req = Request::builder()
+        .uri("/elsewhere")
+        .header("Host", "shop.localhost")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Exposes a stable TCP port for one service running on `port`.
+#[derive(Debug)]
+struct ExposingResolver {
+    exposed: u16,
+    port: u16,
+}
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for ExposingResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        None
+    }
+    async fn resolve_service_port(&self, service: &str) -> Option<u16> {
+        (service == "db").then_some(self.port)
+    }
+    async fn exposed_ports(&self) -> Vec<locald_core::resolver::ExposedPort> {
+        vec![locald_core::resolver::ExposedPort {
+            service: "db".to_string(),
+            protocol: locald_core::resolver::ExposeProtocol::Tcp,
+            port: self.exposed,
+        }]
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+#[tokio::test]
+async fn test_exposed_tcp_port_forwards_to_service() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // A backend that echoes what it's sent
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let (mut reader, mut writer) = socket.split();
+        tokio::io::copy(&mut reader, &mut writer).await.ok();
+    });
+
+    let exposed = {
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    let resolver = Arc::new(ExposingResolver { exposed, port });
+    let proxy = ProxyManager::new(resolver, Router::new(), None);
+    proxy.reconcile_forwarding().await;
+
+    let mut client = tokio::net::TcpStream::connect(("127.0.0.1", exposed))
+        .await
+        .unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut reply = [0; 4];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(&reply, b"ping");
+}
+
+/// Sends every request to `api` on `port`, with header rules and CORS for the
+/// frontend at `https://web.localhost`.
+#[derive(Debug)]
+struct PolicyResolver {
+    port: u16,
+}
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for PolicyResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        Some(("api".to_string(), self.port))
+    }
+    async fn service_headers(&self, _service: &str) -> Option<locald_core::config::HeaderRules> {
+        Some(locald_core::config::HeaderRules {
+            forwarded: true,
+            request_id: true,
+            response: locald_core::config::HeaderChanges {
+                remove: vec!["x-powered-by".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+    async fn service_cors(&self, _service: &str) -> Option<locald_core::config::CorsConfig> {
+        Some(locald_core::config::CorsConfig {
+            origins: vec!["https://web.localhost".to_string()],
+            ..Default::default()
+        })
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+#[tokio::test]
+async fn test_proxy_applies_header_rules_and_cors() {
+    // A backend that echoes the forwarded host and advertises itself
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let backend = Router::new().fallback(|headers: axum::http::HeaderMap| async move {
+        let host = headers["x-forwarded-host"].to_str().unwrap().to_string();
+        ([("x-powered-by", "express")], host)
+    });
+    tokio::spawn(async move { axum::serve(listener, backend).await });
+
+    let proxy = ProxyManager::new(Arc::new(PolicyResolver { port }), Router::new(), None);
+    let app = proxy.make_app();
+
+    // Preflights are answered by the proxy
+    let req = Request::builder()
+        .method("OPTIONS")
+        .uri("/orders")
+        .header("Host", "api.localhost")
+        .header("Origin", "https://web.localhost")
+        .header("Access-Control-Request-Method", "POST")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://web.localhost"
+    );
+
+    let req = Request::builder()
+        .uri("/orders")
+        .header("Host", "api.localhost")
+        .header("Origin", "https://web.localhost")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://web.localhost"
+    );
+    assert!(headers.contains_key("x-request-id"));
+    assert!(!headers.contains_key("x-powered-by"));
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body_bytes[..], b"api.localhost");
+}
+
+/// Sends every request to a gRPC service named `greeter` on `port`.
+#[derive(Debug)]
+struct GrpcResolver {
+    port: u16,
+}
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for GrpcResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        Some(("greeter".to_string(), self.port))
+    }
+    async fn service_protocol(&self, _service: &str) -> locald_core::config::Protocol {
+        locald_core::config::Protocol::Grpc
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+#[tokio::test]
+async fn test_grpc_backend_over_http2_with_trailers() {
+    use http_body_util::BodyExt;
+    use hyper::body::{Bytes, Frame};
+
+    // An HTTP/2-only backend that answers like a gRPC server: data, then trailers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = hyper::service::service_fn(|req: hyper::Request<_>| async move {
+                assert_eq!(req.version(), hyper::Version::HTTP_2);
+                let mut trailers = hyper::HeaderMap::new();
+                trailers.insert("grpc-status", "0".parse().unwrap());
+                let frames = vec![
+                    Ok::<_, std::convert::Infallible>(Frame::data(Bytes::from("hello"))),
+                    Ok(Frame::trailers(trailers)),
+                ];
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(
+                    http_body_util::StreamBody::new(futures_util::stream::iter(frames)),
+                ))
+            });
+            tokio::spawn(
+                hyper::server::conn::http2::Builder::new(hyper_util::rt::TokioExecutor::new())
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+            );
+        }
+    });
+
+    let proxy = ProxyManager::new(Arc::new(GrpcResolver { port }), Router::new(), None);
+    let req = Request::builder()
+        .method("POST")
+        .uri("/helloworld.Greeter/SayHello")
+        .header("Host", "greeter.localhost")
+        .header("Content-Type", "application/grpc")
+        .body(Body::empty())
+        .unwrap();
+    let response = proxy.make_app().oneshot(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap();
+    assert_eq!(body.trailers().unwrap()["grpc-status"], "0");
+    assert_eq!(&body.to_bytes()[..], b"hello");
+
+    // When the service is down, gRPC clients get a status they understand
+    let closed = {
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    let proxy = ProxyManager::new(Arc::new(GrpcResolver { port: closed }), Router::new(), None);
+    let req = Request::builder()
+        .method("POST")
+        .uri("/helloworld.Greeter/SayHello")
+        .header("Host", "greeter.localhost")
+        .body(Body::empty())
+        .unwrap();
+    let response = proxy.make_app().oneshot(req).await.unwrap();
+    assert_eq!(response.headers()["grpc-status"], "14");
+}
+
+/// An h2c service named `api` on `port`, with a request header rule and `faults`.
+#[derive(Debug)]
+struct ReplayResolver {
+    port: u16,
+    faults: Option<locald_core::config::FaultConfig>,
+}
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for ReplayResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        Some(("api".to_string(), self.port))
+    }
+    async fn resolve_service_port(&self, _service: &str) -> Option<u16> {
+        Some(self.port)
+    }
+    async fn service_protocol(&self, _service: &str) -> locald_core::config::Protocol {
+        locald_core::config::Protocol::H2c
+    }
+    async fn service_headers(&self, _service: &str) -> Option<locald_core::config::HeaderRules> {
+        Some(locald_core::config::HeaderRules {
+            request: locald_core::config::HeaderChanges {
+                set: [("x-env".to_string(), "dev".to_string())].into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+    async fn service_faults(&self, _service: &str) -> Option<locald_core::config::FaultConfig> {
+        self.faults
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+#[tokio::test]
+async fn test_replay_uses_service_protocol_and_rules() {
+    // An HTTP/2-only backend that echoes the x-env header
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = hyper::service::service_fn(|req: hyper::Request<_>| async move {
+                assert_eq!(req.version(), hyper::Version::HTTP_2);
+                let env = req.headers()["x-env"].as_bytes().to_vec();
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(http_body_util::Full::new(
+                    hyper::body::Bytes::from(env),
+                )))
+            });
+            tokio::spawn(
+                hyper::server::conn::http2::Builder::new(hyper_util::rt::TokioExecutor::new())
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+            );
+        }
+    });
+
+    let inspector = crate::inspect::RequestInspector::default();
+    inspector.set_enabled("api", true);
+    let req = Request::builder()
+        .uri("/orders")
+        .header("Host", "api.localhost")
+        .body(Body::empty())
+        .unwrap();
+    let (_, capture) = inspector.capture("api", req, "/orders");
+    let Some(capture) = capture else {
+        unreachable!("capture is on");
+    };
+    capture.error("not sent");
+    let original = inspector.list("api").requests[0].id;
+
+    let resolver = ReplayResolver { port, faults: None };
+    let replayed = inspector.replay(&resolver, "api", original).await.unwrap();
+    assert_eq!(replayed.replay_of, Some(original));
+    let Some(response) = &replayed.response else {
+        unreachable!("response was recorded");
+    };
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.text().as_deref(), Some("dev"));
+
+    // Fault rules answer in the service's place
+    let resolver = ReplayResolver {
+        port,
+        faults: Some(locald_core::config::FaultConfig {
+            error_rate: 100,
+            ..Default::default()
+        }),
+    };
+    let replayed = inspector.replay(&resolver, "api", original).await.unwrap();
+    assert_eq!(replayed.response.map(|r| r.status), Some(503));
+}
+
+#[tokio::test]
+async fn test_http2_client_to_http1_backend() {
+    use http_body_util::BodyExt;
+
+    // An HTTP/1 backend that answers with the Host header it got
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = hyper::service::service_fn(|req: hyper::Request<_>| async move {
+                assert_eq!(req.version(), hyper::Version::HTTP_11);
+                let host = req.headers()[hyper::header::HOST]
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(http_body_util::Full::new(
+                    hyper::body::Bytes::from(host),
+                )))
+            });
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service),
+            );
+        }
+    });
+
+    let proxy = ProxyManager::new(
+        Arc::new(MockResolver { port: Some(port) }),
+        Router::new(),
+        None,
+    );
+    let proxy_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_addr = proxy_listener.local_addr().unwrap();
+    let app = proxy.make_app();
+    tokio::spawn(async move {
+        axum::serve(
+            proxy_listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    // An HTTP/2 client sends the host as `:authority`, with no Host header
+    let stream = tokio::net::TcpStream::connect(proxy_addr).await.unwrap();
+    let (mut sender, conn) = hyper::client::conn::http2::handshake(
+        hyper_util::rt::TokioExecutor::new(),
+        hyper_util::rt::TokioIo::new(stream),
+    )
+    .await
+    .unwrap();
+    tokio::spawn(conn);
+    let req = hyper::Request::builder()
+        .uri("http://app.localhost/")
+        .body(http_body_util::Empty::<hyper::body::Bytes>::new())
+        .unwrap();
+    let response = sender.send_request(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"app.localhost");
+}
+
+/// Claims `shop.test` as a project domain, for the ACME server.
+#[derive(Debug)]
+struct DomainResolver;
+
+#[async_trait::async_trait]
+impl locald_core::resolver::ServiceResolver for DomainResolver {
+    async fn resolve_service_by_domain(&self, _domain: &str) -> Option<(String, u16)> {
+        None
+    }
+    async fn local_domains(&self) -> Vec<String> {
+        vec!["shop.test".to_string()]
+    }
+    async fn set_http_port(&self, _port: Option<u16>) {}
+    async fn set_https_port(&self, _port: Option<u16>) {}
+}
+
+/// A minimal ACME client with an ES256 account key.
+struct AcmeClient {
+    app: Router,
+    key: ring::signature::EcdsaKeyPair,
+    rng: ring::rand::SystemRandom,
+    kid: Option<String>,
+    nonce: String,
+    peer: std::net::SocketAddr,
+}
+
+impl AcmeClient {
+    const BASE: &str = "http://locald.localhost/api/acme";
+
+    async fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Body,
+    ) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+        use http_body_util::BodyExt;
+
+        let req = Request::builder()
+            .method(method)
+            .uri(url.strip_prefix("http://locald.localhost").unwrap())
+            .header("Host", "locald.localhost")
+            .header("Content-Type", "application/jose+json")
+            .extension(axum::extract::ConnectInfo(self.peer))
+            .body(body)
+            .unwrap();
+        let res = self.app.clone().oneshot(req).await.unwrap();
+        let (parts, body) = res.into_parts();
+        let body = body.collect().await.unwrap().to_bytes().to_vec();
+        (parts.status, parts.headers, body)
+    }
+
+    /// Sends a signed request; `None` is a POST-as-GET.
+    async fn post(
+        &mut self,
+        url: &str,
+        payload: Option<serde_json::Value>,
+    ) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+        use base64::Engine;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use ring::signature::KeyPair;
+
+        let mut protected = serde_json::json!({
+            "alg": "ES256",
+            "nonce": self.nonce,
+            "url": url,
+        });
+        match &self.kid {
+            Some(kid) => protected["kid"] = serde_json::json!(kid),
+            None => {
+                let point = self.key.public_key().as_ref();
+                protected["jwk"] = serde_json::json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                });
+            }
+        }
+        let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+        let payload = payload.map_or_else(String::new, |p| URL_SAFE_NO_PAD.encode(p.to_string()));
+        let signature = self
+            .key
+            .sign(&self.rng, format!("{protected}.{payload}").as_bytes())
+            .unwrap();
+        let body = serde_json::json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        });
+
+        let res = self
+            .request("POST", url, Body::from(body.to_string()))
+            .await;
+        self.nonce = res.1["replay-nonce"].to_str().unwrap().to_string();
+        res
+    }
+}
+
+#[tokio::test]
+async fn test_acme_issues_certificates_for_local_domains() {
+    use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair};
+
+    let temp = tempfile::tempdir().unwrap();
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let certs = locald_utils::cert::CertManager::from_ca_key(&ca_key.serialize_pem()).unwrap();
+    let acme = crate::acme::Acme::load(
+        &["shop.test".to_string(), "co.uk".to_string()],
+        Some(Arc::new(certs)),
+        temp.path().join("accounts.json"),
+    )
+    .await;
+    let api = Router::new().nest("/acme", acme.router());
+    let app = ProxyManager::new(Arc::new(DomainResolver), api, None).make_app();
+
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+    let key =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+    let mut client = AcmeClient {
+        app,
+        key,
+        rng,
+        kid: None,
+        nonce: String::new(),
+        peer: "192.168.1.20:40000".parse().unwrap(),
+    };
+
+    // Only clients on this machine get an answer
+    let (status, _, _) = client
+        .request(
+            "GET",
+            &format!("{}/directory", AcmeClient::BASE),
+            Body::empty(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    client.peer = "127.0.0.1:40000".parse().unwrap();
+
+    let (status, _, body) = client
+        .request(
+            "GET",
+            &format!("{}/directory", AcmeClient::BASE),
+            Body::empty(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let directory: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let new_nonce = directory["newNonce"].as_str().unwrap().to_string();
+    let (status, headers, _) = client.request("HEAD", &new_nonce, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+    client.nonce = headers["replay-nonce"].to_str().unwrap().to_string();
+
+    // Register, and get the same account back for the same key
+    let new_account = directory["newAccount"].as_str().unwrap().to_string();
+    let terms = serde_json::json!({ "termsOfServiceAgreed": true });
+    let (status, headers, _) = client.post(&new_account, Some(terms.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let kid = headers["location"].to_str().unwrap().to_string();
+    let (status, headers, _) = client.post(&new_account, Some(terms)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["location"], kid.as_str());
+    assert!(temp.path().join("accounts.json").exists());
+    client.kid = Some(kid);
+
+    // Names outside locald's domains are refused
+    let new_order = directory["newOrder"].as_str().unwrap().to_string();
+    let order = |names: &[&str]| {
+        serde_json::json!({
+            "identifiers": names
+                .iter()
+                .map(|name| serde_json::json!({ "type": "dns", "value": name }))
+                .collect::<Vec<_>>(),
+        })
+    };
+    for rejected in ["example.com", "shop.com", "*.co.uk", "shop.co.uk", "*.test"] {
+        let (status, _, body) = client.post(&new_order, Some(order(&[rejected]))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{rejected}");
+        assert!(String::from_utf8_lossy(&body).contains("rejectedIdentifier"));
+    }
+
+    let names = ["web.shop.localhost", "api.shop.test"];
+    let (status, headers, body) = client.post(&new_order, Some(order(&names))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let order_url = headers["location"].to_str().unwrap().to_string();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(created["status"], "ready");
+
+    let authz = created["authorizations"][0].as_str().unwrap().to_string();
+    let (status, _, body) = client.post(&authz, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let authz: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(authz["status"], "valid");
+    assert_eq!(authz["identifier"]["value"], "web.shop.localhost");
+
+    // A request can't be replayed with a used nonce
+    let used = client.nonce.clone();
+    client.post(&order_url, None).await;
+    client.nonce = used;
+    let (status, _, body) = client.post(&order_url, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(String::from_utf8_lossy(&body).contains("badNonce"));
+
+    let csr = rcgen::CertificateParams::new(names.map(String::from).to_vec())
+        .unwrap()
+        .serialize_request(&rcgen::KeyPair::generate().unwrap())
+        .unwrap();
+    let finalize = created["finalize"].as_str().unwrap().to_string();
+    let payload = serde_json::json!({
+        "csr": base64::Engine::encode(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+            csr.der(),
+        ),
+    });
+    let (status, _, body) = client.post(&finalize, Some(payload)).await;
+    assert_eq!(status, StatusCode::OK);
+    let finalized: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(finalized["status"], "valid");
+
+    let certificate = finalized["certificate"].as_str().unwrap().to_string();
+    let (status, headers, body) = client.post(&certificate, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/pem-certificate-chain");
+    assert!(String::from_utf8_lossy(&body).starts_with("-----BEGIN CERTIFICATE-----"));
+}
@@ -148,6 +148,7 @@ impl DockerRuntime {
                         service: service_name.clone(),
                         stream: LogStream::Stdout, // Docker logs mix? Bollard output has stream type
                         message: msg.to_string().trim().to_string(),
+                        structured: None,
                     };
                     if tx.send(entry).await.is_err() {
                         break;
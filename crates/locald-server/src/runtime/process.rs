@@ -99,6 +99,7 @@ impl ProcessRuntime {
                                 service: service_name.clone(),
                                 stream: LogStream::Stdout,
                                 message: line,
+                                structured: None,
                             };
                             if tx.blocking_send(entry).is_err() {
                                 return;
@@ -81,8 +81,10 @@ impl ServiceController for DockerController {
             }
             ServiceConfig::Typed(
                 TypedServiceConfig::Worker(_)
+                | TypedServiceConfig::Task(_)
                 | TypedServiceConfig::Container(_)
                 | TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
                 | TypedServiceConfig::Site(_),
             ) => {
                 anyhow::bail!("Invalid config for DockerController")
@@ -212,8 +214,10 @@ impl ServiceFactory for DockerFactory {
             ServiceConfig::Legacy(c) => c.image.is_some(),
             ServiceConfig::Typed(
                 TypedServiceConfig::Worker(_)
+                | TypedServiceConfig::Task(_)
                 | TypedServiceConfig::Container(_)
                 | TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
                 | TypedServiceConfig::Site(_),
             ) => false,
         }
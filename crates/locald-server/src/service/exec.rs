@@ -19,6 +19,18 @@ use sysinfo::{Pid, ProcessesToUpdate, System};
 use tokio::sync::{Mutex, broadcast};
 use tracing::warn;
 
+/// The signal a `stop_signal` names (`SIGINT` or `INT`, in any case), if it's one
+/// services can be stopped with.
+pub(crate) fn parse_stop_signal(name: &str) -> Option<Signal> {
+    match name.to_uppercase().as_str() {
+        "SIGTERM" | "TERM" => Some(Signal::SIGTERM),
+        "SIGINT" | "INT" => Some(Signal::SIGINT),
+        "SIGQUIT" | "QUIT" => Some(Signal::SIGQUIT),
+        "SIGKILL" | "KILL" => Some(Signal::SIGKILL),
+        _ => None,
+    }
+}
+
 pub struct ExecController {
     id: String,
     runtime: ProcessRuntime,
@@ -126,6 +138,7 @@ impl ServiceController for ExecController {
                                 service: id,
                                 stream: LogStream::Stdout,
                                 message: line,
+                                structured: None,
                             });
                         });
                     });
@@ -165,7 +178,9 @@ impl ServiceController for ExecController {
             }
             ServiceConfig::Typed(
                 TypedServiceConfig::Postgres(_)
+                | TypedServiceConfig::Redis(_)
                 | TypedServiceConfig::Worker(_)
+                | TypedServiceConfig::Task(_)
                 | TypedServiceConfig::Site(_),
             ) => {}
         }
@@ -185,9 +200,13 @@ impl ServiceController for ExecController {
                     ServiceConfig::Typed(TypedServiceConfig::Worker(c)) => {
                         (Some(c.command.clone()), c.workdir.clone())
                     }
+                    ServiceConfig::Typed(TypedServiceConfig::Task(c)) => {
+                        (Some(c.command.clone()), c.workdir.clone())
+                    }
                     ServiceConfig::Typed(
                         TypedServiceConfig::Container(_)
                         | TypedServiceConfig::Postgres(_)
+                        | TypedServiceConfig::Redis(_)
                         | TypedServiceConfig::Site(_),
                     ) => anyhow::bail!("Invalid config for ExecController (Host Process)"),
                 };
@@ -234,15 +253,13 @@ impl ServiceController for ExecController {
     async fn stop(&mut self) -> Result<()> {
         if let Some(child_mutex) = self.child.take() {
             if let Ok(mut child) = child_mutex.into_inner() {
-                let signal = self.config.common().stop_signal.as_deref().map_or(
-                    Signal::SIGTERM,
-                    |s| match s.to_uppercase().as_str() {
-                        "SIGINT" | "INT" => Signal::SIGINT,
-                        "SIGQUIT" | "QUIT" => Signal::SIGQUIT,
-                        "SIGKILL" | "KILL" => Signal::SIGKILL,
-                        _ => Signal::SIGTERM,
-                    },
-                );
+                let signal = self
+                    .config
+                    .common()
+                    .stop_signal
+                    .as_deref()
+                    .and_then(parse_stop_signal)
+                    .unwrap_or(Signal::SIGTERM);
 
                 crate::runtime::process::ProcessRuntime::terminate_process(
                     &mut child, &self.id, signal,
@@ -361,6 +378,13 @@ impl ServiceController for ExecController {
         match key {
             "port" => self.port.map(|p| p.to_string()),
             "cgroup_path" => self.cgroup_path.clone(),
+            "exit_code" => self.child.as_ref().and_then(|child_mutex| {
+                let mut child = child_mutex.lock().ok()?;
+                match child.try_wait() {
+                    Ok(Some(status)) => Some(status.exit_code().to_string()),
+                    _ => None,
+                }
+            }),
             _ => None,
         }
     }
@@ -437,8 +461,10 @@ impl ServiceFactory for ExecFactory {
             ServiceConfig::Typed(TypedServiceConfig::Exec(c)) => c.image.is_none(),
             ServiceConfig::Legacy(c) => c.image.is_none(),
             ServiceConfig::Typed(TypedServiceConfig::Worker(_)) => true,
+            ServiceConfig::Typed(TypedServiceConfig::Task(_)) => true,
             ServiceConfig::Typed(TypedServiceConfig::Container(_)) => true,
             ServiceConfig::Typed(TypedServiceConfig::Postgres(_)) => false,
+            ServiceConfig::Typed(TypedServiceConfig::Redis(_)) => false,
             ServiceConfig::Typed(TypedServiceConfig::Site(_)) => false,
         }
     }
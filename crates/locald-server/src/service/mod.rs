@@ -1,4 +1,5 @@
 pub mod docker;
 pub mod exec;
 pub mod postgres;
+pub mod redis;
 pub mod site;
@@ -5,7 +5,7 @@ use futures_util::stream::BoxStream;
 use locald_core::ipc::{LogEntry, LogStream, ServiceMetrics};
 use locald_core::service::{RuntimeState, ServiceCommand, ServiceController};
 use locald_core::state::{HealthStatus, ServiceState};
-use locald_utils::postgres::PostgresRunner;
+use locald_utils::postgres::{PostgresRunner, Provisioning};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -71,6 +71,7 @@ impl ServiceController for PostgresController {
                     service: service_name.clone(),
                     stream: if stream_name == "stderr" { LogStream::Stderr } else { LogStream::Stdout },
                     message: line,
+                    structured: None,
                 };
             }
         })
@@ -79,17 +80,21 @@ impl ServiceController for PostgresController {
     fn get_metadata(&self, key: &str) -> Option<String> {
         match key {
             "port" => Some(self.runner.port().to_string()),
-            "url" | "connection_string" => Some(format!(
-                "postgres://postgres@localhost:{}/postgres",
-                self.runner.port()
-            )),
+            "url" | "connection_string" => Some(self.runner.url()),
             _ => None,
         }
     }
 
-    async fn execute_command(&mut self, _cmd: ServiceCommand) -> Result<()> {
-        // Not implemented yet
-        Ok(())
+    async fn execute_command(&mut self, cmd: ServiceCommand) -> Result<()> {
+        match cmd {
+            // The manager wipes the data directory itself
+            ServiceCommand::Reset => Ok(()),
+            ServiceCommand::Snapshot(dest) => self.runner.snapshot(&dest).await,
+            ServiceCommand::Restore(src) => self.runner.restore(&src).await,
+            ServiceCommand::Custom(name, _) => {
+                anyhow::bail!("Postgres services don't support the {name} command")
+            }
+        }
     }
 
     fn snapshot(&self) -> serde_json::Value {
@@ -113,6 +118,26 @@ use tokio::sync::Mutex;
 #[derive(Debug, Copy, Clone)]
 pub struct PostgresFactory;
 
+/// Turns on SSL with the certificate locald issued, for `tls = true`. Comes before
+/// the user's own settings, so those can still override it.
+fn tls_settings(ctx: &ServiceContext) -> Vec<(String, String)> {
+    let files = [
+        ("ssl_cert_file", crate::tls::CERT_ENV),
+        ("ssl_key_file", crate::tls::KEY_ENV),
+        ("ssl_ca_file", crate::tls::CA_ENV),
+    ];
+    if files.iter().any(|(_, var)| !ctx.env.contains_key(*var)) {
+        return Vec::new();
+    }
+    std::iter::once(("ssl".to_string(), "on".to_string()))
+        .chain(
+            files
+                .iter()
+                .map(|(setting, var)| ((*setting).to_string(), ctx.env[*var].clone())),
+        )
+        .collect()
+}
+
 impl ServiceFactory for PostgresFactory {
     fn can_handle(&self, config: &ServiceConfig) -> bool {
         matches!(
@@ -125,7 +150,7 @@ impl ServiceFactory for PostgresFactory {
         &self,
         name: String,
         config: &ServiceConfig,
-        _ctx: &ServiceContext,
+        ctx: &ServiceContext,
     ) -> Arc<Mutex<dyn ServiceController>> {
         if let ServiceConfig::Typed(TypedServiceConfig::Postgres(pg_config)) = config {
             // We need data_dir.
@@ -133,19 +158,41 @@ impl ServiceFactory for PostgresFactory {
                 .map(|d| d.data_dir().join("postgres").join(&name))
                 .unwrap_or_else(|| PathBuf::from(".locald/postgres").join(&name));
 
-            let runner = Arc::new(PostgresRunner::new(
-                name.clone(),
-                pg_config
-                    .version
-                    .clone()
-                    .unwrap_or_else(|| "15".to_string()),
-                pg_config.common.port.unwrap_or_else(|| {
-                    std::net::TcpListener::bind("127.0.0.1:0")
-                        .map(|l| l.local_addr().unwrap().port())
-                        .unwrap_or(5432)
-                }),
-                data_dir,
-            ));
+            let provisioning = Provisioning {
+                databases: pg_config.databases.clone(),
+                extensions: pg_config.extensions.clone(),
+                init_scripts: pg_config
+                    .init_scripts
+                    .iter()
+                    .map(|script| ctx.project_root.join(script))
+                    .collect(),
+                settings: tls_settings(ctx)
+                    .into_iter()
+                    .chain(
+                        pg_config
+                            .settings
+                            .iter()
+                            .map(|(key, value)| (key.clone(), value.to_string())),
+                    )
+                    .collect(),
+            };
+
+            let runner = Arc::new(
+                PostgresRunner::new(
+                    name.clone(),
+                    pg_config
+                        .version
+                        .clone()
+                        .unwrap_or_else(|| "15".to_string()),
+                    pg_config.common.port.unwrap_or_else(|| {
+                        std::net::TcpListener::bind("127.0.0.1:0")
+                            .map(|l| l.local_addr().unwrap().port())
+                            .unwrap_or(5432)
+                    }),
+                    data_dir,
+                )
+                .with_provisioning(provisioning),
+            );
 
             Arc::new(Mutex::new(PostgresController::new(name, runner)))
         } else {
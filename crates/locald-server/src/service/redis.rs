@@ -0,0 +1,155 @@
+use anyhow::Result;
+use async_stream::stream;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use locald_core::ipc::{LogEntry, LogStream, ServiceMetrics};
+use locald_core::service::{RuntimeState, ServiceCommand, ServiceController};
+use locald_core::state::{HealthStatus, ServiceState};
+use locald_utils::redis::RedisRunner;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct RedisController {
+    id: String,
+    runner: Arc<RedisRunner>,
+}
+
+impl RedisController {
+    #[must_use]
+    pub fn new(id: String, runner: Arc<RedisRunner>) -> Self {
+        Self { id, runner }
+    }
+}
+
+#[async_trait]
+impl ServiceController for RedisController {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn prepare(&mut self) -> Result<()> {
+        // RedisRunner downloads and builds the binary in start().
+        Ok(())
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        self.runner.start().await
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.runner.stop().await
+    }
+
+    async fn read_state(&self) -> RuntimeState {
+        let running = self.runner.is_running().await;
+        RuntimeState {
+            pid: None,
+            port: Some(self.runner.port()),
+            status: if running {
+                ServiceState::Running
+            } else {
+                ServiceState::Stopped
+            },
+            health_status: if running {
+                HealthStatus::Healthy
+            } else {
+                HealthStatus::Unknown
+            },
+        }
+    }
+
+    async fn logs(&self) -> BoxStream<'static, LogEntry> {
+        let mut rx = self.runner.subscribe_logs();
+        let service_name = self.id.clone();
+
+        Box::pin(stream! {
+            while let Ok((stream_name, line)) = rx.recv().await {
+                yield LogEntry {
+                    timestamp: chrono::Utc::now().timestamp(),
+                    service: service_name.clone(),
+                    stream: if stream_name == "stderr" { LogStream::Stderr } else { LogStream::Stdout },
+                    message: line,
+                    structured: None,
+                };
+            }
+        })
+    }
+
+    fn get_metadata(&self, key: &str) -> Option<String> {
+        match key {
+            "port" => Some(self.runner.port().to_string()),
+            "url" | "connection_string" => Some(self.runner.url()),
+            _ => None,
+        }
+    }
+
+    async fn execute_command(&mut self, cmd: ServiceCommand) -> Result<()> {
+        match cmd {
+            ServiceCommand::Reset => self.runner.reset().await,
+            ServiceCommand::Snapshot(_) | ServiceCommand::Restore(_) => {
+                anyhow::bail!("Redis services don't support snapshots")
+            }
+            ServiceCommand::Custom(name, _) => {
+                anyhow::bail!("Redis services don't support the {name} command")
+            }
+        }
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    async fn restore(&mut self, _state: serde_json::Value) -> Result<()> {
+        Ok(())
+    }
+
+    async fn metrics(&self) -> Result<Option<ServiceMetrics>> {
+        Ok(None)
+    }
+}
+
+use locald_core::config::{ServiceConfig, TypedServiceConfig};
+use locald_core::service::{ServiceContext, ServiceFactory};
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Copy, Clone)]
+pub struct RedisFactory;
+
+impl ServiceFactory for RedisFactory {
+    fn can_handle(&self, config: &ServiceConfig) -> bool {
+        matches!(config, ServiceConfig::Typed(TypedServiceConfig::Redis(_)))
+    }
+
+    fn create(
+        &self,
+        name: String,
+        config: &ServiceConfig,
+        ctx: &ServiceContext,
+    ) -> Arc<Mutex<dyn ServiceController>> {
+        let version = if let ServiceConfig::Typed(TypedServiceConfig::Redis(redis_config)) = config
+        {
+            redis_config.version.clone()
+        } else {
+            tracing::error!("RedisFactory called with invalid config: {:?}", config);
+            None
+        };
+
+        // Each service gets its own data directory, keyed by "project:service"
+        let data_dir = directories::ProjectDirs::from("com", "locald", "locald")
+            .map(|d| d.data_dir().join("redis").join(&name))
+            .unwrap_or_else(|| PathBuf::from(".locald/redis").join(&name));
+
+        // The manager always assigns a port (sticky or configured) for non-worker services
+        let port = ctx.port.unwrap_or(locald_utils::redis::DEFAULT_PORT);
+
+        let runner = Arc::new(RedisRunner::new(
+            name.clone(),
+            version.unwrap_or_else(|| locald_utils::redis::DEFAULT_VERSION.to_string()),
+            port,
+            data_dir,
+        ));
+
+        Arc::new(Mutex::new(RedisController::new(name, runner)))
+    }
+}
@@ -73,7 +73,8 @@ impl SiteService {
             service: format!("{}:build", name),
             stream,
             message,
-            timestamp: chrono::Utc::now().timestamp_millis(),
+            timestamp: chrono::Utc::now().timestamp(),
+            structured: None,
         };
         let _ = sender.send(entry);
     }
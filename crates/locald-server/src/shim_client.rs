@@ -3,7 +3,17 @@ use std::os::unix::io::{AsRawFd, FromRawFd};
 use std::os::unix::net::UnixListener;
 use tracing::{debug, info};
 
+/// Binds a privileged port on all interfaces through `locald-shim`.
 pub async fn bind_privileged_port(port: u16) -> Result<std::net::TcpListener> {
+    bind_privileged(port, false).await
+}
+
+/// Binds a privileged port on `127.0.0.1` only through `locald-shim`.
+pub async fn bind_privileged_loopback_port(port: u16) -> Result<std::net::TcpListener> {
+    bind_privileged(port, true).await
+}
+
+async fn bind_privileged(port: u16, loopback: bool) -> Result<std::net::TcpListener> {
     info!("Requesting privileged port {} from locald-shim...", port);
 
     // 1. Create a temporary directory for the socket
@@ -19,10 +29,11 @@ pub async fn bind_privileged_port(port: u16) -> Result<std::net::TcpListener> {
 
     debug!("Invoking shim");
 
+    cmd.arg("bind").arg(port.to_string()).arg(&socket_path);
+    if loopback {
+        cmd.arg("--loopback");
+    }
     let status = cmd
-        .arg("bind")
-        .arg(port.to_string())
-        .arg(&socket_path)
         .status()
         .await
         .context("Failed to execute locald-shim")?;
@@ -0,0 +1,154 @@
+//! The locald-managed store of database snapshots.
+//!
+//! Each snapshot lives in `<root>/<service>/<name>/`: `data/` holds the copied data
+//! directory and `snapshot.json` the [`SnapshotInfo`] describing it.
+
+use anyhow::{Context, Result};
+use locald_core::ipc::SnapshotInfo;
+use std::path::{Path, PathBuf};
+
+const INFO_FILE: &str = "snapshot.json";
+
+/// The directory all snapshots are stored under.
+pub(crate) fn root() -> PathBuf {
+    directories::ProjectDirs::from("com", "locald", "locald").map_or_else(
+        || PathBuf::from(".locald/snapshots"),
+        |d| d.data_dir().join("snapshots"),
+    )
+}
+
+/// The directory holding the snapshot `name` of `service`.
+pub(crate) fn dir(root: &Path, service: &str, name: &str) -> PathBuf {
+    root.join(service).join(name)
+}
+
+/// The name used when the user doesn't pick one, e.g. `20261017-142501`.
+pub(crate) fn default_name() -> String {
+    chrono::Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// Snapshot names become directory names, so keep them to a safe alphabet.
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid snapshot name {name:?}: use letters, digits, '-', '_' and '.' (not leading)"
+        );
+    }
+    Ok(())
+}
+
+/// Records the metadata of a snapshot whose `data/` directory has been written.
+pub(crate) async fn finish(dir: &Path, service: &str, name: &str) -> Result<SnapshotInfo> {
+    let data = dir.join("data");
+    let size_bytes = tokio::task::spawn_blocking(move || dir_size(&data)).await??;
+    let info = SnapshotInfo {
+        service: service.to_string(),
+        name: name.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        size_bytes,
+    };
+    tokio::fs::write(dir.join(INFO_FILE), serde_json::to_vec_pretty(&info)?)
+        .await
+        .context("Failed to write snapshot metadata")?;
+    Ok(info)
+}
+
+/// Lists the snapshots in `root`, optionally only those of `service`.
+///
+/// Snapshots are sorted by service, then oldest first.
+pub(crate) async fn list(root: &Path, service: Option<&str>) -> Result<Vec<SnapshotInfo>> {
+    let mut snapshots = Vec::new();
+    let service_dirs = match service {
+        Some(service) => vec![root.join(service)],
+        None => subdirs(root).await?,
+    };
+
+    for service_dir in service_dirs {
+        for snapshot_dir in subdirs(&service_dir).await? {
+            // Snapshots without metadata were interrupted half-way
+            let Ok(content) = tokio::fs::read(snapshot_dir.join(INFO_FILE)).await else {
+                continue;
+            };
+            match serde_json::from_slice::<SnapshotInfo>(&content) {
+                Ok(info) => snapshots.push(info),
+                Err(e) => tracing::warn!("Ignoring snapshot {:?}: {}", snapshot_dir, e),
+            }
+        }
+    }
+
+    snapshots.sort_by(|a, b| {
+        (&a.service, a.created_at, &a.name).cmp(&(&b.service, b.created_at, &b.name))
+    });
+    Ok(snapshots)
+}
+
+async fn subdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(dirs),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        for (service, name) in [("shop:db", "before-migration"), ("blog:db", "seed")] {
+            let dir = dir(root, service, name);
+            tokio::fs::create_dir_all(dir.join("data")).await.unwrap();
+            tokio::fs::write(dir.join("data/PG_VERSION"), "16\n")
+                .await
+                .unwrap();
+            let info = finish(&dir, service, name).await.unwrap();
+            assert_eq!(info.size_bytes, 3);
+        }
+        // An interrupted snapshot has no metadata yet
+        tokio::fs::create_dir_all(dir(root, "shop:db", "partial").join("data"))
+            .await
+            .unwrap();
+
+        let all = list(root, None).await.unwrap();
+        let names: Vec<_> = all.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["seed", "before-migration"]);
+
+        let shop = list(root, Some("shop:db")).await.unwrap();
+        assert_eq!(shop.len(), 1);
+        assert!(list(root, Some("missing:db")).await.unwrap().is_empty());
+
+        assert!(validate_name("before-migration.1").is_ok());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(".hidden").is_err());
+    }
+}
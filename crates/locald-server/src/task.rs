@@ -0,0 +1,161 @@
+//! Bookkeeping for one-shot `task` services.
+//!
+//! A task is skipped when its [`fingerprint`] matches the one recorded for its last
+//! successful run. The fingerprint covers the task's configuration, its resolved
+//! environment and the contents of every file matched by its `inputs` globs.
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSetBuilder};
+use ignore::WalkBuilder;
+use locald_core::config::ServiceConfig;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Hashes everything that decides whether a task has to run again.
+///
+/// `root` is the task's working directory; `inputs` are resolved relative to it.
+pub(crate) async fn fingerprint(
+    config: &ServiceConfig,
+    env: &HashMap<String, String>,
+    root: &Path,
+    inputs: &[String],
+) -> Result<String> {
+    let mut hasher = Sha256::new();
+
+    // `serde_json::Value` keeps object keys sorted, so this is stable across runs
+    let config = serde_json::to_value(config).context("Failed to serialize task config")?;
+    hasher.update(config.to_string().as_bytes());
+    hasher.update([0]);
+
+    let mut env: Vec<_> = env.iter().collect();
+    env.sort();
+    for (key, value) in env {
+        hasher.update(format!("{key}={value}").as_bytes());
+        hasher.update([0]);
+    }
+
+    let files = {
+        let root = root.to_path_buf();
+        let inputs = inputs.to_vec();
+        tokio::task::spawn_blocking(move || input_files(&root, &inputs)).await??
+    };
+    for relative in files {
+        let content = tokio::fs::read(root.join(&relative))
+            .await
+            .with_context(|| format!("Failed to read task input {}", relative.display()))?;
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(Sha256::digest(&content));
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Lists the files under `root` matched by `inputs`, relative to `root`.
+fn input_files(root: &Path, inputs: &[String]) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    if inputs.is_empty() {
+        return Ok(files);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in inputs {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid input glob {pattern:?}"))?);
+    }
+    let globs = builder.build()?;
+
+    // Only walk the literal prefix of each pattern rather than the whole project
+    let bases: BTreeSet<PathBuf> = inputs.iter().map(|p| glob_base(p)).collect();
+    for base in bases {
+        let dir = root.join(base);
+        if !dir.exists() {
+            continue;
+        }
+
+        for entry in WalkBuilder::new(&dir)
+            .standard_filters(false)
+            .filter_entry(|e| e.file_name() != ".git")
+            .build()
+        {
+            let entry = entry?;
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            if let Ok(relative) = entry.path().strip_prefix(root) {
+                if globs.is_match(relative) {
+                    files.insert(relative.to_path_buf());
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| {
+            !c.as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{'])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use locald_core::config::{TaskServiceConfig, TypedServiceConfig};
+
+    #[tokio::test]
+    async fn test_fingerprint_tracks_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        tokio::fs::create_dir_all(root.join("db/migrate"))
+            .await
+            .unwrap();
+        tokio::fs::write(root.join("db/migrate/001_init.sql"), "create table a();")
+            .await
+            .unwrap();
+        tokio::fs::write(root.join("README.md"), "hello")
+            .await
+            .unwrap();
+
+        let inputs = vec!["db/migrate/**".to_string()];
+        let config = ServiceConfig::Typed(TypedServiceConfig::Task(TaskServiceConfig {
+            command: "migrate".to_string(),
+            inputs: inputs.clone(),
+            ..TaskServiceConfig::default()
+        }));
+        let env = HashMap::new();
+
+        let first = fingerprint(&config, &env, root, &inputs).await.unwrap();
+        assert_eq!(
+            first,
+            fingerprint(&config, &env, root, &inputs).await.unwrap()
+        );
+
+        // Files outside the inputs don't matter
+        tokio::fs::write(root.join("README.md"), "changed")
+            .await
+            .unwrap();
+        assert_eq!(
+            first,
+            fingerprint(&config, &env, root, &inputs).await.unwrap()
+        );
+
+        tokio::fs::write(root.join("db/migrate/002_more.sql"), "create table b();")
+            .await
+            .unwrap();
+        let second = fingerprint(&config, &env, root, &inputs).await.unwrap();
+        assert_ne!(first, second);
+
+        let env = HashMap::from([("DATABASE_URL".to_string(), "postgres://x".to_string())]);
+        assert_ne!(
+            second,
+            fingerprint(&config, &env, root, &inputs).await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Certificates for services that terminate TLS themselves (`tls = true`).
+//!
+//! Each service gets `<root>/<project>/<service>/` holding `cert.pem`, `key.pem` and
+//! `names` (the names the certificate covers). The certificate is reissued when the
+//! names change or it gets close to expiring; the daemon checks for that periodically
+//! and restarts services whose certificate was renewed.
+
+use anyhow::{Context, Result};
+use locald_utils::cert::CertManager;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable holding the path of the service's certificate.
+pub const CERT_ENV: &str = "LOCALD_TLS_CERT";
+/// Environment variable holding the path of the service's private key.
+pub const KEY_ENV: &str = "LOCALD_TLS_KEY";
+/// Environment variable holding the path of the locald root CA.
+pub const CA_ENV: &str = "LOCALD_TLS_CA";
+
+/// How long service certificates are valid for.
+pub(crate) const VALIDITY: Duration = Duration::from_hours(30 * 24);
+/// Certificates are renewed once they have less than this left.
+const RENEW_BEFORE: Duration = Duration::from_hours(7 * 24);
+
+const NAMES_FILE: &str = "names";
+
+/// The directory all service certificates are stored under.
+pub(crate) fn root() -> PathBuf {
+    directories::ProjectDirs::from("com", "locald", "locald").map_or_else(
+        || PathBuf::from(".locald/tls"),
+        |d| d.data_dir().join("tls"),
+    )
+}
+
+/// Where a service's certificate and key live.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceTls {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub ca: PathBuf,
+}
+
+impl ServiceTls {
+    /// The files for `service` (`project:name`) under `root`.
+    pub(crate) fn new(root: &Path, service: &str, ca: PathBuf) -> Self {
+        let dir = service
+            .split(':')
+            .fold(root.to_path_buf(), |dir, part| dir.join(part));
+        Self {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+            ca,
+        }
+    }
+
+    /// The environment variables pointing the service at its files.
+    #[must_use]
+    pub fn env(&self) -> [(String, String); 3] {
+        [
+            (CERT_ENV.to_string(), self.cert.display().to_string()),
+            (KEY_ENV.to_string(), self.key.display().to_string()),
+            (CA_ENV.to_string(), self.ca.display().to_string()),
+        ]
+    }
+
+    fn names_file(&self) -> PathBuf {
+        self.cert.with_file_name(NAMES_FILE)
+    }
+
+    /// Whether the certificate is missing, for other names, or expires within
+    /// `RENEW_BEFORE` of `now`.
+    pub(crate) async fn needs_renewal(&self, names: &[String], now: SystemTime) -> bool {
+        if !self.key.exists() {
+            return true;
+        }
+        let Ok(issued) = tokio::fs::metadata(&self.cert)
+            .await
+            .and_then(|m| m.modified())
+        else {
+            return true;
+        };
+        let current = tokio::fs::read_to_string(self.names_file())
+            .await
+            .unwrap_or_default();
+        current != names.join("\n") || issued + VALIDITY < now + RENEW_BEFORE
+    }
+
+    /// Issues a certificate for `names` if the current one needs renewal. Returns
+    /// whether a new certificate was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if issuing the certificate or writing the files fails.
+    pub(crate) async fn ensure(&self, certs: &CertManager, names: &[String]) -> Result<bool> {
+        if !self.needs_renewal(names, SystemTime::now()).await {
+            return Ok(false);
+        }
+        let issued = certs.issue(names, VALIDITY)?;
+
+        if let Some(dir) = self.cert.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        write_private(&self.key, &issued.key_pem).await?;
+        tokio::fs::write(&self.cert, issued.cert_pem)
+            .await
+            .with_context(|| format!("Failed to write {}", self.cert.display()))?;
+        tokio::fs::write(self.names_file(), names.join("\n"))
+            .await
+            .with_context(|| format!("Failed to write {}", self.names_file().display()))?;
+        Ok(true)
+    }
+}
+
+/// Writes a private key readable only by the current user (Postgres, among others,
+/// refuses keys anyone else can read).
+async fn write_private(path: &Path, contents: &str) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let _ = tokio::fs::remove_file(path).await;
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_renewal() {
+        let Ok(temp) = tempfile::tempdir() else {
+            unreachable!("temp dir should be creatable");
+        };
+        let tls = ServiceTls::new(temp.path(), "shop:api", temp.path().join("rootCA.pem"));
+        assert_eq!(
+            tls.cert,
+            temp.path().join("shop").join("api").join("cert.pem")
+        );
+        let names = vec!["api.shop.localhost".to_string(), "localhost".to_string()];
+        let now = SystemTime::now();
+        assert!(tls.needs_renewal(&names, now).await);
+
+        let Some(dir) = tls.cert.parent() else {
+            unreachable!("cert path has a parent");
+        };
+        assert!(std::fs::create_dir_all(dir).is_ok());
+        assert!(write_private(&tls.key, "key").await.is_ok());
+        assert!(tokio::fs::write(&tls.cert, "cert").await.is_ok());
+        assert!(
+            tokio::fs::write(tls.names_file(), names.join("\n"))
+                .await
+                .is_ok()
+        );
+        assert!(!tls.needs_renewal(&names, now).await);
+
+        // Other names, or close to expiry
+        assert!(tls.needs_renewal(&names[..1], now).await);
+        assert!(
+            tls.needs_renewal(&names, now + VALIDITY - RENEW_BEFORE / 2)
+                .await
+        );
+
+        let Ok(meta) = std::fs::metadata(&tls.key) else {
+            unreachable!("key was written");
+        };
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&meta.permissions()) & 0o777,
+            0o600
+        );
+    }
+}
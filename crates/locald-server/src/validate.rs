@@ -0,0 +1,693 @@
+//! Checks a project's `locald.toml` and reports every problem at once, with where in
+//! the file each one is (`locald config validate`, and `locald up` before it starts
+//! anything).
+//!
+//! - Syntax errors come from the TOML parser, which recovers and keeps going.
+//! - Values of the wrong type come from deserializing each section on its own, so a
+//!   mistake in one service doesn't hide mistakes in the others.
+//! - Keys the config schema (the one `locald ai schema` prints) doesn't know are
+//!   warnings: serde ignores them, so a typo silently does nothing.
+//! - Everything else is [`ConfigLoader::check_config`], run on the project merged with
+//!   the workspace layers above it, the way the daemon loads it.
+//! - `${env.NAME}` references are checked against this process's environment with the
+//!   workspace's env and `.env` over it, which is what `locald up` hands the daemon.
+
+use crate::config_loader::{ConfigLoader, ConfigProblem, LayerConfig, dotted_key};
+use anyhow::{Context, Result};
+use locald_core::config::{
+    ExecServiceConfig, LocaldConfig, ProfileConfig, ProjectConfig, RouteTarget, ServiceConfig,
+    TypedServiceConfig,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+use toml::de::{DeTable, DeValue, ValueDeserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A place in a file. Lines and columns start at 1; columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The part of the file a diagnostic is about. `end` is just past its last character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourceSpan {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// One problem with the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The key the problem is about, such as `services.web.depends_on`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub file: PathBuf,
+    /// Where in `file` the problem is. Missing when the key isn't in the file, e.g.
+    /// a service defined by a workspace layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+}
+
+/// Validates the `locald.toml` in `path`, with `profile` applied if given.
+///
+/// Diagnostics are sorted by where they are in the file; an empty list means the
+/// config is valid.
+///
+/// # Errors
+///
+/// Returns an error if `locald.toml` can't be read.
+pub async fn validate_project(path: &Path, profile: Option<&str>) -> Result<Vec<Diagnostic>> {
+    let file = path.join("locald.toml");
+    let source = tokio::fs::read_to_string(&file)
+        .await
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let path = path.to_path_buf();
+    let layers = ConfigLoader::load_upstream_configs(&path).await;
+    let env_vars = ConfigLoader::load_env_vars(&path).await.unwrap_or_default();
+    let host_env = ConfigLoader::host_env(ConfigLoader::process_env(), &env_vars);
+    Ok(validate_source(&source, &file, layers, profile, &host_env))
+}
+
+/// Validates `source`, the contents of `file`. `${env.NAME}` references are looked
+/// up in `host_env` (see [`ConfigLoader::host_env`]).
+#[must_use]
+pub fn validate_source<S: std::hash::BuildHasher>(
+    source: &str,
+    file: &Path,
+    layers: Vec<(LayerConfig, PathBuf)>,
+    profile: Option<&str>,
+    host_env: &HashMap<String, String, S>,
+) -> Vec<Diagnostic> {
+    let mut report = Report {
+        source,
+        file,
+        diagnostics: Vec::new(),
+    };
+
+    let (document, errors) = DeTable::parse_recoverable(source);
+    if !errors.is_empty() {
+        // What the parser recovered is too unreliable to check further
+        for error in errors {
+            report.push(Severity::Error, error.message(), None, error.span());
+        }
+        return report.finish();
+    }
+    let document = document.into_inner();
+
+    if let Ok(schema) = serde_json::to_value(schemars::schema_for!(LocaldConfig)) {
+        let mut key = Vec::new();
+        unknown_keys(&schema, &schema, &document, &mut key, &mut report);
+    }
+
+    if let Some(mut config) = parse_config(&document, &mut report) {
+        ConfigLoader::apply_upstream_layers(&mut config, layers);
+        if let Some(profile) = profile {
+            if let Err(e) = config.apply_profile(profile) {
+                let key = vec!["profiles".to_string(), profile.to_string()];
+                report.problem(&document, &ConfigProblem { key, message: e });
+            }
+        }
+        for problem in ConfigLoader::check_config(&config) {
+            report.problem(&document, &problem);
+        }
+        for problem in ConfigLoader::check_env_references(&config, host_env) {
+            report.problem(&document, &problem);
+        }
+    }
+
+    report.finish()
+}
+
+/// Whether any of `diagnostics` is an error.
+#[must_use]
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+struct Report<'a> {
+    source: &'a str,
+    file: &'a Path,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Report<'_> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        message: impl Into<String>,
+        key: Option<String>,
+        span: Option<Range<usize>>,
+    ) {
+        let span = span.map(|span| SourceSpan {
+            start: position(self.source, span.start),
+            end: position(self.source, span.end),
+        });
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message: message.into(),
+            key,
+            file: self.file.to_path_buf(),
+            span,
+        });
+    }
+
+    fn problem(&mut self, document: &DeTable<'_>, problem: &ConfigProblem) {
+        let key = (!problem.key.is_empty()).then(|| problem.dotted_key());
+        let span = key_span(document, &problem.key);
+        self.push(Severity::Error, problem.message.clone(), key, span);
+    }
+
+    fn finish(mut self) -> Vec<Diagnostic> {
+        // Problems outside the file go last
+        self.diagnostics.sort_by_key(|d| {
+            (
+                d.span.is_none(),
+                d.span.map(|s| s.start),
+                d.severity,
+                d.message.clone(),
+            )
+        });
+        self.diagnostics.dedup();
+        self.diagnostics
+    }
+}
+
+/// The line and column of byte `offset` in `source`.
+fn position(source: &str, offset: usize) -> Position {
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+fn get<'a, 'i>(table: &'a DeTable<'i>, key: &str) -> Option<&'a Spanned<DeValue<'i>>> {
+    table
+        .iter()
+        .find(|(k, _)| k.get_ref() == key)
+        .map(|(_, v)| v)
+}
+
+/// Where `key` is in the document: the value for a scalar or array, the key for a
+/// table. If only part of the key is there, the deepest part found, as long as that
+/// still names something specific (a service, a route) rather than a whole section.
+fn key_span(document: &DeTable<'_>, key: &[String]) -> Option<Range<usize>> {
+    let mut table = document;
+    let mut found = None;
+    for (depth, part) in key.iter().enumerate() {
+        let Some((k, v)) = table.iter().find(|(k, _)| k.get_ref() == part) else {
+            break;
+        };
+        found = Some((
+            depth + 1,
+            if v.get_ref().is_table() {
+                k.span()
+            } else {
+                v.span()
+            },
+        ));
+        match v.get_ref() {
+            DeValue::Table(inner) => table = inner,
+            DeValue::String(_)
+            | DeValue::Integer(_)
+            | DeValue::Float(_)
+            | DeValue::Boolean(_)
+            | DeValue::Datetime(_)
+            | DeValue::Array(_) => break,
+        }
+    }
+    found
+        .filter(|(depth, _)| *depth >= key.len().min(2))
+        .map(|(_, span)| span)
+}
+
+/// Deserializes the document section by section, reporting every section that
+/// doesn't, and returns the config if they all do.
+fn parse_config(document: &DeTable<'_>, report: &mut Report<'_>) -> Option<LocaldConfig> {
+    let reported = report.diagnostics.len();
+
+    let project = match get(document, "project") {
+        Some(value) => deserialize::<ProjectConfig>(value, &["project"], report),
+        None => {
+            report.push(Severity::Error, "Missing the [project] table", None, None);
+            None
+        }
+    };
+
+    let mut services = HashMap::new();
+    for (name, value) in tables(document, "services", report) {
+        let key = ["services", name];
+        let service = if value
+            .get_ref()
+            .as_table()
+            .and_then(|t| get(t, "type"))
+            .is_some()
+        {
+            deserialize::<TypedServiceConfig>(value, &key, report).map(ServiceConfig::Typed)
+        } else {
+            deserialize::<ExecServiceConfig>(value, &key, report).map(ServiceConfig::Legacy)
+        };
+        if let Some(service) = service {
+            services.insert(name.to_string(), service);
+        }
+    }
+
+    let mut routes = BTreeMap::new();
+    for (pattern, value) in tables(document, "routes", report) {
+        if let Some(target) = deserialize::<RouteTarget>(value, &["routes", pattern], report) {
+            routes.insert(pattern.to_string(), target);
+        }
+    }
+
+    let mut profiles = BTreeMap::new();
+    for (name, value) in tables(document, "profiles", report) {
+        if let Some(profile) = deserialize::<ProfileConfig>(value, &["profiles", name], report) {
+            profiles.insert(name.to_string(), profile);
+        }
+    }
+
+    // Only errors are reported while parsing
+    let project = project?;
+    (report.diagnostics.len() == reported).then_some(LocaldConfig {
+        project,
+        services,
+        routes,
+        profiles,
+    })
+}
+
+/// The entries of the table at `key`, reporting it if it isn't a table.
+fn tables<'a, 'i>(
+    document: &'a DeTable<'i>,
+    key: &str,
+    report: &mut Report<'_>,
+) -> Vec<(&'a str, &'a Spanned<DeValue<'i>>)> {
+    match get(document, key).map(|v| (v, v.get_ref())) {
+        None => Vec::new(),
+        Some((_, DeValue::Table(table))) => table
+            .iter()
+            .map(|(k, v)| (k.get_ref().as_ref(), v))
+            .collect(),
+        Some((value, other)) => {
+            report.push(
+                Severity::Error,
+                format!("Expected a table, found {}", other.type_str()),
+                Some(key.to_string()),
+                Some(value.span()),
+            );
+            Vec::new()
+        }
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(
+    value: &Spanned<DeValue<'_>>,
+    key: &[&str],
+    report: &mut Report<'_>,
+) -> Option<T> {
+    match T::deserialize(ValueDeserializer::from(value.clone())) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            let key: Vec<String> = key.iter().map(ToString::to_string).collect();
+            let span = e.span().unwrap_or_else(|| value.span());
+            report.push(
+                Severity::Error,
+                e.message(),
+                Some(dotted_key(&key)),
+                Some(span),
+            );
+            None
+        }
+    }
+}
+
+/// What a schema says about an object's keys.
+#[derive(Default)]
+struct Keys<'s> {
+    properties: BTreeMap<&'s str, &'s Value>,
+    /// The schema of keys not in `properties`, for maps.
+    additional: Option<&'s Value>,
+    /// Whether any key is allowed.
+    open: bool,
+}
+
+/// Warns about every key in `table` that `schema` doesn't allow, and the same for
+/// the tables inside it.
+fn unknown_keys(
+    root: &Value,
+    schema: &Value,
+    table: &DeTable<'_>,
+    key: &mut Vec<String>,
+    report: &mut Report<'_>,
+) {
+    let tag = get(table, "type").and_then(|t| t.get_ref().as_str());
+    let mut keys = Keys::default();
+    collect_keys(root, schema, tag, &mut keys);
+    if keys.properties.is_empty() && keys.additional.is_none() {
+        // Not an object schema, or one that allows anything
+        return;
+    }
+
+    for (name, value) in table {
+        let name = name.get_ref().as_ref();
+        key.push(name.to_string());
+        match keys.properties.get(name).copied().or(keys.additional) {
+            Some(child) => check_value(root, child, value.get_ref(), key, report),
+            None if keys.open => {}
+            None => {
+                let message = closest(name, keys.properties.keys().copied()).map_or_else(
+                    || format!("Unknown key '{name}'"),
+                    |suggestion| format!("Unknown key '{name}', did you mean '{suggestion}'?"),
+                );
+                let span = key_span_in(table, name);
+                report.push(Severity::Warning, message, Some(dotted_key(key)), span);
+            }
+        }
+        key.pop();
+    }
+}
+
+fn check_value(
+    root: &Value,
+    schema: &Value,
+    value: &DeValue<'_>,
+    key: &mut Vec<String>,
+    report: &mut Report<'_>,
+) {
+    match value {
+        DeValue::Table(table) => unknown_keys(root, schema, table, key, report),
+        DeValue::Array(items) => {
+            if let Some(item_schema) = items_schema(root, schema) {
+                for (index, item) in items.iter().enumerate() {
+                    key.push(index.to_string());
+                    check_value(root, item_schema, item.get_ref(), key, report);
+                    key.pop();
+                }
+            }
+        }
+        DeValue::String(_)
+        | DeValue::Integer(_)
+        | DeValue::Float(_)
+        | DeValue::Boolean(_)
+        | DeValue::Datetime(_) => {}
+    }
+}
+
+fn key_span_in(table: &DeTable<'_>, name: &str) -> Option<Range<usize>> {
+    table
+        .iter()
+        .find(|(k, _)| k.get_ref() == name)
+        .map(|(k, _)| k.span())
+}
+
+/// Follows a `$ref` to its definition in `$defs`.
+fn definition<'s>(root: &'s Value, schema: &'s Value) -> Option<&'s Value> {
+    let name = schema.get("$ref")?.as_str()?.strip_prefix("#/$defs/")?;
+    root.get("$defs")?.get(name)
+}
+
+/// Gathers the keys `schema` allows, following references and combinations. Of
+/// `anyOf`/`oneOf` alternatives, only those for the table's `type` are used.
+fn collect_keys<'s>(root: &'s Value, schema: &'s Value, tag: Option<&str>, keys: &mut Keys<'s>) {
+    if let Some(definition) = definition(root, schema) {
+        collect_keys(root, definition, tag, keys);
+    }
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        keys.properties
+            .extend(properties.iter().map(|(k, v)| (k.as_str(), v)));
+    }
+    match schema.get("additionalProperties") {
+        Some(Value::Bool(true)) => keys.open = true,
+        Some(additional @ Value::Object(_)) => keys.additional = Some(additional),
+        _ => {}
+    }
+    for branch in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        collect_keys(root, branch, tag, keys);
+    }
+
+    let alternatives: Vec<&Value> = ["anyOf", "oneOf"]
+        .iter()
+        .filter_map(|k| schema.get(*k).and_then(Value::as_array))
+        .flatten()
+        .collect();
+    let tagged = |branch: &'s Value| {
+        let mut tags = Vec::new();
+        branch_tags(root, branch, &mut tags);
+        tags
+    };
+    let matching: Vec<&Value> = alternatives
+        .iter()
+        .copied()
+        .filter(|b| tag.is_some_and(|tag| tagged(b).contains(&tag)))
+        .collect();
+    let chosen = if matching.is_empty() {
+        if tag.is_some() && alternatives.iter().any(|b| !tagged(b).is_empty()) {
+            // An unknown `type`, which deserializing reports
+            keys.open = true;
+            return;
+        }
+        alternatives
+            .into_iter()
+            .filter(|b| tagged(b).is_empty())
+            .collect()
+    } else {
+        matching
+    };
+    for branch in chosen {
+        collect_keys(root, branch, tag, keys);
+    }
+}
+
+/// The `type` values that select `schema`, if it's (a choice of) tagged variants.
+fn branch_tags<'s>(root: &'s Value, schema: &'s Value, tags: &mut Vec<&'s str>) {
+    if let Some(tag) = schema
+        .pointer("/properties/type/const")
+        .and_then(Value::as_str)
+    {
+        tags.push(tag);
+        return;
+    }
+    if let Some(definition) = definition(root, schema) {
+        branch_tags(root, definition, tags);
+    }
+    for branch in ["anyOf", "oneOf"]
+        .iter()
+        .filter_map(|k| schema.get(*k).and_then(Value::as_array))
+        .flatten()
+    {
+        branch_tags(root, branch, tags);
+    }
+}
+
+/// The schema of an array's items.
+fn items_schema<'s>(root: &'s Value, schema: &'s Value) -> Option<&'s Value> {
+    if let Some(items) = schema.get("items") {
+        return Some(items);
+    }
+    if let Some(definition) = definition(root, schema) {
+        return items_schema(root, definition);
+    }
+    ["anyOf", "oneOf"]
+        .iter()
+        .filter_map(|k| schema.get(*k).and_then(Value::as_array))
+        .flatten()
+        .find_map(|branch| items_schema(root, branch))
+}
+
+/// The candidate closest to `name`, if it's close enough to be a typo.
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 4))
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(source: &str) -> Vec<Diagnostic> {
+        validate_source(
+            source,
+            Path::new("locald.toml"),
+            Vec::new(),
+            None,
+            &HashMap::new(),
+        )
+    }
+
+    fn at(diagnostic: &Diagnostic) -> Option<(usize, usize)> {
+        diagnostic.span.map(|s| (s.start.line, s.start.column))
+    }
+
+    #[test]
+    fn test_reports_every_problem_with_its_location() {
+        let diagnostics = validate(
+            r#"[project]
+name = "shop"
+
+[services.web]
+command = "web"
+depends_on = ["api", "dbb"]
+stop_signal = "SIGSTOP"
+comand = "typo"
+
+[services.api]
+command = "api ${services.web.password}"
+
+[services.docs]
+command = "docs"
+domains = ["shop.localhost"]
+
+[routes]
+"/blog/*" = "blog"
+"#,
+        );
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.key.as_deref(), at(d)))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Severity::Error,
+                    Some("services.web.depends_on"),
+                    Some((6, 14))
+                ),
+                (
+                    Severity::Error,
+                    Some("services.web.stop_signal"),
+                    Some((7, 15))
+                ),
+                (Severity::Warning, Some("services.web.comand"), Some((8, 1))),
+                (
+                    Severity::Error,
+                    Some("services.api.command"),
+                    Some((11, 11))
+                ),
+                (
+                    Severity::Error,
+                    Some("services.docs.domains"),
+                    Some((15, 11))
+                ),
+                (Severity::Error, Some("routes.\"/blog/*\""), Some((18, 13))),
+            ],
+            "{diagnostics:#?}"
+        );
+        assert!(diagnostics[0].message.contains("unknown service 'dbb'"));
+        assert!(diagnostics[2].message.ends_with("did you mean 'command'?"));
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn test_semantic_checks_run_once_the_config_parses() {
+        let diagnostics = validate(
+            r#"[project]
+name = "shop"
+
+[services.api]
+command = "api ${services.web.password}"
+depends_on = ["web"]
+
+[services.web]
+command = "web"
+depends_on = ["api"]
+"#,
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(diagnostics.len(), 2, "{messages:?}");
+        assert!(messages[0].contains("'password' is only available for Postgres"));
+        assert_eq!(at(&diagnostics[0]), Some((5, 11)));
+        assert_eq!(
+            messages[1],
+            "Circular dependency between services: api, web"
+        );
+        assert_eq!(
+            diagnostics[1].key.as_deref(),
+            Some("services.api.depends_on")
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors_and_typed_services() {
+        let diagnostics = validate("[project]\nname = \"shop\"\n[services.web\ncommand = 1\n");
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:#?}");
+        assert_eq!(at(&diagnostics[0]).map(|(line, _)| line), Some(3));
+
+        let diagnostics = validate(
+            "[project]\nname = \"shop\"\n\n[services.db]\ntype = \"postgres\"\nversion = \"16\"\ncommand = \"postgres\"\n\n[services.cache]\ntype = \"memcached\"\n",
+        );
+        let found: Vec<(Severity, Option<&str>)> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.key.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Warning, Some("services.db.command")),
+                (Severity::Error, Some("services.cache")),
+            ],
+            "{diagnostics:#?}"
+        );
+        assert!(diagnostics[1].message.contains("memcached"));
+
+        // A value of the wrong type stops the checks that need the whole config
+        let diagnostics = validate(
+            "[project]\nname = \"shop\"\n\n[services.api]\ncommand = \"api\"\nport = \"eighty\"\ndepends_on = [\"dbb\"]\n",
+        );
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:#?}");
+        assert_eq!(diagnostics[0].key.as_deref(), Some("services.api"));
+        assert!(diagnostics[0].message.contains("\"eighty\""));
+
+        assert!(validate("[project]\nname = \"shop\"\n[services.web]\ncommand = \"web\"\nenv = { ANY_NAME = \"x\" }\nhealth_check = { type = \"http\", path = \"/\" }\n").is_empty());
+    }
+
+    #[test]
+    fn test_position() {
+        let source = "a = 1\nbé = 2\n";
+        assert_eq!(position(source, 0), Position { line: 1, column: 1 });
+        assert_eq!(position(source, 10), Position { line: 2, column: 4 });
+        assert_eq!(edit_distance("comand", "command"), 1);
+        assert_eq!(closest("prot", ["port", "env"].into_iter()), Some("port"));
+        assert_eq!(closest("xyz", ["port", "env"].into_iter()), None);
+    }
+}
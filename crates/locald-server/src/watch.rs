@@ -0,0 +1,279 @@
+//! File watching for services with a `watch` block.
+//!
+//! A [`ServiceWatcher`] watches a service's working directory, filters events through
+//! the configured include/exclude globs (and optionally `.gitignore`), and reports
+//! each settled batch of changes once the debounce interval has passed.
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use locald_core::config::WatchConfig;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tracing::{error, warn};
+
+/// Decides whether a changed path should trigger a restart.
+#[derive(Debug)]
+pub(crate) struct WatchFilter {
+    root: PathBuf,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    gitignore: Option<Gitignore>,
+}
+
+impl WatchFilter {
+    /// Builds a filter for files under `root`.
+    ///
+    /// `.gitignore` rules are read from `project_root`, which is where the repository's
+    /// top-level ignore file usually lives even when the service has its own `workdir`.
+    pub(crate) fn new(project_root: &Path, root: &Path, config: &WatchConfig) -> Result<Self> {
+        let include = if config.include.is_empty() {
+            None
+        } else {
+            Some(build_globset(&config.include)?)
+        };
+        let exclude = build_globset(&config.exclude)?;
+
+        let gitignore = if config.gitignore {
+            let mut builder = GitignoreBuilder::new(project_root);
+            for file in [
+                project_root.join(".gitignore"),
+                project_root.join(".git/info/exclude"),
+            ] {
+                if file.exists() {
+                    if let Some(e) = builder.add(&file) {
+                        warn!("Failed to parse {}: {}", file.display(), e);
+                    }
+                }
+            }
+            Some(
+                builder
+                    .build()
+                    .context("Failed to build gitignore matcher")?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            include,
+            exclude,
+            gitignore,
+        })
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        if relative.components().any(|c| c.as_os_str() == ".git") {
+            return false;
+        }
+
+        if let Some(gitignore) = &self.gitignore {
+            // `matched_path_or_any_parents` panics on paths outside the matcher's root
+            if path.starts_with(gitignore.path())
+                && gitignore
+                    .matched_path_or_any_parents(path, path.is_dir())
+                    .is_ignore()
+            {
+                return false;
+            }
+        }
+
+        if self.exclude.is_match(relative) {
+            return false;
+        }
+
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative))
+    }
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid watch glob {pattern:?}"))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// A running file watcher for a single service.
+///
+/// Dropping the watcher stops watching and cancels any pending change notification.
+#[derive(Debug)]
+pub(crate) struct ServiceWatcher {
+    _watcher: RecommendedWatcher,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for ServiceWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl ServiceWatcher {
+    /// Starts watching `root` and calls `on_change` with the changed paths after each
+    /// debounced batch of relevant changes.
+    pub(crate) fn spawn<F>(
+        project_root: &Path,
+        root: &Path,
+        config: &WatchConfig,
+        on_change: F,
+    ) -> Result<Self>
+    where
+        F: Fn(Vec<PathBuf>) + Send + Sync + 'static,
+    {
+        let filter = WatchFilter::new(project_root, root, config)?;
+        let debounce = config.debounce();
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+        let mut watcher =
+            notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+                let _ = tx.blocking_send(res);
+            })?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+        let task = tokio::spawn(async move {
+            let mut pending: Vec<PathBuf> = Vec::new();
+            // Only relevant changes move the deadline, so a steady stream of ignored
+            // events (logs, `.git`) can't hold a restart back forever
+            let mut deadline: Option<tokio::time::Instant> = None;
+
+            loop {
+                let timer = async move {
+                    match deadline {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                };
+
+                tokio::select! {
+                    res = rx.recv() => {
+                        match res {
+                            Some(Ok(event)) => {
+                                if event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove() {
+                                    for path in event.paths {
+                                        if filter.matches(&path) {
+                                            deadline = Some(tokio::time::Instant::now() + debounce);
+                                            if !pending.contains(&path) {
+                                                pending.push(path);
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            Some(Err(e)) => error!("Watch error: {}", e),
+                            None => return,
+                        }
+                    }
+                    () = timer => {
+                        deadline = None;
+                        on_change(std::mem::take(&mut pending));
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            task,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(root: &Path, config: &WatchConfig) -> WatchFilter {
+        WatchFilter::new(root, root, config).unwrap()
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let config = WatchConfig {
+            include: vec!["src/**/*.rs".to_string()],
+            exclude: vec!["src/generated/**".to_string()],
+            ..WatchConfig::default()
+        };
+        let filter = filter(root, &config);
+
+        assert!(filter.matches(&root.join("src/main.rs")));
+        assert!(filter.matches(&root.join("src/api/routes.rs")));
+        assert!(!filter.matches(&root.join("src/generated/schema.rs")));
+        assert!(!filter.matches(&root.join("README.md")));
+        assert!(!filter.matches(Path::new("/somewhere/else/src/main.rs")));
+    }
+
+    #[tokio::test]
+    async fn test_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        tokio::fs::write(root.join(".gitignore"), "target/\n*.log\n")
+            .await
+            .unwrap();
+
+        let filter_with = filter(root, &WatchConfig::default());
+        assert!(filter_with.matches(&root.join("src/main.rs")));
+        assert!(!filter_with.matches(&root.join("target/debug/app")));
+        assert!(!filter_with.matches(&root.join("server.log")));
+        assert!(!filter_with.matches(&root.join(".git/index")));
+
+        let filter_without = filter(
+            root,
+            &WatchConfig {
+                gitignore: false,
+                ..WatchConfig::default()
+            },
+        );
+        assert!(filter_without.matches(&root.join("server.log")));
+    }
+
+    #[tokio::test]
+    async fn test_ignored_changes_do_not_delay_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        tokio::fs::write(root.join(".gitignore"), "*.log\n")
+            .await
+            .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let config = WatchConfig {
+            debounce: Some(200),
+            ..WatchConfig::default()
+        };
+        let _watcher = ServiceWatcher::spawn(&root, &root, &config, move |changed| {
+            let _ = tx.send(changed);
+        })
+        .unwrap();
+
+        tokio::fs::write(root.join("main.rs"), "fn main() {}")
+            .await
+            .unwrap();
+        // Keep writing an ignored file for longer than the debounce
+        let log = root.join("server.log");
+        let writer = tokio::spawn(async move {
+            for i in 0..60 {
+                let _ = tokio::fs::write(&log, format!("line {i}\n")).await;
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        });
+
+        let changed = tokio::time::timeout(std::time::Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        writer.abort();
+        assert!(changed.iter().any(|p| p.ends_with("main.rs")));
+        assert!(!changed.iter().any(|p| p.ends_with("server.log")));
+    }
+}
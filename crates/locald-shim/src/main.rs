@@ -72,6 +72,10 @@ struct BundleRunArgs {
 struct BindArgs {
     port: u16,
     socket_path: PathBuf,
+
+    /// Bind on 127.0.0.1 instead of all interfaces.
+    #[arg(long)]
+    loopback: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -720,7 +724,12 @@ fn main() -> Result<()> {
         Commands::Bind(args) => {
             // Case: Bind - Run as root
             // Bind a privileged port and pass the FD to locald via Unix socket.
-            let listener = std::net::TcpListener::bind(format!("0.0.0.0:{}", args.port))
+            let host = if args.loopback {
+                "127.0.0.1"
+            } else {
+                "0.0.0.0"
+            };
+            let listener = std::net::TcpListener::bind(format!("{host}:{}", args.port))
                 .with_context(|| format!("Failed to bind to port {}", args.port))?;
 
             let stream =
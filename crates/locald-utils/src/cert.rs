@@ -1,24 +1,82 @@
 use anyhow::{Context, Result};
 use rcgen::{
-    BasicConstraints, CertificateParams, CertifiedIssuer, DistinguishedName, DnType, IsCa, KeyPair,
-    KeyUsagePurpose, SanType,
+    BasicConstraints, CertificateParams, CertificateSigningRequestParams, CertifiedIssuer,
+    DistinguishedName, DnType, IsCa, KeyPair, KeyUsagePurpose, SanType,
 };
 use rustls::crypto::ring::sign;
-use rustls::pki_types::PrivateKeyDer;
+use rustls::pki_types::{CertificateSigningRequestDer, PrivateKeyDer};
 use rustls::server::{ClientHello, ResolvesServerCert};
 use rustls::sign::CertifiedKey;
 use std::collections::HashMap;
 use std::fmt;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use tracing::{error, info};
 
 /// Manages TLS certificates for locald.
 ///
 /// Generates and caches certificates on the fly for requested domains, signed by the locald CA.
+/// Names covered by a wildcard domain (see [`CertManager::set_wildcard_domains`]) share one
+/// wildcard certificate.
 pub struct CertManager {
     issuer: CertifiedIssuer<'static, KeyPair>,
     cache: Mutex<HashMap<String, Arc<CertifiedKey>>>,
+    wildcards: Mutex<Vec<String>>,
+}
+
+/// A certificate and key for a service that terminates TLS itself.
+#[derive(Debug, Clone)]
+pub struct IssuedCert {
+    /// The certificate, PEM encoded.
+    pub cert_pem: String,
+    /// The private key (PKCS#8), PEM encoded.
+    pub key_pem: String,
+    /// When the certificate stops being valid.
+    pub not_after: SystemTime,
+}
+
+/// A certificate signing request (PKCS#10) whose signature has been checked.
+pub struct SigningRequest {
+    params: CertificateSigningRequestParams,
+    /// The DNS names and IP addresses the request asks for.
+    pub names: Vec<String>,
+}
+
+impl fmt::Debug for SigningRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningRequest")
+            .field("names", &self.names)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningRequest {
+    /// Parses a DER-encoded request and verifies its signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request can't be parsed, its signature is invalid, or it
+    /// asks for names other than DNS names and IP addresses.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let params =
+            CertificateSigningRequestParams::from_der(&CertificateSigningRequestDer::from(der))
+                .context("Invalid certificate signing request")?;
+        let names = params
+            .params
+            .subject_alt_names
+            .iter()
+            .map(|san| match san {
+                SanType::DnsName(name) => Ok(name.as_str().to_ascii_lowercase()),
+                SanType::IpAddress(ip) => Ok(ip.to_string()),
+                other => Err(anyhow::anyhow!(
+                    "Unsupported subject alternative name {other:?}"
+                )),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { params, names })
+    }
 }
 
 impl fmt::Debug for CertManager {
@@ -26,6 +84,7 @@ impl fmt::Debug for CertManager {
         f.debug_struct("CertManager")
             .field("issuer", &"CertifiedIssuer(...)")
             .field("cache", &self.cache)
+            .field("wildcards", &self.wildcards)
             .finish()
     }
 }
@@ -97,30 +156,120 @@ impl CertManager {
             .context("Failed to read rootCA-key.pem")?;
 
         // Offload CPU-intensive key parsing and issuer creation
-        let issuer = tokio::task::spawn_blocking(move || {
-            let ca_key =
-                KeyPair::from_pem(&ca_key_pem).context("Failed to parse rootCA-key.pem")?;
-
-            // Reconstruct CA params to match `locald trust`
-            let mut ca_params = CertificateParams::default();
-            let mut dn = DistinguishedName::new();
-            dn.push(DnType::CommonName, "locald Development CA");
-            dn.push(DnType::OrganizationName, "locald");
-            ca_params.distinguished_name = dn;
-            ca_params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
-            ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
-
-            CertifiedIssuer::self_signed(ca_params, ca_key)
-                .context("Failed to create CertifiedIssuer")
-        })
-        .await??;
+        tokio::task::spawn_blocking(move || Self::from_ca_key(&ca_key_pem)).await?
+    }
+
+    /// Creates a `CertManager` that signs with the CA key `ca_key_pem`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key cannot be parsed.
+    pub fn from_ca_key(ca_key_pem: &str) -> Result<Self> {
+        let ca_key = KeyPair::from_pem(ca_key_pem).context("Failed to parse rootCA-key.pem")?;
+
+        // Reconstruct CA params to match `locald trust`
+        let mut ca_params = CertificateParams::default();
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, "locald Development CA");
+        dn.push(DnType::OrganizationName, "locald");
+        ca_params.distinguished_name = dn;
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+
+        let issuer = CertifiedIssuer::self_signed(ca_params, ca_key)
+            .context("Failed to create CertifiedIssuer")?;
 
         Ok(Self {
             issuer,
             cache: Mutex::new(HashMap::new()),
+            wildcards: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Sets the wildcard domains (e.g. `*.myapp.localhost`) to issue wildcard
+    /// certificates for, instead of one certificate per subdomain.
+    pub fn set_wildcard_domains(&self, domains: Vec<String>) {
+        match self.wildcards.lock() {
+            Ok(mut wildcards) => *wildcards = domains,
+            Err(e) => error!("CertManager wildcards lock poisoned: {}", e),
+        }
+    }
+
+    /// The name to issue a certificate for: the wildcard covering `sni`, if any.
+    fn certificate_name(&self, sni: &str) -> Option<String> {
+        let wildcards = match self.wildcards.lock() {
+            Ok(wildcards) => wildcards,
+            Err(e) => {
+                error!("CertManager wildcards lock poisoned: {}", e);
+                return None;
+            }
+        };
+        let name = wildcards
+            .iter()
+            .find(|pattern| crate::domain::domain_matches(pattern, sni))
+            .cloned()
+            .unwrap_or_else(|| sni.to_string());
+        drop(wildcards);
+        Some(name)
+    }
+
+    /// Issues a certificate valid for `names` (domain names or IP addresses) for
+    /// `validity`, with its own key. Used for services that serve TLS themselves
+    /// rather than through the proxy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `names` is empty, a name is invalid, or signing fails.
+    pub fn issue(&self, names: &[String], validity: Duration) -> Result<IssuedCert> {
+        let Some(first) = names.first() else {
+            anyhow::bail!("A certificate needs at least one name");
+        };
+        info!("Issuing certificate for {}", names.join(", "));
+
+        let mut params = CertificateParams::default();
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, first.as_str());
+        params.distinguished_name = dn;
+        params.subject_alt_names = names
+            .iter()
+            .map(|name| match name.parse::<IpAddr>() {
+                Ok(ip) => Ok(SanType::IpAddress(ip)),
+                Err(_) => Ok(SanType::DnsName(name.clone().try_into()?)),
+            })
+            .collect::<Result<_>>()?;
+
+        // Allow for a little clock skew between locald and whoever checks the cert
+        let now = SystemTime::now();
+        let not_after = now + validity;
+        params.not_before = time::OffsetDateTime::from(now - Duration::from_hours(1));
+        params.not_after = time::OffsetDateTime::from(not_after);
+
+        let key_pair = KeyPair::generate()?;
+        let cert = params.signed_by(&key_pair, &self.issuer)?;
+
+        Ok(IssuedCert {
+            cert_pem: cert.pem(),
+            key_pem: key_pair.serialize_pem(),
+            not_after,
         })
     }
 
+    /// Signs a certificate for `request`, valid for `validity`. Returns the
+    /// certificate, PEM encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if signing fails.
+    pub fn sign(&self, request: SigningRequest, validity: Duration) -> Result<String> {
+        info!("Signing certificate for {}", request.names.join(", "));
+        let mut request = request.params;
+        let now = SystemTime::now();
+        request.params.is_ca = IsCa::ExplicitNoCa;
+        request.params.not_before = time::OffsetDateTime::from(now - Duration::from_hours(1));
+        request.params.not_after = time::OffsetDateTime::from(now + validity);
+        Ok(request.signed_by(&self.issuer)?.pem())
+    }
+
     fn generate_cert(&self, domain: &str) -> Result<Arc<CertifiedKey>> {
         info!("Generating certificate for {}", domain);
         let mut params = CertificateParams::new(vec![domain.to_string()])?;
@@ -148,12 +297,13 @@ impl CertManager {
 impl ResolvesServerCert for CertManager {
     fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
         let sni = client_hello.server_name()?;
+        let name = self.certificate_name(sni)?;
 
         // Check cache first
         {
             match self.cache.lock() {
                 Ok(cache) => {
-                    if let Some(cert) = cache.get(sni) {
+                    if let Some(cert) = cache.get(&name) {
                         return Some(cert.clone());
                     }
                 }
@@ -166,12 +316,12 @@ impl ResolvesServerCert for CertManager {
 
         // Generate new cert
         // Use block_in_place to avoid stalling the async reactor during heavy CPU ops
-        let cert_res = tokio::task::block_in_place(|| self.generate_cert(sni));
+        let cert_res = tokio::task::block_in_place(|| self.generate_cert(&name));
 
         match cert_res {
             Ok(cert) => match self.cache.lock() {
                 Ok(mut cache) => {
-                    cache.insert(sni.to_string(), cert.clone());
+                    cache.insert(name, cert.clone());
                     Some(cert)
                 }
                 Err(e) => {
@@ -180,13 +330,22 @@ impl ResolvesServerCert for CertManager {
                 }
             },
             Err(e) => {
-                error!("Failed to generate certificate for {}: {}", sni, e);
+                error!("Failed to generate certificate for {}: {}", name, e);
                 None
             }
         }
     }
 }
 
+/// Returns the path of the locald root CA certificate.
+///
+/// # Errors
+///
+/// Returns an error if the user's home directory cannot be determined.
+pub fn get_ca_cert_path() -> Result<PathBuf> {
+    Ok(get_certs_dir()?.join("rootCA.pem"))
+}
+
 /// Returns the directory where locald certificates are stored.
 ///
 /// # Errors
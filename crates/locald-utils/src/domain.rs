@@ -0,0 +1,60 @@
+//! Matching hosts against service domains, which may start with a `*.` wildcard label.
+//!
+//! The proxy routes requests and picks certificates with the same matcher, so a
+//! wildcard domain is served and covered by its certificate alike.
+
+/// The domain below a wildcard pattern (`myapp.localhost` for `*.myapp.localhost`).
+#[must_use]
+pub fn wildcard_base(pattern: &str) -> Option<&str> {
+    pattern.strip_prefix("*.")
+}
+
+/// Whether `host` is `pattern`, or a single label below a wildcard `pattern`.
+#[must_use]
+pub fn domain_matches(pattern: &str, host: &str) -> bool {
+    wildcard_base(pattern).map_or_else(
+        || pattern.eq_ignore_ascii_case(host),
+        |base| {
+            host.len() > base.len() + 1
+                && host.is_char_boundary(host.len() - base.len())
+                && host[host.len() - base.len()..].eq_ignore_ascii_case(base)
+                && host[..host.len() - base.len()]
+                    .strip_suffix('.')
+                    .is_some_and(|label| !label.contains('.'))
+        },
+    )
+}
+
+/// Whether `name` is a single label or a public suffix (`com`, `co.uk`, `github.io`),
+/// which names on the internet are registered under rather than a domain of its own.
+#[must_use]
+pub fn is_public_suffix(name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    !name.contains('.') || psl::suffix_str(&name).is_some_and(|suffix| suffix == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_matches_single_label_wildcards() {
+        assert!(domain_matches("tenant.test", "tenant.test"));
+        assert!(domain_matches("*.myapp.localhost", "acme.myapp.localhost"));
+        assert!(domain_matches("*.myapp.localhost", "ACME.MyApp.localhost"));
+        assert!(!domain_matches("*.myapp.localhost", "myapp.localhost"));
+        assert!(!domain_matches("*.myapp.localhost", "a.b.myapp.localhost"));
+        assert!(!domain_matches("*.myapp.localhost", "xmyapp.localhost"));
+    }
+
+    #[test]
+    fn test_is_public_suffix() {
+        assert!(is_public_suffix("com"));
+        assert!(is_public_suffix("localhost"));
+        assert!(is_public_suffix("co.uk"));
+        assert!(is_public_suffix("GitHub.io"));
+        assert!(!is_public_suffix("myapp.localhost"));
+        assert!(!is_public_suffix("tenant.test"));
+        assert!(!is_public_suffix("example.co.uk"));
+    }
+}
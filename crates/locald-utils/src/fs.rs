@@ -126,6 +126,40 @@ pub fn safe_join(root: &Path, path: &str) -> Result<PathBuf> {
     Ok(result)
 }
 
+/// Recursively copies the directory `src` to `dst`, preserving permissions and symlinks.
+///
+/// `dst` must not exist yet. Returns the total number of bytes copied.
+///
+/// # Errors
+///
+/// Returns an error if `dst` already exists or any entry cannot be copied.
+pub fn copy_dir(src: &Path, dst: &Path) -> Result<u64> {
+    if dst.exists() {
+        return Err(anyhow!("Destination already exists: {}", dst.display()));
+    }
+
+    fs::create_dir_all(dst)?;
+    fs::set_permissions(dst, fs::metadata(src)?.permissions())?;
+
+    let mut bytes = 0;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&from)?, &to)?;
+        } else if file_type.is_dir() {
+            bytes += copy_dir(&from, &to)?;
+        } else {
+            bytes += fs::copy(&from, &to)?;
+        }
+    }
+
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,4 +175,27 @@ mod tests {
         assert!(safe_join(&root, "../foo").is_err());
         assert!(safe_join(&root, "foo/../../bar").is_err());
     }
+
+    #[test]
+    fn test_copy_dir() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir()?;
+        let src = tmp.path().join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o700))?;
+        fs::File::create(src.join("nested/data"))?;
+        std::os::unix::fs::symlink("nested/data", src.join("link"))?;
+
+        let dst = tmp.path().join("dst");
+        copy_dir(&src, &dst)?;
+
+        assert!(dst.join("nested/data").is_file());
+        assert_eq!(fs::read_link(dst.join("link"))?, Path::new("nested/data"));
+        assert_eq!(fs::metadata(&dst)?.permissions().mode() & 0o777, 0o700);
+
+        // Never copies over an existing directory
+        assert!(copy_dir(&src, &dst).is_err());
+        Ok(())
+    }
 }
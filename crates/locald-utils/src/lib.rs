@@ -7,6 +7,8 @@ pub mod cert;
 pub mod cgroup;
 /// Port discovery utilities.
 pub mod discovery;
+/// Domain matching utilities.
+pub mod domain;
 /// Environment variable utilities.
 pub mod env;
 /// Filesystem utilities.
@@ -26,5 +28,7 @@ pub mod probe;
 pub mod process;
 /// Project management utilities.
 pub mod project;
+/// Redis management utilities.
+pub mod redis;
 /// Shim management utilities.
 pub mod shim;
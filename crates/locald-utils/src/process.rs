@@ -2,6 +2,7 @@ use anyhow::Result;
 use nix::sys::signal::{Signal, kill};
 use nix::unistd::Pid;
 use portable_pty::Child;
+use tokio::sync::broadcast;
 use tracing::{error, info, warn};
 
 /// Kills a process by PID.
@@ -13,6 +14,14 @@ pub fn kill_pid(pid: i32, signal: Signal) -> Result<()> {
     kill(Pid::from_raw(pid), signal).map_err(|e| anyhow::anyhow!("Failed to kill pid {pid}: {e}"))
 }
 
+/// Reports progress of a managed service (`service` is e.g. `postgres:db`): logs
+/// `message`, and sends it to the service's log channel as a stdout line.
+pub fn send_log(tx: &broadcast::Sender<(String, String)>, service: &str, message: String) {
+    info!("[{service}] {message}");
+    // Nobody may be listening yet; the message is in the daemon log either way
+    tx.send(("stdout".to_string(), message)).ok();
+}
+
 /// Terminates a child process gracefully.
 ///
 /// Sends the specified signal (usually SIGTERM or SIGINT), waits for the process to exit,
@@ -0,0 +1,314 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{Mutex, broadcast};
+use tracing::{info, warn};
+
+/// The Redis version used when a service doesn't ask for one.
+pub const DEFAULT_VERSION: &str = "7.4.2";
+
+/// The port Redis listens on by default.
+pub const DEFAULT_PORT: u16 = 6379;
+
+/// Manages a Redis service instance.
+///
+/// Redis doesn't publish prebuilt binaries, so the first start of a version downloads
+/// the release tarball and builds `redis-server` into a shared cache. Later starts (in
+/// any project) reuse the cached binary.
+#[derive(Debug)]
+pub struct RedisRunner {
+    name: String,
+    version: String,
+    port: u16,
+    data_dir: PathBuf,
+    process: Arc<Mutex<Option<tokio::process::Child>>>,
+    log_tx: broadcast::Sender<(String, String)>,
+}
+
+impl RedisRunner {
+    /// Create a new `RedisRunner`.
+    pub fn new(name: String, version: String, port: u16, data_dir: PathBuf) -> Self {
+        let (log_tx, _) = broadcast::channel(1000);
+        Self {
+            name,
+            version,
+            port,
+            data_dir,
+            process: Arc::new(Mutex::new(None)),
+            log_tx,
+        }
+    }
+
+    /// Subscribe to the log stream.
+    pub fn subscribe_logs(&self) -> broadcast::Receiver<(String, String)> {
+        self.log_tx.subscribe()
+    }
+
+    /// Start the Redis service.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The data directory cannot be created.
+    /// - Redis cannot be downloaded or built.
+    /// - The process fails to spawn.
+    pub async fn start(&self) -> Result<()> {
+        {
+            let process_guard = self.process.lock().await;
+            if process_guard.is_some() {
+                info!("Redis service {} is already running", self.name);
+                return Ok(());
+            }
+        }
+
+        info!(
+            "Starting Redis service {} (v{}) on port {} with data dir {:?}",
+            self.name, self.version, self.port, self.data_dir
+        );
+
+        if !self.data_dir.exists() {
+            tokio::fs::create_dir_all(&self.data_dir)
+                .await
+                .context("Failed to create data directory")?;
+        }
+
+        let binary_path = self.ensure_installed().await?;
+        info!("Found redis-server binary at {:?}", binary_path);
+
+        let mut cmd = Command::new(&binary_path);
+        cmd.arg("--port").arg(self.port.to_string());
+        cmd.arg("--bind").arg("127.0.0.1"); // Bind to localhost only
+        cmd.arg("--dir").arg(&self.data_dir);
+        cmd.arg("--appendonly").arg("yes");
+        cmd.arg("--daemonize").arg("no");
+
+        // Capture logs
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        // Ensure it dies when we die (best effort)
+        cmd.kill_on_drop(true);
+
+        let mut child = cmd.spawn().context("Failed to spawn redis-server")?;
+
+        let stdout = child.stdout.take().context("Failed to capture stdout")?;
+        let stderr = child.stderr.take().context("Failed to capture stderr")?;
+        self.forward_logs("stdout", stdout);
+        self.forward_logs("stderr", stderr);
+
+        {
+            let mut process_guard = self.process.lock().await;
+            *process_guard = Some(child);
+        }
+        info!("Redis service {} started successfully", self.name);
+
+        Ok(())
+    }
+
+    fn forward_logs<R>(&self, stream: &'static str, reader: R)
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        let name = self.name.clone();
+        let log_tx = self.log_tx.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                info!("[redis:{}] {}", name, line);
+                // Keep draining the pipe even without subscribers, or the child gets SIGPIPE
+                log_tx.send((stream.to_string(), line)).ok();
+            }
+        });
+    }
+
+    /// Returns the cached `redis-server` binary for this version, building it first if needed.
+    async fn ensure_installed(&self) -> Result<PathBuf> {
+        let dist_dir = directories::ProjectDirs::from("com", "locald", "locald").map_or_else(
+            || PathBuf::from(".locald/redis-dist"),
+            |d| d.data_dir().join("redis-dist"),
+        );
+        let install_dir = dist_dir.join(&self.version);
+        let binary_path = install_dir.join("src").join("redis-server");
+        if binary_path.exists() {
+            return Ok(binary_path);
+        }
+
+        tokio::fs::create_dir_all(&dist_dir)
+            .await
+            .context("Failed to create Redis cache directory")?;
+
+        // Build in a scratch directory so an interrupted build is never mistaken for a good one
+        let build_dir = dist_dir.join(format!(".build-{}-{}", self.version, uuid::Uuid::new_v4()));
+        let result = self.build(&build_dir).await;
+        if let Err(e) = result {
+            Self::remove_build_dir(&build_dir).await;
+            return Err(e);
+        }
+
+        if let Err(e) = tokio::fs::rename(&build_dir, &install_dir).await {
+            // Another project may have finished building the same version first
+            Self::remove_build_dir(&build_dir).await;
+            if !binary_path.exists() {
+                return Err(e).context("Failed to install Redis build");
+            }
+        }
+
+        Ok(binary_path)
+    }
+
+    async fn remove_build_dir(build_dir: &Path) {
+        if let Err(e) = tokio::fs::remove_dir_all(build_dir).await {
+            warn!(
+                "Failed to clean up Redis build directory {:?}: {}",
+                build_dir, e
+            );
+        }
+    }
+
+    async fn build(&self, build_dir: &Path) -> Result<()> {
+        let url = if self.version == "stable" {
+            "https://download.redis.io/redis-stable.tar.gz".to_string()
+        } else {
+            format!(
+                "https://download.redis.io/releases/redis-{}.tar.gz",
+                self.version
+            )
+        };
+
+        self.log(format!("Downloading Redis {} from {url}", self.version));
+        let archive = reqwest::get(&url)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Failed to download {url}"))?
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to download {url}"))?;
+
+        let target = build_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || unpack_source(&archive, &target))
+            .await?
+            .context("Failed to unpack Redis source")?;
+
+        self.log(format!("Building Redis {}", self.version));
+        let jobs = std::thread::available_parallelism().map_or(1, std::num::NonZero::get);
+        let mut cmd = Command::new("make");
+        cmd.current_dir(build_dir)
+            .arg(format!("-j{jobs}"))
+            .arg("BUILD_TLS=no")
+            .arg("MALLOC=libc")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd
+            .spawn()
+            .context("Failed to run make (is a C toolchain installed?)")?;
+        if let Some(stdout) = child.stdout.take() {
+            self.forward_logs("stdout", stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.forward_logs("stderr", stderr);
+        }
+
+        let status = child.wait().await?;
+        if !status.success() {
+            anyhow::bail!(
+                "Building Redis {} failed: make exited with {status}",
+                self.version
+            );
+        }
+
+        Ok(())
+    }
+
+    fn log(&self, message: String) {
+        crate::process::send_log(&self.log_tx, &format!("redis:{}", self.name), message);
+    }
+
+    /// Stop the Redis service.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process cannot be killed.
+    pub async fn stop(&self) -> Result<()> {
+        let child_opt = {
+            let mut process_guard = self.process.lock().await;
+            process_guard.take()
+        };
+
+        if let Some(mut child) = child_opt {
+            info!("Stopping Redis service {}", self.name);
+
+            if let Some(pid) = child.id() {
+                let pid = nix::unistd::Pid::from_raw(pid as i32);
+                if let Err(e) = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGTERM) {
+                    warn!("Failed to send SIGTERM to redis: {}", e);
+                }
+            }
+
+            match tokio::time::timeout(std::time::Duration::from_secs(5), child.wait()).await {
+                Ok(_) => info!("Redis service {} stopped", self.name),
+                Err(_) => {
+                    warn!("Redis service {} did not stop, killing", self.name);
+                    if let Err(e) = child.kill().await {
+                        warn!("Failed to kill redis: {}", e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop the service and delete its data directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data directory cannot be removed.
+    pub async fn reset(&self) -> Result<()> {
+        self.stop().await?;
+        if self.data_dir.exists() {
+            info!("Removing Redis data directory {:?}", self.data_dir);
+            tokio::fs::remove_dir_all(&self.data_dir)
+                .await
+                .context("Failed to remove Redis data directory")?;
+        }
+        Ok(())
+    }
+
+    /// Check if the service is running.
+    pub async fn is_running(&self) -> bool {
+        let process_guard = self.process.lock().await;
+        process_guard.is_some()
+    }
+
+    /// Get the port number.
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Get the connection URL.
+    pub fn url(&self) -> String {
+        format!("redis://localhost:{}", self.port)
+    }
+}
+
+/// Unpacks a `redis-x.y.z.tar.gz` source archive into `target`, dropping the top-level directory.
+fn unpack_source(archive: &[u8], target: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let relative: PathBuf = path.components().skip(1).collect();
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        let dest = crate::fs::safe_join(target, &relative.to_string_lossy())?;
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest)?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-import type { ServiceStatus } from './types';
+import type { CapturedRequest, RequestCapture, ServiceStatus } from './types';
 
 export async function getServices(): Promise<ServiceStatus[]> {
 	const res = await fetch('/api/state');
@@ -54,6 +54,40 @@ export async function getServiceInspect(name: string): Promise<unknown> {
 	return res.json();
 }
 
+export async function getCapturedRequests(name: string): Promise<RequestCapture> {
+	const res = await fetch(`/api/services/${name}/requests`);
+	if (!res.ok) {
+		throw new Error(`Failed to fetch requests for ${name}`);
+	}
+	return res.json();
+}
+
+export async function setRequestCapture(name: string, enabled: boolean): Promise<void> {
+	const res = await fetch(`/api/services/${name}/requests/capture`, {
+		method: 'PUT',
+		headers: { 'Content-Type': 'application/json' },
+		body: JSON.stringify({ enabled })
+	});
+	if (!res.ok) {
+		throw new Error(`Failed to update request capture for ${name}`);
+	}
+}
+
+export async function clearCapturedRequests(name: string): Promise<void> {
+	const res = await fetch(`/api/services/${name}/requests`, { method: 'DELETE' });
+	if (!res.ok) {
+		throw new Error(`Failed to clear requests for ${name}`);
+	}
+}
+
+export async function replayRequest(name: string, id: number): Promise<CapturedRequest> {
+	const res = await fetch(`/api/services/${name}/requests/${id}/replay`, { method: 'POST' });
+	if (!res.ok) {
+		throw new Error(await res.text());
+	}
+	return res.json();
+}
+
 export function connectEvents() {
 	const eventSource = new EventSource('/api/events');
 
@@ -11,10 +11,23 @@ export interface ServiceStatus {
 	workspace: string | null;
 	constellation: string | null;
 	warnings: string[];
+	crash_count: number;
+	last_exit_code: number | null;
+	crash_looping: boolean;
+	task_run: TaskRun | null;
+	/** Routes on the project's domain that lead to this service, e.g. "shop.localhost/api/*". */
+	routes: string[];
 	metrics?: ServiceMetrics;
 	cpu_history?: number[];
 }
 
+export interface TaskRun {
+	fingerprint: string;
+	exit_code: number | null;
+	finished_at: number;
+	duration_ms: number;
+}
+
 export interface ServiceMetrics {
 	name: string;
 	cpu_percent: number;
@@ -22,9 +35,55 @@ export interface ServiceMetrics {
 	timestamp: number;
 }
 
+export type LogLevel = 'trace' | 'debug' | 'info' | 'warn' | 'error' | 'fatal';
+
+/** A JSON or logfmt log line, parsed by the daemon. */
+export interface StructuredLog {
+	format: 'json' | 'logfmt';
+	level?: LogLevel;
+	message?: string;
+	timestamp?: string;
+	fields?: Record<string, unknown>;
+}
+
 export interface LogEntry {
 	timestamp: number;
 	service: string;
 	stream: string;
 	message: string;
+	structured?: StructuredLog;
+}
+
+/** A request or response body; `data` is base64 and may be cut off at the capture limit. */
+export interface CapturedBody {
+	data: string;
+	size: number;
+	truncated: boolean;
+}
+
+export interface CapturedResponse {
+	status: number;
+	headers: [string, string][];
+	body: CapturedBody;
+	duration_ms: number;
+}
+
+/** An HTTP request the proxy captured on its way to a service. */
+export interface CapturedRequest {
+	id: number;
+	service: string;
+	timestamp: number;
+	method: string;
+	host: string;
+	path: string;
+	headers: [string, string][];
+	body: CapturedBody;
+	response?: CapturedResponse;
+	error?: string;
+	replay_of?: number;
+}
+
+export interface RequestCapture {
+	enabled: boolean;
+	requests: CapturedRequest[];
 }
@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { cleanLog } from './logs';
+import { cleanLog, formatMessage } from './logs';
 
 describe('cleanLog', () => {
 	it('should return the message as is if no special characters', () => {
@@ -30,3 +30,27 @@ describe('cleanLog', () => {
 		expect(cleanLog('\x1b[2J\x1b[3JClean')).toBe('Clean');
 	});
 });
+
+describe('formatMessage', () => {
+	const entry = { timestamp: 0, service: 'app:api', stream: 'stdout' };
+
+	it('should return plain messages as is', () => {
+		expect(formatMessage({ ...entry, message: 'hello' })).toBe('hello');
+	});
+
+	it('should render structured lines as level, message and fields', () => {
+		const formatted = formatMessage({
+			...entry,
+			message: '{"level":"warn","msg":"slow query","ms":812,"sql":"select 1"}',
+			structured: {
+				format: 'json',
+				level: 'warn',
+				message: 'slow query',
+				fields: { ms: 812, sql: 'select 1' }
+			}
+		});
+		expect(cleanLog(formatted).replace(/\x1b\[[\d;]*m/g, '')).toBe(
+			'WARN  slow query ms=812 sql="select 1"'
+		);
+	});
+});
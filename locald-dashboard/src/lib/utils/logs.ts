@@ -1,3 +1,5 @@
+import type { LogEntry, LogLevel } from '$lib/types';
+
 export function cleanLog(message: string): string {
 	// Strip CSI sequences (cursor movement, clear line, etc)
 	// eslint-disable-next-line no-control-regex
@@ -6,3 +8,33 @@ export function cleanLog(message: string): string {
 	const parts = stripped.split('\r');
 	return parts[parts.length - 1];
 }
+
+const LEVEL_COLORS: Record<LogLevel, string> = {
+	trace: '\x1b[90m',
+	debug: '\x1b[90m',
+	info: '\x1b[34m',
+	warn: '\x1b[33m',
+	error: '\x1b[31m',
+	fatal: '\x1b[1;31m'
+};
+
+/**
+ * The text to show for a log entry. Structured (JSON or logfmt) lines are rendered
+ * as `LEVEL message key=value ...` with ANSI colors instead of the raw line.
+ */
+export function formatMessage(entry: LogEntry): string {
+	const log = entry.structured;
+	if (!log) return entry.message;
+
+	const parts: string[] = [];
+	if (log.level) {
+		parts.push(`${LEVEL_COLORS[log.level]}${log.level.toUpperCase().padEnd(5)}\x1b[0m`);
+	}
+	if (log.message) parts.push(log.message);
+	for (const [key, value] of Object.entries(log.fields ?? {})) {
+		let text = typeof value === 'string' ? value : JSON.stringify(value);
+		if (/\s/.test(text)) text = JSON.stringify(text);
+		parts.push(`\x1b[90m${key}=\x1b[0m${text}`);
+	}
+	return parts.join(' ');
+}